@@ -0,0 +1,39 @@
+//! Compares decoding many multiboxes onto the heap with decoding them into a bump arena.
+//!
+//! Run with `cargo bench --features bumpalo --bench arena`.
+use std::hint::black_box;
+use std::time::Instant;
+
+use bumpalo::Bump;
+use ssb_multiformats::multibox::Multibox;
+
+const BOXES: usize = 100_000;
+
+fn main() {
+    let encodings: Vec<Vec<u8>> = (0..BOXES)
+        .map(|i| {
+            let len = 200 + i % 400;
+            Multibox::new_private_box((0..len).map(|j| (i + j) as u8).collect()).to_legacy_vec()
+        })
+        .collect();
+
+    let start = Instant::now();
+    let boxes: Vec<Multibox> = encodings
+        .iter()
+        .map(|enc| Multibox::from_legacy(enc).unwrap().0)
+        .collect();
+    black_box(&boxes);
+    drop(boxes);
+    println!("global allocator: {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let bump = Bump::new();
+    let boxes: Vec<_> = encodings
+        .iter()
+        .map(|enc| Multibox::from_legacy_in(enc, &bump).unwrap().0)
+        .collect();
+    black_box(&boxes);
+    drop(boxes);
+    drop(bump);
+    println!("bump arena:       {:?}", start.elapsed());
+}
@@ -53,18 +53,14 @@ fn handle_nays_key(path: &Path) -> Result<(), io::Error> {
         let mut contents = Vec::new();
         file.read_to_end(&mut contents)?;
 
-        match Multikey::from_legacy(&contents) {
-            Ok(e) => {
-                if e.1.len() == 0 {
-                    println!("{:?}", path);
-                    println!("{:?}", &contents);
-                    println!("{:?}", std::str::from_utf8(&contents));
-                    println!("{:?}", e);
-                    panic!()
-                }
+        if let Ok(e) = Multikey::from_legacy(&contents) {
+            if e.1.is_empty() {
+                println!("{:?}", path);
+                println!("{:?}", &contents);
+                println!("{:?}", std::str::from_utf8(&contents));
+                println!("{:?}", e);
+                panic!()
             }
-
-            Err(_) => {}
         }
 
         i += 1;
@@ -115,18 +111,14 @@ fn handle_nays_hash(path: &Path) -> Result<(), io::Error> {
         let mut contents = Vec::new();
         file.read_to_end(&mut contents)?;
 
-        match Multihash::from_legacy(&contents) {
-            Ok(e) => {
-                if e.1.len() == 0 {
-                    println!("{:?}", path);
-                    println!("{:?}", &contents);
-                    println!("{:?}", std::str::from_utf8(&contents));
-                    println!("{:?}", e);
-                    panic!()
-                }
+        if let Ok(e) = Multihash::from_legacy(&contents) {
+            if e.1.is_empty() {
+                println!("{:?}", path);
+                println!("{:?}", &contents);
+                println!("{:?}", std::str::from_utf8(&contents));
+                println!("{:?}", e);
+                panic!()
             }
-
-            Err(_) => {}
         }
 
         i += 1;
@@ -176,5 +168,5 @@ pub(crate) fn split_at_byte(input: &[u8], byte: u8) -> Option<(&[u8], &[u8])> {
         }
     }
 
-    return None;
+    None
 }
@@ -0,0 +1,160 @@
+#ifndef SSB_MULTIFORMATS_H
+#define SSB_MULTIFORMATS_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ The operation succeeded.
+ */
+#define SSB_OK 0
+
+/*
+ A required pointer argument was null.
+ */
+#define SSB_ERR_NULL 1
+
+/*
+ The output buffer was too small to hold the result.
+ */
+#define SSB_ERR_BUFFER_TOO_SMALL 2
+
+/*
+ A multihash target other than `SSB_TARGET_MESSAGE` or `SSB_TARGET_BLOB` was given.
+ */
+#define SSB_ERR_INVALID_TARGET 3
+
+/*
+ The target of a multihash that refers to a message.
+ */
+#define SSB_TARGET_MESSAGE 0
+
+/*
+ The target of a multihash that refers to a blob.
+ */
+#define SSB_TARGET_BLOB 1
+
+/*
+ Parse a legacy multikey, writing the 32 bytes of the ed25519 public key to `out_key`, and the
+ number of bytes the encoding took up to `out_consumed` (which may be null).
+
+ # Safety
+
+ `input` must point to `input_len` readable bytes, `out_key` to 32 writable bytes, and
+ `out_consumed` must be null or point to a writable `size_t`.
+ */
+uint16_t ssb_multikey_from_legacy(const uint8_t *input,
+                                  size_t input_len,
+                                  uint8_t *out_key,
+                                  size_t *out_consumed);
+
+/*
+ Write the legacy encoding of the ed25519 public key at `key` into `out`.
+
+ # Safety
+
+ `key` must point to 32 readable bytes, `out` to `out_cap` writable bytes, and `out_len` to a
+ writable `size_t`.
+ */
+uint16_t ssb_multikey_to_legacy(const uint8_t *key, uint8_t *out, size_t out_cap, size_t *out_len);
+
+/*
+ Parse a legacy multihash, writing its target (`SSB_TARGET_MESSAGE` or `SSB_TARGET_BLOB`) to
+ `out_target`, the 32 bytes of the sha256 digest to `out_digest`, and the number of bytes the
+ encoding took up to `out_consumed` (which may be null).
+
+ # Safety
+
+ `input` must point to `input_len` readable bytes, `out_target` to a writable byte,
+ `out_digest` to 32 writable bytes, and `out_consumed` must be null or point to a writable
+ `size_t`.
+ */
+uint16_t ssb_multihash_from_legacy(const uint8_t *input,
+                                   size_t input_len,
+                                   uint8_t *out_target,
+                                   uint8_t *out_digest,
+                                   size_t *out_consumed);
+
+/*
+ Write the legacy encoding of the sha256 digest at `digest` with the given target into `out`.
+
+ # Safety
+
+ `digest` must point to 32 readable bytes, `out` to `out_cap` writable bytes, and `out_len` to
+ a writable `size_t`.
+ */
+uint16_t ssb_multihash_to_legacy(uint8_t target,
+                                 const uint8_t *digest,
+                                 uint8_t *out,
+                                 size_t out_cap,
+                                 size_t *out_len);
+
+/*
+ Parse a legacy ed25519 signature, writing the 64 signature bytes to `out_sig`, and the number
+ of bytes the encoding took up to `out_consumed` (which may be null).
+
+ # Safety
+
+ `input` must point to `input_len` readable bytes, `out_sig` to 64 writable bytes, and
+ `out_consumed` must be null or point to a writable `size_t`.
+ */
+uint16_t ssb_multisig_from_legacy(const uint8_t *input,
+                                  size_t input_len,
+                                  uint8_t *out_sig,
+                                  size_t *out_consumed);
+
+/*
+ Write the legacy encoding of the ed25519 signature at `sig` into `out`.
+
+ # Safety
+
+ `sig` must point to 64 readable bytes, `out` to `out_cap` writable bytes, and `out_len` to a
+ writable `size_t`.
+ */
+uint16_t ssb_multisig_to_legacy(const uint8_t *sig, uint8_t *out, size_t out_cap, size_t *out_len);
+
+/*
+ Parse a legacy multibox, writing its identifier (zero for private boxes) to `out_id`, the
+ decoded cyphertext to `out_cyphertext`, the length of the cyphertext to
+ `out_cyphertext_len`, and the number of bytes the encoding took up to `out_consumed` (which
+ may be null).
+
+ If the cyphertext is longer than `out_cyphertext_cap`, this returns
+ `SSB_ERR_BUFFER_TOO_SMALL` but still stores the cyphertext length.
+
+ # Safety
+
+ `input` must point to `input_len` readable bytes, `out_id` to a writable `uint64_t`,
+ `out_cyphertext` to `out_cyphertext_cap` writable bytes, `out_cyphertext_len` to a writable
+ `size_t`, and `out_consumed` must be null or point to a writable `size_t`.
+ */
+uint16_t ssb_multibox_from_legacy(const uint8_t *input,
+                                  size_t input_len,
+                                  uint64_t *out_id,
+                                  uint8_t *out_cyphertext,
+                                  size_t out_cyphertext_cap,
+                                  size_t *out_cyphertext_len,
+                                  size_t *out_consumed);
+
+/*
+ Write the legacy encoding of a multibox with the given identifier (zero for private boxes)
+ and cyphertext into `out`.
+
+ # Safety
+
+ `cyphertext` must point to `cyphertext_len` readable bytes, `out` to `out_cap` writable
+ bytes, and `out_len` to a writable `size_t`.
+ */
+uint16_t ssb_multibox_to_legacy(uint64_t id,
+                                const uint8_t *cyphertext,
+                                size_t cyphertext_len,
+                                uint8_t *out,
+                                size_t out_cap,
+                                size_t *out_len);
+
+#endif  /* SSB_MULTIFORMATS_H */
@@ -0,0 +1,95 @@
+//! [clap](https://docs.rs/clap) integration, so command line tools can declare arguments such
+//! as `--feed <Multikey>` and get validation and error messages for free:
+//!
+//! ```ignore
+//! #[arg(long, value_parser = clap::value_parser!(Multikey))]
+//! feed: Multikey,
+//! ```
+//!
+//! Refs must be exactly their legacy encoding, multiserver addresses their string form.
+use clap::builder::ValueParserFactory;
+
+use super::multibox::{self, Multibox};
+use super::multifeed::{self, Multifeed};
+use super::multihash::{self, Multihash};
+use super::multikey::{self, Multikey};
+use super::multiserver::{DecodeMultiserverError, MultiserverAddress};
+
+impl ValueParserFactory for Multikey {
+    type Parser = fn(&str) -> Result<Multikey, multikey::DecodeLegacyError>;
+
+    fn value_parser() -> Self::Parser {
+        Multikey::from_legacy_str
+    }
+}
+
+impl ValueParserFactory for Multihash {
+    type Parser = fn(&str) -> Result<Multihash, multihash::DecodeLegacyError>;
+
+    fn value_parser() -> Self::Parser {
+        Multihash::from_legacy_str
+    }
+}
+
+impl ValueParserFactory for Multibox {
+    type Parser = fn(&str) -> Result<Multibox, multibox::DecodeLegacyError>;
+
+    fn value_parser() -> Self::Parser {
+        Multibox::from_legacy_str
+    }
+}
+
+impl ValueParserFactory for Multifeed {
+    type Parser = fn(&str) -> Result<Multifeed, multifeed::DecodeLegacyError>;
+
+    fn value_parser() -> Self::Parser {
+        Multifeed::from_legacy_str
+    }
+}
+
+impl ValueParserFactory for MultiserverAddress {
+    type Parser = fn(&str) -> Result<MultiserverAddress, DecodeMultiserverError>;
+
+    fn value_parser() -> Self::Parser {
+        MultiserverAddress::parse
+    }
+}
+
+#[test]
+fn test_value_parsers() {
+    use clap::{Arg, Command};
+
+    let cmd = || {
+        Command::new("test")
+            .arg(
+                Arg::new("feed")
+                    .long("feed")
+                    .value_parser(clap::value_parser!(Multikey)),
+            )
+            .arg(
+                Arg::new("peer")
+                    .long("peer")
+                    .value_parser(clap::value_parser!(MultiserverAddress)),
+            )
+    };
+
+    let key = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let matches = cmd()
+        .try_get_matches_from(["test", "--feed", key, "--peer", "net:host:8008"])
+        .unwrap();
+    assert_eq!(
+        matches.get_one::<Multikey>("feed").unwrap(),
+        &Multikey::from_legacy_str(key).unwrap()
+    );
+    assert_eq!(
+        matches
+            .get_one::<MultiserverAddress>("peer")
+            .unwrap()
+            .to_string(),
+        "net:host:8008"
+    );
+
+    assert!(cmd()
+        .try_get_matches_from(["test", "--feed", "@abc.ed25519"])
+        .is_err());
+}
@@ -0,0 +1,113 @@
+//! A DNS-safe representation of raw bytes: lowercase
+//! [RFC 4648](https://tools.ietf.org/html/rfc4648#section-6) base32 without padding.
+//!
+//! The encoding of a 32 byte key is 52 characters long, so it fits into a single DNS label.
+use std::fmt;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+// Encode the input as lowercase base32, without padding.
+pub(crate) fn encode_base32(input: &[u8]) -> String {
+    let mut out = String::with_capacity((input.len() * 8).div_ceil(5));
+    let mut acc: u16 = 0;
+    let mut bits = 0;
+
+    for &b in input {
+        acc = (acc << 8) | b as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((acc >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((acc << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+// Decode the canonical encoding of exactly `out.len()` bytes.
+pub(crate) fn decode_base32(s: &str, out: &mut [u8]) -> Result<(), DecodeBase32Error> {
+    let s = s.as_bytes();
+    if s.len() != (out.len() * 8).div_ceil(5) {
+        return Err(DecodeBase32Error::WrongLength);
+    }
+
+    let mut acc: u16 = 0;
+    let mut bits = 0;
+    let mut written = 0;
+
+    for (i, &c) in s.iter().enumerate() {
+        let value = match c {
+            b'a'..=b'z' => c - b'a',
+            b'2'..=b'7' => c - b'2' + 26,
+            _ => return Err(DecodeBase32Error::InvalidCharacter(i)),
+        };
+        acc = (acc << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out[written] = (acc >> bits) as u8;
+            written += 1;
+        }
+    }
+
+    // Unused trailing bits must be zero, so that every value has exactly one encoding.
+    if acc & ((1 << bits) - 1) != 0 {
+        return Err(DecodeBase32Error::NoncanonicBits);
+    }
+    Ok(())
+}
+
+/// Everything that can go wrong when decoding raw bytes from DNS-safe base32.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeBase32Error {
+    /// The input did not encode the expected number of bytes.
+    WrongLength,
+    /// The input contained a character that is not lowercase base32, at the given offset.
+    InvalidCharacter(usize),
+    /// The unused bits of the last character were not zero.
+    NoncanonicBits,
+}
+
+impl fmt::Display for DecodeBase32Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeBase32Error::WrongLength => write!(f, "Wrong length"),
+            DecodeBase32Error::InvalidCharacter(i) => {
+                write!(f, "Invalid base32 character at offset {}", i)
+            }
+            DecodeBase32Error::NoncanonicBits => write!(f, "Noncanonic trailing bits"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeBase32Error {}
+
+#[test]
+fn test_base32() {
+    // Test vectors from RFC 4648, lowercased and without padding.
+    assert_eq!(encode_base32(b""), "");
+    assert_eq!(encode_base32(b"f"), "my");
+    assert_eq!(encode_base32(b"fo"), "mzxq");
+    assert_eq!(encode_base32(b"foobar"), "mzxw6ytboi");
+
+    let mut out = [0u8; 6];
+    decode_base32("mzxw6ytboi", &mut out).unwrap();
+    assert_eq!(&out, b"foobar");
+
+    let mut out = [0u8; 1];
+    assert_eq!(
+        decode_base32("mz", &mut out),
+        Err(DecodeBase32Error::NoncanonicBits)
+    );
+    assert_eq!(
+        decode_base32("My", &mut out),
+        Err(DecodeBase32Error::InvalidCharacter(0))
+    );
+    assert_eq!(
+        decode_base32("my=", &mut out),
+        Err(DecodeBase32Error::WrongLength)
+    );
+}
@@ -0,0 +1,56 @@
+//! Parse many legacy encodings in parallel, using [rayon](https://docs.rs/rayon).
+use rayon::prelude::*;
+
+use super::LegacyEncoding;
+
+/// Parse each of the inputs as the complete legacy encoding of a `T`, in parallel. The results
+/// are in the same order as the inputs.
+pub fn parse_batch<T, I>(inputs: &[I]) -> Vec<Result<T, T::Error>>
+where
+    T: LegacyEncoding + Send,
+    T::Error: Send,
+    I: AsRef<[u8]> + Sync,
+{
+    inputs
+        .par_iter()
+        .map(|input| T::from_legacy_exact(input.as_ref()))
+        .collect()
+}
+
+/// Check whether each of the inputs is the complete legacy encoding of a `T`, in parallel. The
+/// results are in the same order as the inputs.
+pub fn validate_batch<T, I>(inputs: &[I]) -> Vec<Result<(), T::Error>>
+where
+    T: LegacyEncoding,
+    T::Error: Send,
+    I: AsRef<[u8]> + Sync,
+{
+    inputs
+        .par_iter()
+        .map(|input| T::from_legacy_exact(input.as_ref()).map(|_| ()))
+        .collect()
+}
+
+#[cfg(feature = "multihash")]
+#[test]
+fn test_parse_batch() {
+    use super::multihash::{DecodeLegacyError, Multihash, Target};
+
+    let valid = Multihash::from_sha256([3; 32], Target::Blob).to_legacy_string();
+    let inputs = vec![valid.clone(), format!("{}\"", valid), "%".to_string()];
+
+    let parsed = parse_batch::<Multihash, _>(&inputs);
+    assert_eq!(parsed.len(), 3);
+    assert_eq!(parsed[0], Ok(Multihash::from_sha256([3; 32], Target::Blob)));
+    assert_eq!(parsed[1], Err(DecodeLegacyError::TrailingBytes));
+    assert_eq!(parsed[2], Err(DecodeLegacyError::NoDot));
+
+    assert_eq!(
+        validate_batch::<Multihash, _>(&inputs),
+        vec![
+            Ok(()),
+            Err(DecodeLegacyError::TrailingBytes),
+            Err(DecodeLegacyError::NoDot)
+        ]
+    );
+}
@@ -0,0 +1,460 @@
+//! `ssb-mf`, a small tool for inspecting and validating ssb multiformats.
+//!
+//! ```text
+//! ssb-mf inspect <VALUE>...           Print a breakdown of each legacy encoding
+//! ssb-mf validate                     Check every line of stdin, report the invalid ones
+//! ssb-mf encode <KIND> <HEX>          Turn raw bytes into a legacy encoding, where KIND is
+//!                                     one of feed, message or blob
+//! ssb-mf hash --blob <FILE>           Print the id of a blob
+//! ssb-mf hash --msg <FILE>            Print the id of a message, given as the signed message
+//!                                     (including its signature) serialized by
+//!                                     `JSON.stringify(msg, null, 2)`. The file is hashed
+//!                                     verbatim apart from trailing whitespace, so any other
+//!                                     formatting yields a wrong id rather than an error
+//! ssb-mf scan [OPTIONS] <FILE>        Print every ref in a log or dump, `-` reads stdin
+//!     --unique                        Print each ref only once
+//!     --json | --csv                  Print the line, offset and kind of each ref as well, as
+//!                                     JSON lines or CSV
+//! ```
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Read, Write};
+use std::process;
+
+use ssb_crypto::hash;
+
+use ssb_multiformats::multibox::Multibox;
+use ssb_multiformats::multihash::{Multihash, Target};
+use ssb_multiformats::multikey::Multikey;
+use ssb_multiformats::scan::scan_refs;
+
+const USAGE: &str = "Usage:
+    ssb-mf inspect <VALUE>...
+    ssb-mf validate
+    ssb-mf encode <feed|message|blob> <HEX>
+    ssb-mf hash --blob <FILE>
+    ssb-mf hash --msg <FILE>
+    ssb-mf scan [--unique] [--json|--csv] <FILE>";
+
+// The parts of a decoded value that are worth showing.
+#[derive(Debug, PartialEq, Eq)]
+struct Breakdown {
+    kind: &'static str,
+    algorithm: String,
+    bytes: Vec<u8>,
+}
+
+// Decode a legacy encoding of a feed id, message or blob hash, signature or box.
+fn inspect(s: &str) -> Result<Breakdown, String> {
+    let b = s.as_bytes();
+
+    if s.starts_with('@') {
+        let mk = Multikey::from_legacy_exact(b).map_err(|e| e.to_string())?;
+        Ok(Breakdown {
+            kind: "feed",
+            algorithm: "ed25519".to_string(),
+            bytes: mk.into_ed25519_public_key().unwrap()[..].to_vec(),
+        })
+    } else if s.starts_with('%') || s.starts_with('&') {
+        let mh = Multihash::from_legacy_exact(b).map_err(|e| e.to_string())?;
+        Ok(Breakdown {
+            kind: match mh.0 {
+                Target::Message => "message",
+                Target::Blob => "blob",
+            },
+            algorithm: "sha256".to_string(),
+            bytes: mh.as_sha256().unwrap().to_vec(),
+        })
+    } else if s.ends_with(".sig.ed25519") {
+        let sig = Multikey::from_ed25519(&[0; 32])
+            .sig_from_legacy_exact(b)
+            .map_err(|e| e.to_string())?;
+        Ok(Breakdown {
+            kind: "signature",
+            algorithm: "ed25519".to_string(),
+            bytes: sig.as_ed25519().unwrap().to_vec(),
+        })
+    } else {
+        let mb = Multibox::from_legacy_exact(b).map_err(|e| e.to_string())?;
+        Ok(Breakdown {
+            kind: "box",
+            algorithm: mb.suffix_str().into_owned(),
+            bytes: mb.cyphertext().to_vec(),
+        })
+    }
+}
+
+// Produce the legacy encoding of the given raw bytes.
+fn encode(kind: &str, bytes: &[u8]) -> Result<String, String> {
+    match kind {
+        "feed" => {
+            if bytes.len() != 32 {
+                return Err("An ed25519 public key must be 32 bytes long".to_string());
+            }
+            Ok(Multikey::from_ed25519_slice(bytes).to_legacy_string())
+        }
+        "message" | "blob" => {
+            if bytes.len() != 32 {
+                return Err("A sha256 digest must be 32 bytes long".to_string());
+            }
+            let mut digest = [0u8; 32];
+            digest.copy_from_slice(bytes);
+            let target = if kind == "blob" {
+                Target::Blob
+            } else {
+                Target::Message
+            };
+            Ok(Multihash::from_sha256(digest, target).to_legacy_string())
+        }
+        _ => Err(format!("Unknown kind {}", kind)),
+    }
+}
+
+// The id of a blob with the given contents.
+fn hash_blob(data: &[u8]) -> Multihash {
+    Multihash::blob_from_digest(hash::hash(data).0)
+}
+
+// The id of a message, given as the signed message, including its `signature` field, serialized
+// by `JSON.stringify(msg, null, 2)`. Legacy message ids are the hash of the UTF-16 code units of
+// that JSON, each truncated to its low byte. Trailing whitespace (such as the final newline of a
+// file) is never part of the JSON, so it is ignored. Everything else is hashed verbatim: the JSON
+// is neither parsed nor reformatted, so input in any other formatting yields a wrong id without
+// an error.
+fn hash_message(json: &str) -> Multihash {
+    let bytes: Vec<u8> = json
+        .trim_end()
+        .encode_utf16()
+        .map(|unit| unit as u8)
+        .collect();
+    Multihash::message_from_digest(hash::hash(&bytes).0)
+}
+
+// How `scan` prints the refs it finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanFormat {
+    Plain,
+    Json,
+    Csv,
+}
+
+// Parse the arguments of `scan` into the format, whether to deduplicate, and the file to scan.
+fn parse_scan_args(args: &[String]) -> Option<(ScanFormat, bool, &str)> {
+    let mut format = ScanFormat::Plain;
+    let mut unique = false;
+    let mut path = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "--unique" => unique = true,
+            "--json" if format == ScanFormat::Plain => format = ScanFormat::Json,
+            "--csv" if format == ScanFormat::Plain => format = ScanFormat::Csv,
+            _ if arg.starts_with("--") => return None,
+            _ if path.is_none() => path = Some(arg.as_str()),
+            _ => return None,
+        }
+    }
+    path.map(|path| (format, unique, path))
+}
+
+// The longest legacy encoding of a ref, that of a feed id.
+const MAX_REF_LEN: usize = 53;
+// How many bytes `scan` reads at a time.
+const SCAN_CHUNK_LEN: usize = 1 << 16;
+
+// Print every ref in the input, with the line (counting from one) it was found on and its byte
+// offset within that line. The input is read in chunks of bounded size, so neither large files
+// nor long lines (as in binary logs without newlines) have to be held in memory as a whole.
+fn scan<R: Read, W: Write>(
+    mut input: R,
+    out: &mut W,
+    format: ScanFormat,
+    unique: bool,
+) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    if format == ScanFormat::Csv {
+        writeln!(out, "line,offset,kind,ref")?;
+    }
+
+    let mut buf = Vec::with_capacity(SCAN_CHUNK_LEN + MAX_REF_LEN);
+    // The line and the offset within it of the first byte of `buf`.
+    let mut line = 1;
+    let mut column = 0;
+
+    loop {
+        let filled = buf.len();
+        buf.resize(filled + SCAN_CHUNK_LEN, 0);
+        let n = loop {
+            match input.read(&mut buf[filled..]) {
+                Ok(n) => break n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        };
+        buf.truncate(filled + n);
+        let done = n == 0;
+
+        // A ref that starts before `limit` lies in `buf` as a whole, a later one is only
+        // looked for once the next chunk has been read.
+        let limit = if done {
+            buf.len()
+        } else {
+            buf.len().saturating_sub(MAX_REF_LEN - 1)
+        };
+        let mut resume = limit;
+        let mut counted = 0;
+
+        let mut refs = scan_refs(&buf);
+        while let Some((offset, kind)) = refs.next() {
+            if offset >= limit {
+                break;
+            }
+            count_lines(&buf[counted..offset], &mut line, &mut column);
+            counted = offset;
+            resume = resume.max(refs.position());
+
+            // Legacy encodings are ascii.
+            let r = std::str::from_utf8(&buf[offset..refs.position()]).unwrap();
+            if unique && !seen.insert(r.to_string()) {
+                continue;
+            }
+
+            match format {
+                ScanFormat::Plain => writeln!(out, "{}", r)?,
+                ScanFormat::Json => writeln!(
+                    out,
+                    r#"{{"line":{},"offset":{},"kind":"{}","ref":"{}"}}"#,
+                    line, column, kind, r
+                )?,
+                ScanFormat::Csv => writeln!(out, "{},{},{},{}", line, column, kind, r)?,
+            }
+        }
+
+        count_lines(&buf[counted..resume], &mut line, &mut column);
+        buf.drain(..resume);
+        if done {
+            return Ok(());
+        }
+    }
+}
+
+// Advance the line and the offset within it past the given bytes.
+fn count_lines(bytes: &[u8], line: &mut usize, column: &mut usize) {
+    for &b in bytes {
+        if b == b'\n' {
+            *line += 1;
+            *column = 0;
+        } else {
+            *column += 1;
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(s, "{:02x}", b).unwrap();
+    }
+    s
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    // `from_str_radix` alone would accept a sign, as in `+f`.
+    if s.len() % 2 == 1 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("Invalid hex".to_string());
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| "Invalid hex".to_string()))
+        .collect()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let ok = match args.first().map(String::as_str) {
+        Some("inspect") if args.len() > 1 => {
+            let mut ok = true;
+            for value in &args[1..] {
+                match inspect(value) {
+                    Ok(b) => {
+                        println!("{}", value);
+                        println!("  type:      {}", b.kind);
+                        println!("  algorithm: {}", b.algorithm);
+                        println!("  bytes:     {}", to_hex(&b.bytes));
+                    }
+                    Err(err) => {
+                        eprintln!("{}: {}", value, err);
+                        ok = false;
+                    }
+                }
+            }
+            ok
+        }
+        Some("validate") if args.len() == 1 => {
+            let mut ok = true;
+            for (i, line) in io::stdin().lock().lines().enumerate() {
+                let line = line.unwrap_or_else(|err| {
+                    eprintln!("Could not read line {}: {}", i + 1, err);
+                    process::exit(2);
+                });
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                if let Err(err) = inspect(line) {
+                    println!("{}: {}: {}", i + 1, line, err);
+                    ok = false;
+                }
+            }
+            ok
+        }
+        Some("encode") if args.len() == 3 => {
+            match from_hex(&args[2]).and_then(|b| encode(&args[1], &b)) {
+                Ok(enc) => {
+                    println!("{}", enc);
+                    true
+                }
+                Err(err) => {
+                    eprintln!("{}", err);
+                    false
+                }
+            }
+        }
+        Some("hash") if args.len() == 3 && (args[1] == "--blob" || args[1] == "--msg") => {
+            match fs::read(&args[2]) {
+                Ok(data) if args[1] == "--blob" => {
+                    println!("{}", hash_blob(&data));
+                    true
+                }
+                Ok(data) => match String::from_utf8(data) {
+                    Ok(json) => {
+                        println!("{}", hash_message(&json));
+                        true
+                    }
+                    Err(_) => {
+                        eprintln!("{}: Not valid UTF-8", args[2]);
+                        false
+                    }
+                },
+                Err(err) => {
+                    eprintln!("{}: {}", args[2], err);
+                    false
+                }
+            }
+        }
+        Some("scan") => {
+            let (format, unique, path) = parse_scan_args(&args[1..]).unwrap_or_else(|| {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            });
+            let stdout = io::stdout();
+            let mut out = io::BufWriter::new(stdout.lock());
+            let scanned = if path == "-" {
+                scan(io::stdin().lock(), &mut out, format, unique)
+            } else {
+                File::open(path).and_then(|file| scan(file, &mut out, format, unique))
+            };
+            match scanned.and_then(|_| out.flush()) {
+                Ok(()) => true,
+                Err(err) => {
+                    eprintln!("{}: {}", path, err);
+                    false
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if !ok {
+        process::exit(1);
+    }
+}
+
+#[test]
+fn test_inspect() {
+    let b = inspect("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256").unwrap();
+    assert_eq!(b.kind, "message");
+    assert_eq!(
+        to_hex(&b.bytes),
+        "3308dd2d5f793fb56a1dfae04b8f6749c5ecc88c097cbdb6f5ee4e48a73ed2b7"
+    );
+    assert_eq!(
+        encode("message", &from_hex(&to_hex(&b.bytes)).unwrap()).unwrap(),
+        "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256"
+    );
+
+    assert_eq!(inspect("lA==.box").unwrap().bytes, vec![0x94]);
+    assert_eq!(inspect("lA==.box").unwrap().algorithm, "box");
+    assert_eq!(inspect("lA==.boxV").unwrap().algorithm, "boxV");
+    assert_eq!(from_hex("0f"), Ok(vec![0x0f]));
+    assert!(from_hex("+f").is_err());
+    assert!(inspect("@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed2551").is_err());
+}
+
+#[test]
+fn test_hash() {
+    assert_eq!(
+        hash_blob(b"").to_legacy_string(),
+        "&47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=.sha256"
+    );
+    assert_eq!(
+        hash_message("{\n  \"a\": \"\u{e4}\u{1f600}\"\n}\n"),
+        Multihash::message_from_digest(hash::hash(b"{\n  \"a\": \"\xe4\x3d\x00\"\n}").0)
+    );
+}
+
+#[test]
+fn test_scan() {
+    let input = b"{\"author\":\"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\"}
+see &MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256 and @zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519
+";
+
+    let mut out = Vec::new();
+    scan(&input[..], &mut out, ScanFormat::Csv, true).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "line,offset,kind,ref
+1,11,feed,@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519
+2,4,blob,&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256
+"
+    );
+
+    let mut out = Vec::new();
+    scan(&input[..], &mut out, ScanFormat::Json, false).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(out.lines().count(), 3);
+    assert_eq!(
+        out.lines().nth(2).unwrap(),
+        r#"{"line":2,"offset":61,"kind":"feed","ref":"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"}"#
+    );
+
+    let args: Vec<String> = vec!["--unique".into(), "log.json".into(), "--csv".into()];
+    assert_eq!(
+        parse_scan_args(&args),
+        Some((ScanFormat::Csv, true, "log.json"))
+    );
+    let args: Vec<String> = vec!["--json".into(), "--csv".into(), "log.json".into()];
+    assert_eq!(parse_scan_args(&args), None);
+    assert_eq!(parse_scan_args(&[]), None);
+
+    // Refs are found across chunk boundaries, and in input without any newlines.
+    let mut input = vec![0u8; SCAN_CHUNK_LEN - 20];
+    input.extend_from_slice(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256");
+    input.resize(3 * SCAN_CHUNK_LEN, b'@');
+    let mut out = Vec::new();
+    scan(&input[..], &mut out, ScanFormat::Csv, false).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!(
+            "line,offset,kind,ref\n1,{},message,%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256\n",
+            SCAN_CHUNK_LEN - 20
+        )
+    );
+}
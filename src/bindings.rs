@@ -0,0 +1,270 @@
+//! The logic behind the bindings to other languages, so that all of them apply the same rules.
+//! The modules of the individual bindings only convert arguments, results and errors to the
+//! conventions of their language.
+use std::fmt;
+
+#[cfg(any(feature = "pyo3", feature = "uniffi"))]
+use super::canonicalize;
+use super::multibox::Multibox;
+use super::multifeed::Multifeed;
+use super::multihash::{self, Multihash, Target};
+use super::multikey::{self, Multikey, Multisig};
+#[cfg(feature = "wasm-bindgen")]
+use super::uri::{self, SsbUri};
+
+// Everything that can go wrong in the functions of this module.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum BindingError {
+    // The string was not a valid legacy encoding, with the message of the decoding error.
+    Invalid(String),
+    // The raw bytes of the named kind of value did not have the length it requires.
+    WrongLength {
+        what: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindingError::Invalid(ref reason) => write!(f, "{}", reason),
+            BindingError::WrongLength {
+                what,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} must be {} bytes long, got {}",
+                what, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+impl From<multikey::DecodeLegacyError> for BindingError {
+    fn from(err: multikey::DecodeLegacyError) -> BindingError {
+        BindingError::Invalid(err.to_string())
+    }
+}
+
+impl From<multihash::DecodeLegacyError> for BindingError {
+    fn from(err: multihash::DecodeLegacyError) -> BindingError {
+        BindingError::Invalid(err.to_string())
+    }
+}
+
+impl From<multikey::DecodeSignatureError> for BindingError {
+    fn from(err: multikey::DecodeSignatureError) -> BindingError {
+        BindingError::Invalid(err.to_string())
+    }
+}
+
+#[cfg(feature = "wasm-bindgen")]
+impl From<uri::DecodeUriError> for BindingError {
+    fn from(err: uri::DecodeUriError) -> BindingError {
+        BindingError::Invalid(err.to_string())
+    }
+}
+
+// Copy the input into an array of exactly `N` bytes, naming `what` in the error otherwise.
+fn exactly<const N: usize>(bytes: &[u8], what: &'static str) -> Result<[u8; N], BindingError> {
+    if bytes.len() != N {
+        return Err(BindingError::WrongLength {
+            what,
+            expected: N,
+            actual: bytes.len(),
+        });
+    }
+    let mut out = [0u8; N];
+    out.copy_from_slice(bytes);
+    Ok(out)
+}
+
+pub(crate) fn is_multikey(s: &str) -> bool {
+    Multikey::is_valid_legacy(s.as_bytes())
+}
+
+pub(crate) fn is_multifeed(s: &str) -> bool {
+    Multifeed::is_valid_legacy(s.as_bytes())
+}
+
+pub(crate) fn is_message_hash(s: &str) -> bool {
+    classify(s) == Some("message")
+}
+
+pub(crate) fn is_blob_hash(s: &str) -> bool {
+    classify(s) == Some("blob")
+}
+
+pub(crate) fn is_multibox(s: &str) -> bool {
+    Multibox::is_valid_legacy(s.as_bytes())
+}
+
+pub(crate) fn is_signature(s: &str) -> bool {
+    Multikey::from_ed25519(&[0; 32]).is_valid_legacy_sig(s.as_bytes())
+}
+
+// What kind of value the string is the legacy encoding of: `"feed"`, `"message"`, `"blob"` or
+// `"box"`, or `None` if it is none of these.
+pub(crate) fn classify(s: &str) -> Option<&'static str> {
+    let s = s.as_bytes();
+
+    if Multifeed::is_valid_legacy(s) {
+        Some("feed")
+    } else if let Ok(mh) = Multihash::from_legacy_exact(s) {
+        match mh.0 {
+            Target::Message => Some("message"),
+            Target::Blob => Some("blob"),
+        }
+    } else if Multibox::is_valid_legacy(s) {
+        Some("box")
+    } else {
+        None
+    }
+}
+
+// The canonical legacy encoding of a feed id, message or blob hash, or box that may deviate from
+// it in padding, base64 alphabet or suffix case, or `None` if the string is none of these.
+#[cfg(any(feature = "pyo3", feature = "uniffi"))]
+pub(crate) fn canonical_ref(s: &str) -> Option<String> {
+    let s = s.as_bytes();
+
+    let canonical = match canonicalize::<Multikey>(s) {
+        Ok((enc, [])) => enc,
+        _ => match canonicalize::<Multihash>(s) {
+            Ok((enc, [])) => enc,
+            _ => match canonicalize::<Multibox>(s) {
+                Ok((enc, [])) => enc,
+                _ => return None,
+            },
+        },
+    };
+
+    String::from_utf8(canonical).ok()
+}
+
+pub(crate) fn decode_multikey(s: &str) -> Result<Vec<u8>, BindingError> {
+    let mk = Multikey::from_legacy_exact(s.as_bytes())?;
+    Ok(mk.as_ed25519().unwrap().to_vec())
+}
+
+pub(crate) fn encode_multikey(pk: &[u8]) -> Result<String, BindingError> {
+    let pk = exactly(pk, "An ed25519 public key")?;
+    Ok(Multikey::from_ed25519(&pk).to_legacy_string())
+}
+
+pub(crate) fn decode_multihash(s: &str) -> Result<Vec<u8>, BindingError> {
+    let mh = Multihash::from_legacy_exact(s.as_bytes())?;
+    Ok(mh.as_sha256().unwrap().to_vec())
+}
+
+pub(crate) fn encode_multihash(digest: &[u8], blob: bool) -> Result<String, BindingError> {
+    let digest = exactly(digest, "A sha256 digest")?;
+    let target = if blob { Target::Blob } else { Target::Message };
+    Ok(Multihash::from_sha256(digest, target).to_legacy_string())
+}
+
+pub(crate) fn decode_signature(s: &str) -> Result<Vec<u8>, BindingError> {
+    let sig: Multisig = Multikey::from_ed25519(&[0; 32]).sig_from_legacy_exact(s.as_bytes())?;
+    Ok(sig.as_ed25519().unwrap().to_vec())
+}
+
+// The `ssb:` URI of the legacy encoding of a feed id or a message or blob hash.
+#[cfg(feature = "wasm-bindgen")]
+pub(crate) fn to_uri(s: &str) -> Result<String, BindingError> {
+    let uri = if s.starts_with('@') {
+        SsbUri::Feed(Multikey::from_legacy_exact(s.as_bytes())?)
+    } else {
+        let mh = Multihash::from_legacy_exact(s.as_bytes())?;
+        match mh.0 {
+            Target::Message => SsbUri::Message(mh),
+            Target::Blob => SsbUri::Blob(mh),
+        }
+    };
+    Ok(uri.to_uri())
+}
+
+// The legacy encoding of the feed id or message or blob hash of an `ssb:` URI.
+#[cfg(feature = "wasm-bindgen")]
+pub(crate) fn from_uri(s: &str) -> Result<String, BindingError> {
+    match SsbUri::from_uri(s)? {
+        SsbUri::Feed(mk) => Ok(mk.to_legacy_string()),
+        SsbUri::Message(mh) | SsbUri::Blob(mh) => Ok(mh.to_legacy_string()),
+        _ => Err(BindingError::Invalid(
+            "Not the URI of a feed, message or blob".to_string(),
+        )),
+    }
+}
+
+#[test]
+fn test_classify() {
+    assert_eq!(
+        classify("@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"),
+        Some("feed")
+    );
+    assert_eq!(
+        classify("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256"),
+        Some("message")
+    );
+    assert_eq!(
+        classify("&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256"),
+        Some("blob")
+    );
+    assert_eq!(classify("lA==.box"), Some("box"));
+    assert_eq!(classify("lA==.box\""), None);
+    assert_eq!(
+        classify("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha25"),
+        None
+    );
+}
+
+#[test]
+fn test_round_trips() {
+    let feed = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    assert!(is_multikey(feed) && is_multifeed(feed));
+    assert_eq!(
+        encode_multikey(&decode_multikey(feed).unwrap()).unwrap(),
+        feed
+    );
+    assert_eq!(
+        encode_multikey(&[0; 31]).unwrap_err().to_string(),
+        "An ed25519 public key must be 32 bytes long, got 31"
+    );
+    assert_matches!(
+        decode_multikey("@abc.ed25519"),
+        Err(BindingError::Invalid(_))
+    );
+
+    let blob = encode_multihash(&[3; 32], true).unwrap();
+    assert!(is_blob_hash(&blob) && !is_message_hash(&blob));
+    assert_eq!(decode_multihash(&blob).unwrap(), vec![3; 32]);
+
+    assert!(is_multibox("lA==.box"));
+    assert!(!is_signature(feed));
+    assert!(decode_signature(feed).is_err());
+}
+
+#[cfg(feature = "wasm-bindgen")]
+#[test]
+fn test_uri() {
+    for legacy in &[
+        "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519",
+        "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256",
+        "&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256",
+    ] {
+        assert_eq!(from_uri(&to_uri(legacy).unwrap()).unwrap(), *legacy);
+    }
+    assert_eq!(
+        to_uri("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256").unwrap(),
+        "ssb:message/classic/MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0rc"
+    );
+    assert_matches!(to_uri("lA==.box"), Err(BindingError::Invalid(_)));
+    assert_matches!(
+        from_uri("ssb:experimental?action=claim-http-invite"),
+        Err(BindingError::Invalid(_))
+    );
+    assert_matches!(from_uri("@abc.ed25519"), Err(BindingError::Invalid(_)));
+}
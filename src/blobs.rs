@@ -0,0 +1,267 @@
+//! The wants and haves maps of the [ssb-blobs](https://github.com/ssbc/ssb-blobs) protocol,
+//! such as `{"&Abc...=.sha256": -1}`, which map blob ids to a number: the negative hop count of
+//! a want, or the size of a blob someone has.
+//!
+//! Every key has to be a valid blob id. Rather than dropping the entries that are not, parsing
+//! fails and reports the position of the first malformed one.
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{
+    de::{Deserialize, Deserializer, Error, MapAccess, Visitor},
+    ser::{Serialize, SerializeMap, Serializer},
+};
+
+use super::multihash::{self, Multihash};
+
+/// A validated wants or haves map, keyed by blob hashes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlobMap(HashMap<Multihash, i64>);
+
+impl BlobMap {
+    /// Create an empty `BlobMap`.
+    pub fn new() -> BlobMap {
+        BlobMap::default()
+    }
+
+    /// Build a map from its entries, in order. Every key must be exactly the legacy encoding of
+    /// a blob hash, and no key may appear twice.
+    pub fn from_entries<K, I>(entries: I) -> Result<BlobMap, DecodeBlobMapError>
+    where
+        K: AsRef<str>,
+        I: IntoIterator<Item = (K, i64)>,
+    {
+        let mut map = HashMap::new();
+        for (position, (key, value)) in entries.into_iter().enumerate() {
+            let key = key.as_ref();
+            let mh = Multihash::from_legacy_str(key).map_err(|error| {
+                DecodeBlobMapError::InvalidKey {
+                    position,
+                    key: key.to_string(),
+                    error,
+                }
+            })?;
+
+            if !mh.is_blob() {
+                return Err(DecodeBlobMapError::NotABlob {
+                    position,
+                    key: key.to_string(),
+                });
+            }
+            if map.insert(mh, value).is_some() {
+                return Err(DecodeBlobMapError::DuplicateKey {
+                    position,
+                    key: key.to_string(),
+                });
+            }
+        }
+        Ok(BlobMap(map))
+    }
+
+    /// The entries of the map.
+    pub fn as_map(&self) -> &HashMap<Multihash, i64> {
+        &self.0
+    }
+
+    /// Unwrap the entries of the map.
+    pub fn into_map(self) -> HashMap<Multihash, i64> {
+        self.0
+    }
+}
+
+impl From<BlobMap> for HashMap<Multihash, i64> {
+    fn from(map: BlobMap) -> HashMap<Multihash, i64> {
+        map.0
+    }
+}
+
+/// Serializes as a map from the legacy encodings of the blob ids to the numbers.
+impl Serialize for BlobMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (mh, value) in &self.0 {
+            map.serialize_entry(&mh.to_legacy_string(), value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for BlobMap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(BlobMapVisitor)
+    }
+}
+
+// The most entries to allocate room for before they have actually been received.
+const MAX_PREALLOCATED_ENTRIES: usize = 4096;
+
+struct BlobMapVisitor;
+
+impl<'de> Visitor<'de> for BlobMapVisitor {
+    type Value = BlobMap;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map from blob ids to integers")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<BlobMap, A::Error> {
+        // The size hint comes from the peer, so like serde's own impls, trust it only up to a
+        // bound rather than preallocating whatever it claims.
+        let capacity = access
+            .size_hint()
+            .unwrap_or(0)
+            .min(MAX_PREALLOCATED_ENTRIES);
+        let mut entries: Vec<(String, i64)> = Vec::with_capacity(capacity);
+        while let Some(entry) = access.next_entry()? {
+            entries.push(entry);
+        }
+        BlobMap::from_entries(entries).map_err(A::Error::custom)
+    }
+}
+
+/// Everything that can go wrong when building a `BlobMap`. The position is the index of the
+/// offending entry, counting from zero in the order in which the entries were given.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeBlobMapError {
+    /// The key was not exactly a valid legacy encoding of a multihash.
+    InvalidKey {
+        /// The index of the entry.
+        position: usize,
+        /// The key of the entry.
+        key: String,
+        /// Why the key is invalid.
+        error: multihash::DecodeLegacyError,
+    },
+    /// The key was a message hash rather than a blob hash.
+    NotABlob {
+        /// The index of the entry.
+        position: usize,
+        /// The key of the entry.
+        key: String,
+    },
+    /// The key was the same blob id as that of an earlier entry.
+    DuplicateKey {
+        /// The index of the entry.
+        position: usize,
+        /// The key of the entry.
+        key: String,
+    },
+}
+
+impl fmt::Display for DecodeBlobMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeBlobMapError::InvalidKey {
+                position,
+                ref key,
+                ref error,
+            } => write!(
+                f,
+                "Invalid blob id {:?} at entry {}: {}",
+                key, position, error
+            ),
+            DecodeBlobMapError::NotABlob { position, ref key } => {
+                write!(f, "Not a blob id {:?} at entry {}", key, position)
+            }
+            DecodeBlobMapError::DuplicateKey { position, ref key } => {
+                write!(f, "Duplicate blob id {:?} at entry {}", key, position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeBlobMapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeBlobMapError::InvalidKey { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_blob_map() {
+    use serde::de::value::{Error, MapDeserializer};
+
+    let blob = "&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256";
+    let other = "&47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=.sha256";
+
+    let map = BlobMap::from_entries(vec![(blob, -1), (other, 12)]).unwrap();
+    assert_eq!(map.as_map().len(), 2);
+    assert_eq!(map.as_map()[&Multihash::from_legacy_str(blob).unwrap()], -1);
+
+    let de: MapDeserializer<_, Error> = MapDeserializer::new(vec![(blob, -2i64)].into_iter());
+    let map = BlobMap::deserialize(de).unwrap();
+    assert_eq!(
+        map.into_map(),
+        vec![(Multihash::from_legacy_str(blob).unwrap(), -2)]
+            .into_iter()
+            .collect::<HashMap<_, _>>()
+    );
+
+    let de: MapDeserializer<_, Error> =
+        MapDeserializer::new(vec![(blob, -1i64), ("&Mwjd.sha256", -1), (other, 3)].into_iter());
+    assert_eq!(
+        BlobMap::deserialize(de).unwrap_err().to_string(),
+        "Invalid blob id \"&Mwjd.sha256\" at entry 1: Data of wrong length: expected 32 bytes, \
+         got 3"
+    );
+
+    assert_eq!(
+        BlobMap::from_entries(vec![(other, 1), (&blob.replacen('&', "%", 1), 1)]),
+        Err(DecodeBlobMapError::NotABlob {
+            position: 1,
+            key: blob.replacen('&', "%", 1),
+        })
+    );
+    assert_eq!(
+        BlobMap::from_entries(vec![(blob, 1), (other, 1), (blob, 2)]),
+        Err(DecodeBlobMapError::DuplicateKey {
+            position: 2,
+            key: blob.to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_blob_map_size_hint() {
+    use serde::de::{
+        value::{Error, MapAccessDeserializer},
+        DeserializeSeed,
+    };
+
+    // Claims far more entries than it has, as a malicious peer might.
+    struct Lying;
+
+    impl<'de> MapAccess<'de> for Lying {
+        type Error = Error;
+
+        fn next_key_seed<K: DeserializeSeed<'de>>(
+            &mut self,
+            _seed: K,
+        ) -> Result<Option<K::Value>, Error> {
+            Ok(None)
+        }
+
+        fn next_value_seed<V: DeserializeSeed<'de>>(
+            &mut self,
+            _seed: V,
+        ) -> Result<V::Value, Error> {
+            unreachable!()
+        }
+
+        fn size_hint(&self) -> Option<usize> {
+            Some(usize::MAX)
+        }
+    }
+
+    let map = BlobMap::deserialize(MapAccessDeserializer::new(Lying)).unwrap();
+    assert!(map.as_map().is_empty());
+}
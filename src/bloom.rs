@@ -0,0 +1,265 @@
+//! Bloom filters over feed ids and message and blob hashes, so that peers can cheaply exchange
+//! approximate sets of the refs they have.
+//!
+//! The hashing scheme is part of the serialization, so it never changes: the 64 bit
+//! [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/) hash `h` of the sigil of a ref (`@`, `%`
+//! or `&`) followed by its raw bytes is computed, and `s` is the splitmix64 finalizer applied to
+//! `h`, with the lowest bit set. The `i`-th of the `k` bits of the ref is then
+//! `(h + i * s) mod m`, where `m` is the number of bits of the filter, computed with wrapping
+//! 64 bit arithmetic.
+//!
+//! A filter serializes as `k` as a big-endian `u32`, then `m` as a big-endian `u32`, then the
+//! `ceil(m / 8)` bytes of the bit array, where bit `j` is bit `j mod 8` (counting from the least
+//! significant one) of byte `j / 8`. Unused bits of the last byte are zero.
+use std::fmt;
+
+use super::fnv1a;
+#[cfg(feature = "multihash")]
+use super::multihash::Multihash;
+#[cfg(feature = "multikey")]
+use super::multikey::Multikey;
+
+/// A ref that can be added to a `BloomFilter`.
+pub trait BloomKey {
+    /// The sigil of the legacy encoding of the ref.
+    fn bloom_sigil(&self) -> u8;
+
+    /// The raw bytes of the ref.
+    fn bloom_bytes(&self) -> &[u8];
+}
+
+#[cfg(feature = "multikey")]
+impl BloomKey for Multikey {
+    fn bloom_sigil(&self) -> u8 {
+        b'@'
+    }
+
+    fn bloom_bytes(&self) -> &[u8] {
+        self.as_ed25519().unwrap()
+    }
+}
+
+#[cfg(feature = "multihash")]
+impl BloomKey for Multihash {
+    fn bloom_sigil(&self) -> u8 {
+        self.sigil() as u8
+    }
+
+    fn bloom_bytes(&self) -> &[u8] {
+        self.as_sha256().unwrap()
+    }
+}
+
+/// The largest number of bits a `BloomFilter` may set per ref. More hashes hardly ever pay off,
+/// and a bound keeps filters received from peers from making every lookup arbitrarily slow.
+pub const MAX_HASHES: u32 = 32;
+
+/// A Bloom filter of refs: a set that may report false positives, but never false negatives.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    num_bits: u32,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Create an empty filter of `num_bits` bits that sets `num_hashes` bits per ref. Both are
+    /// raised to at least one.
+    ///
+    /// Panics if `num_hashes` is greater than [`MAX_HASHES`](constant.MAX_HASHES.html).
+    pub fn new(num_bits: u32, num_hashes: u32) -> BloomFilter {
+        assert!(num_hashes <= MAX_HASHES, "Too many hashes per ref");
+        let num_bits = num_bits.max(1);
+        BloomFilter {
+            bits: vec![0; num_bits.div_ceil(8) as usize],
+            num_bits,
+            num_hashes: num_hashes.max(1),
+        }
+    }
+
+    /// Create an empty filter sized for `expected` refs at the given false positive rate (e.g.
+    /// `0.01` for one percent), using the optimal number of bits per ref, but no more than
+    /// [`MAX_HASHES`](constant.MAX_HASHES.html) hashes.
+    ///
+    /// Panics if the rate is not strictly between zero and one.
+    pub fn with_rate(expected: usize, false_positive_rate: f64) -> BloomFilter {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "False positive rate not between zero and one"
+        );
+        let ln2 = std::f64::consts::LN_2;
+        let n = expected.max(1) as f64;
+        let m = (-n * false_positive_rate.ln() / (ln2 * ln2)).ceil();
+        let m = m.clamp(1.0, u32::MAX as f64);
+        let k = (m / n * ln2).round().clamp(1.0, MAX_HASHES as f64);
+        BloomFilter::new(m as u32, k as u32)
+    }
+
+    /// The number of bits of the filter.
+    pub fn num_bits(&self) -> u32 {
+        self.num_bits
+    }
+
+    /// The number of bits set per ref.
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    /// Add a ref to the filter.
+    pub fn insert<K: BloomKey + ?Sized>(&mut self, key: &K) {
+        for bit in self.bit_indices(key) {
+            self.bits[bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    /// Whether the ref may have been added to the filter. `false` means it certainly was not.
+    pub fn contains<K: BloomKey + ?Sized>(&self, key: &K) -> bool {
+        self.bit_indices(key)
+            .all(|bit| self.bits[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    // The indices of the bits of the given ref.
+    fn bit_indices<K: BloomKey + ?Sized>(&self, key: &K) -> impl Iterator<Item = usize> {
+        let h = fnv1a(&[&[key.bloom_sigil()], key.bloom_bytes()]);
+        let s = splitmix64(h) | 1;
+        let m = self.num_bits as u64;
+        (0..self.num_hashes as u64).map(move |i| (h.wrapping_add(i.wrapping_mul(s)) % m) as usize)
+    }
+
+    /// Serialize the filter into its binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(8 + self.bits.len());
+        out.extend_from_slice(&self.num_hashes.to_be_bytes());
+        out.extend_from_slice(&self.num_bits.to_be_bytes());
+        out.extend_from_slice(&self.bits);
+        out
+    }
+
+    /// Deserialize a filter from its binary form.
+    pub fn from_bytes(s: &[u8]) -> Result<BloomFilter, DecodeBloomError> {
+        if s.len() < 8 {
+            return Err(DecodeBloomError::UnexpectedEnd);
+        }
+
+        let mut word = [0u8; 4];
+        word.copy_from_slice(&s[..4]);
+        let num_hashes = u32::from_be_bytes(word);
+        word.copy_from_slice(&s[4..8]);
+        let num_bits = u32::from_be_bytes(word);
+        if num_hashes == 0 || num_hashes > MAX_HASHES || num_bits == 0 {
+            return Err(DecodeBloomError::InvalidParameters);
+        }
+
+        let data = &s[8..];
+        let len = num_bits.div_ceil(8) as usize;
+        if data.len() < len {
+            return Err(DecodeBloomError::UnexpectedEnd);
+        }
+        if data.len() > len {
+            return Err(DecodeBloomError::TrailingBytes);
+        }
+        if num_bits % 8 != 0 && data[len - 1] >> (num_bits % 8) != 0 {
+            return Err(DecodeBloomError::NoncanonicBits);
+        }
+
+        Ok(BloomFilter {
+            bits: data.to_vec(),
+            num_bits,
+            num_hashes,
+        })
+    }
+}
+
+// The finalizer of splitmix64, which mixes all bits of the input into every bit of the output.
+fn splitmix64(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Everything that can go wrong when deserializing a `BloomFilter`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeBloomError {
+    /// The input ended before the announced number of bits.
+    UnexpectedEnd,
+    /// The input contained more bytes than the announced number of bits.
+    TrailingBytes,
+    /// The number of bits or the number of hashes was zero, or there were more than
+    /// [`MAX_HASHES`](constant.MAX_HASHES.html) hashes.
+    InvalidParameters,
+    /// The unused bits of the last byte were not zero.
+    NoncanonicBits,
+}
+
+impl fmt::Display for DecodeBloomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeBloomError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            DecodeBloomError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeBloomError::InvalidParameters => write!(f, "Invalid filter parameters"),
+            DecodeBloomError::NoncanonicBits => write!(f, "Noncanonic trailing bits"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeBloomError {}
+
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+#[test]
+fn test_bloom_filter() {
+    let key = Multikey::from_ed25519(&[7; 32]);
+    let msg = Multihash::message_from_digest([7; 32]);
+    let blob = Multihash::blob_from_digest([7; 32]);
+
+    let mut filter = BloomFilter::with_rate(100, 0.01);
+    assert_eq!(filter.num_bits(), 959);
+    assert_eq!(filter.num_hashes(), 7);
+    filter.insert(&key);
+    filter.insert(&msg);
+    assert!(filter.contains(&key));
+    assert!(filter.contains(&msg));
+    assert!(!filter.contains(&blob));
+
+    // The bits of a ref are fixed by the documented hashing scheme.
+    let mut small = BloomFilter::new(13, 2);
+    small.insert(&msg);
+    assert_eq!(small.to_bytes(), vec![0, 0, 0, 2, 0, 0, 0, 13, 0x00, 0x09]);
+
+    let enc = filter.to_bytes();
+    assert_eq!(enc.len(), 8 + 120);
+    assert_eq!(BloomFilter::from_bytes(&enc).unwrap(), filter);
+
+    assert_eq!(
+        BloomFilter::from_bytes(&enc[..enc.len() - 1]),
+        Err(DecodeBloomError::UnexpectedEnd)
+    );
+    let mut trailing = enc.clone();
+    trailing.push(0);
+    assert_eq!(
+        BloomFilter::from_bytes(&trailing),
+        Err(DecodeBloomError::TrailingBytes)
+    );
+    assert_eq!(
+        BloomFilter::from_bytes(&[0, 0, 0, 0, 0, 0, 0, 8, 0]),
+        Err(DecodeBloomError::InvalidParameters)
+    );
+    assert_eq!(
+        BloomFilter::from_bytes(&[0, 0, 0, 33, 0, 0, 0, 8, 0]),
+        Err(DecodeBloomError::InvalidParameters)
+    );
+    assert!(BloomFilter::from_bytes(&[0, 0, 0, 32, 0, 0, 0, 8, 0]).is_ok());
+    assert_eq!(
+        BloomFilter::from_bytes(&[0, 0, 0, 1, 0, 0, 0, 7, 0x80]),
+        Err(DecodeBloomError::NoncanonicBits)
+    );
+}
+
+#[test]
+fn test_bloom_filter_limits() {
+    assert_eq!(BloomFilter::with_rate(10, 1e-30).num_hashes(), MAX_HASHES);
+    assert!(std::panic::catch_unwind(|| BloomFilter::new(8, MAX_HASHES + 1)).is_err());
+    for &rate in &[0.0, 1.0, -0.5, f64::NAN] {
+        assert!(std::panic::catch_unwind(|| BloomFilter::with_rate(10, rate)).is_err());
+    }
+}
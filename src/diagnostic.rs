@@ -0,0 +1,88 @@
+//! [miette](https://docs.rs/miette) diagnostics for decoding errors, pointing at the offending
+//! part of the input.
+use std::fmt;
+
+use miette::{Diagnostic, LabeledSpan, SourceCode};
+
+use super::DecodeError;
+
+/// A [`DecodeError`](../struct.DecodeError.html) together with the input that failed to decode,
+/// so that it can be reported as a diagnostic with a labeled span.
+#[derive(Debug, Clone)]
+pub struct DecodeDiagnostic<K> {
+    error: DecodeError<K>,
+    input: String,
+}
+
+impl<K> DecodeDiagnostic<K> {
+    /// Pair a decoding error with the input that produced it. Non-utf8 input is replaced lossily,
+    /// which does not change the offset of valid legacy encodings since they are pure ascii.
+    pub fn new(error: DecodeError<K>, input: &[u8]) -> DecodeDiagnostic<K> {
+        DecodeDiagnostic {
+            error,
+            input: String::from_utf8_lossy(input).into_owned(),
+        }
+    }
+
+    /// The underlying decoding error.
+    pub fn error(&self) -> &DecodeError<K> {
+        &self.error
+    }
+}
+
+impl<K: fmt::Display> fmt::Display for DecodeDiagnostic<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<K: std::error::Error> std::error::Error for DecodeDiagnostic<K> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.kind.source()
+    }
+}
+
+impl<K: std::error::Error> Diagnostic for DecodeDiagnostic<K> {
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.input)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        // Point at a single character, or at the (empty) end of the input.
+        let len = if self.error.offset < self.input.len() {
+            1
+        } else {
+            0
+        };
+        Some(Box::new(std::iter::once(LabeledSpan::new(
+            Some(self.error.kind.to_string()),
+            self.error.offset,
+            len,
+        ))))
+    }
+}
+
+impl<K> DecodeError<K> {
+    /// Pair this error with the input that produced it, for reporting as a miette diagnostic.
+    pub fn into_diagnostic(self, input: &[u8]) -> DecodeDiagnostic<K> {
+        DecodeDiagnostic::new(self, input)
+    }
+}
+
+#[cfg(feature = "multihash")]
+#[test]
+fn test_labels() {
+    use super::multihash::Multihash;
+    use super::LegacyEncoding;
+
+    let input = b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.tha256";
+    let diag = Multihash::from_legacy_located(input)
+        .unwrap_err()
+        .into_diagnostic(input);
+
+    let labels: Vec<_> = diag.labels().unwrap().collect();
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].offset(), 46);
+    assert_eq!(labels[0].len(), 1);
+    assert_eq!(labels[0].label(), Some("Unknown suffix"));
+}
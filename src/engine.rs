@@ -0,0 +1,81 @@
+//! Pluggable base64 implementations.
+//!
+//! All legacy encodings carry their data as base64 (standard alphabet, with padding). The
+//! `from_legacy_with_engine` and `to_legacy_with_engine` functions let consumers substitute their
+//! own implementation for the one from the base64 crate, e.g. a constant-time decoder for key
+//! material or a more compact implementation for embedded targets.
+
+/// A base64 implementation (standard alphabet, with padding).
+pub trait Base64Engine {
+    /// Decode the input into the start of `out`, returning the number of bytes written.
+    ///
+    /// Must reject (rather than repair) everything that is not canonical, padded base64 in the
+    /// standard alphabet, exactly like the base64 crate does. The caller guarantees that `out`
+    /// can hold the decoded bytes whenever the input is valid.
+    fn decode_slice(&self, input: &[u8], out: &mut [u8]) -> Result<usize, base64::DecodeError>;
+
+    /// Encode the input into the start of `out`, returning the number of bytes written. The
+    /// caller guarantees that `out` is at least `4 * ceil(input.len() / 3)` bytes long.
+    fn encode_slice(&self, input: &[u8], out: &mut [u8]) -> usize;
+}
+
+/// The default `Base64Engine`, backed by the base64 crate. This is what all functions that do
+/// not take an engine use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DefaultEngine;
+
+impl Base64Engine for DefaultEngine {
+    fn decode_slice(&self, input: &[u8], out: &mut [u8]) -> Result<usize, base64::DecodeError> {
+        base64::decode_config_slice(input, base64::STANDARD, out)
+    }
+
+    fn encode_slice(&self, input: &[u8], out: &mut [u8]) -> usize {
+        base64::encode_config_slice(input, base64::STANDARD, out)
+    }
+}
+
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+#[test]
+fn test_custom_engine() {
+    use std::cell::Cell;
+
+    use super::multihash::Multihash;
+    use super::multikey::Multikey;
+
+    // Delegates to the default engine, but counts how often it is used.
+    #[derive(Default)]
+    struct Counting(Cell<usize>, Cell<usize>);
+
+    impl Base64Engine for Counting {
+        fn decode_slice(&self, input: &[u8], out: &mut [u8]) -> Result<usize, base64::DecodeError> {
+            self.0.set(self.0.get() + 1);
+            DefaultEngine.decode_slice(input, out)
+        }
+
+        fn encode_slice(&self, input: &[u8], out: &mut [u8]) -> usize {
+            self.1.set(self.1.get() + 1);
+            DefaultEngine.encode_slice(input, out)
+        }
+    }
+
+    let engine = Counting::default();
+    let opts = super::ParseOptions::default();
+
+    let enc = b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let (mk, _) = Multikey::from_legacy_with_engine(enc, &opts, &engine).unwrap();
+    assert_eq!(mk, Multikey::from_legacy(enc).unwrap().0);
+    let mut out = Vec::new();
+    mk.to_legacy_with_engine(&mut out, &engine).unwrap();
+    assert_eq!(&out[..], &enc[..]);
+
+    let (mh, _) = Multihash::from_legacy_with_engine(
+        b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256",
+        &opts,
+        &engine,
+    )
+    .unwrap();
+    assert!(mh.is_message());
+
+    assert_eq!(engine.0.get(), 2);
+    assert_eq!(engine.1.get(), 1);
+}
@@ -0,0 +1,213 @@
+//! Dense integer ids for feeds, for graph algorithms (follow graphs, ranking) that would rather
+//! work with `u32` node ids than with 32 byte keys.
+//!
+//! Keys are numbered in the order in which they are first interned, starting at zero. The
+//! compact binary serialization is the number of keys as a big-endian `u32`, followed by the
+//! raw 32 bytes of each ed25519 key in the order of their ids.
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::FromIterator;
+use std::slice;
+
+use super::multikey::Multikey;
+
+// The length of a single serialized key.
+const KEY_LEN: usize = 32;
+
+/// A bidirectional mapping between `Multikey`s and dense `u32` ids.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedIdx {
+    keys: Vec<Multikey>,
+    ids: HashMap<Multikey, u32>,
+}
+
+impl FeedIdx {
+    /// Create an empty `FeedIdx`.
+    pub fn new() -> FeedIdx {
+        FeedIdx::default()
+    }
+
+    /// The number of interned keys, which is also the id the next new key will get.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether no keys have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Return the id of the given key, assigning it the next free id if it has none yet.
+    ///
+    /// Panics if all `u32` ids are taken.
+    pub fn intern(&mut self, key: &Multikey) -> u32 {
+        if let Some(&id) = self.ids.get(key) {
+            return id;
+        }
+
+        let id = u32::try_from(self.keys.len()).expect("FeedIdx is full");
+        self.keys.push(key.clone());
+        self.ids.insert(key.clone(), id);
+        id
+    }
+
+    /// The id of the given key, if it has been interned.
+    pub fn id(&self, key: &Multikey) -> Option<u32> {
+        self.ids.get(key).copied()
+    }
+
+    /// The key with the given id, if there is one.
+    pub fn key(&self, id: u32) -> Option<&Multikey> {
+        self.keys.get(id as usize)
+    }
+
+    /// Iterate over the keys in the order of their ids.
+    pub fn iter(&self) -> slice::Iter<'_, Multikey> {
+        self.keys.iter()
+    }
+
+    /// The keys in the order of their ids, i.e. the key with id `i` is at index `i`.
+    pub fn as_slice(&self) -> &[Multikey] {
+        &self.keys
+    }
+
+    /// Serialize the table into its compact binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + self.keys.len() * KEY_LEN);
+        out.extend_from_slice(&(self.keys.len() as u32).to_be_bytes());
+        for key in &self.keys {
+            out.extend_from_slice(key.as_ed25519().unwrap());
+        }
+        out
+    }
+
+    /// Deserialize a table from its compact binary form, restoring the ids of all keys. No key
+    /// may appear more than once.
+    pub fn from_bytes(s: &[u8]) -> Result<FeedIdx, DecodeFeedIdxError> {
+        if s.len() < 4 {
+            return Err(DecodeFeedIdxError::UnexpectedEnd);
+        }
+
+        let mut count = [0u8; 4];
+        count.copy_from_slice(&s[..4]);
+        let count = u32::from_be_bytes(count) as usize;
+        let data = &s[4..];
+
+        // Compare against the input length before allocating, so a corrupt count can not
+        // trigger a huge allocation.
+        let expected = count
+            .checked_mul(KEY_LEN)
+            .ok_or(DecodeFeedIdxError::UnexpectedEnd)?;
+        if data.len() < expected {
+            return Err(DecodeFeedIdxError::UnexpectedEnd);
+        }
+        if data.len() > expected {
+            return Err(DecodeFeedIdxError::TrailingBytes);
+        }
+
+        let mut idx = FeedIdx {
+            keys: Vec::with_capacity(count),
+            ids: HashMap::with_capacity(count),
+        };
+        for chunk in data.chunks(KEY_LEN) {
+            let key = Multikey::from_ed25519_slice(chunk);
+            if idx.ids.contains_key(&key) {
+                return Err(DecodeFeedIdxError::Duplicate);
+            }
+            idx.intern(&key);
+        }
+
+        Ok(idx)
+    }
+}
+
+impl FromIterator<Multikey> for FeedIdx {
+    fn from_iter<I: IntoIterator<Item = Multikey>>(iter: I) -> FeedIdx {
+        let mut idx = FeedIdx::new();
+        for key in iter {
+            idx.intern(&key);
+        }
+        idx
+    }
+}
+
+impl<'a> IntoIterator for &'a FeedIdx {
+    type Item = &'a Multikey;
+    type IntoIter = slice::Iter<'a, Multikey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.keys.iter()
+    }
+}
+
+/// Everything that can go wrong when deserializing a `FeedIdx`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeFeedIdxError {
+    /// The input ended before the announced number of keys.
+    UnexpectedEnd,
+    /// The input contained more bytes than the announced number of keys.
+    TrailingBytes,
+    /// A key appeared more than once.
+    Duplicate,
+}
+
+impl fmt::Display for DecodeFeedIdxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeFeedIdxError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            DecodeFeedIdxError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeFeedIdxError::Duplicate => write!(f, "Duplicate key"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeFeedIdxError {}
+
+#[test]
+fn test_feed_idx() {
+    let a = Multikey::from_ed25519(&[1; 32]);
+    let b = Multikey::from_ed25519(&[2; 32]);
+    let c = Multikey::from_ed25519(&[3; 32]);
+
+    let mut idx = FeedIdx::new();
+    assert_eq!(idx.intern(&c), 0);
+    assert_eq!(idx.intern(&a), 1);
+    assert_eq!(idx.intern(&c), 0);
+    assert_eq!(idx.len(), 2);
+    assert_eq!(idx.id(&a), Some(1));
+    assert_eq!(idx.id(&b), None);
+    assert_eq!(idx.key(0), Some(&c));
+    assert_eq!(idx.key(2), None);
+    assert_eq!(idx.as_slice(), &[c.clone(), a.clone()][..]);
+
+    let enc = idx.to_bytes();
+    assert_eq!(enc.len(), 4 + 2 * 32);
+    assert_eq!(&enc[..4], &[0, 0, 0, 2]);
+    assert_eq!(FeedIdx::from_bytes(&enc).unwrap(), idx);
+    assert_eq!(
+        vec![c.clone(), a.clone(), c]
+            .into_iter()
+            .collect::<FeedIdx>(),
+        idx
+    );
+
+    let mut dup = enc.clone();
+    dup[3] = 3;
+    dup.extend_from_slice(&[1; 32]);
+    assert_eq!(
+        FeedIdx::from_bytes(&dup),
+        Err(DecodeFeedIdxError::Duplicate)
+    );
+    assert_eq!(
+        FeedIdx::from_bytes(&enc[..40]),
+        Err(DecodeFeedIdxError::UnexpectedEnd)
+    );
+    dup.truncate(enc.len() + 1);
+    dup[3] = 2;
+    assert_eq!(
+        FeedIdx::from_bytes(&dup),
+        Err(DecodeFeedIdxError::TrailingBytes)
+    );
+}
@@ -0,0 +1,235 @@
+//! A set of feed ids, e.g. the feeds someone follows or blocks.
+//!
+//! The set keeps its keys sorted and deduplicated in a single allocation, so membership tests are
+//! binary searches. It has a compact binary serialization: the number of keys as a big-endian
+//! `u32`, followed by the raw 32 bytes of each ed25519 key in ascending order.
+use std::fmt;
+use std::iter::FromIterator;
+use std::slice;
+
+use super::multikey::Multikey;
+
+// The length of a single serialized key.
+const KEY_LEN: usize = 32;
+
+/// A sorted, deduplicated set of `Multikey`s.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeedSet(Vec<Multikey>);
+
+impl FeedSet {
+    /// Create an empty `FeedSet`.
+    pub fn new() -> FeedSet {
+        FeedSet(Vec::new())
+    }
+
+    /// The number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the set contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether the set contains the given key.
+    pub fn contains(&self, key: &Multikey) -> bool {
+        self.0.binary_search(key).is_ok()
+    }
+
+    /// Add a key to the set, returning whether it was not present before.
+    pub fn insert(&mut self, key: Multikey) -> bool {
+        match self.0.binary_search(&key) {
+            Ok(_) => false,
+            Err(i) => {
+                self.0.insert(i, key);
+                true
+            }
+        }
+    }
+
+    /// Remove a key from the set, returning whether it was present.
+    pub fn remove(&mut self, key: &Multikey) -> bool {
+        match self.0.binary_search(key) {
+            Ok(i) => {
+                self.0.remove(i);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Iterate over the keys in ascending order.
+    pub fn iter(&self) -> slice::Iter<'_, Multikey> {
+        self.0.iter()
+    }
+
+    /// The keys in ascending order.
+    pub fn as_slice(&self) -> &[Multikey] {
+        &self.0
+    }
+
+    /// Serialize the set into its compact binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + self.0.len() * KEY_LEN);
+        out.extend_from_slice(&(self.0.len() as u32).to_be_bytes());
+        for key in &self.0 {
+            out.extend_from_slice(key.as_ed25519().unwrap());
+        }
+        out
+    }
+
+    /// Deserialize a set from its compact binary form. The keys must be strictly ascending, so
+    /// that every set has exactly one valid serialization.
+    pub fn from_bytes(s: &[u8]) -> Result<FeedSet, DecodeFeedSetError> {
+        if s.len() < 4 {
+            return Err(DecodeFeedSetError::UnexpectedEnd);
+        }
+
+        let mut count = [0u8; 4];
+        count.copy_from_slice(&s[..4]);
+        let count = u32::from_be_bytes(count) as usize;
+        let data = &s[4..];
+
+        // Compare against the input length before allocating, so a corrupt count can not
+        // trigger a huge allocation.
+        let expected = count
+            .checked_mul(KEY_LEN)
+            .ok_or(DecodeFeedSetError::UnexpectedEnd)?;
+        if data.len() < expected {
+            return Err(DecodeFeedSetError::UnexpectedEnd);
+        }
+        if data.len() > expected {
+            return Err(DecodeFeedSetError::TrailingBytes);
+        }
+
+        let mut keys: Vec<Multikey> = Vec::with_capacity(count);
+        for chunk in data.chunks(KEY_LEN) {
+            let key = Multikey::from_ed25519_slice(chunk);
+            if let Some(last) = keys.last() {
+                if *last >= key {
+                    return Err(DecodeFeedSetError::NotSorted);
+                }
+            }
+            keys.push(key);
+        }
+
+        Ok(FeedSet(keys))
+    }
+}
+
+impl FromIterator<Multikey> for FeedSet {
+    fn from_iter<I: IntoIterator<Item = Multikey>>(iter: I) -> FeedSet {
+        let mut keys: Vec<Multikey> = iter.into_iter().collect();
+        keys.sort();
+        keys.dedup();
+        FeedSet(keys)
+    }
+}
+
+impl Extend<Multikey> for FeedSet {
+    fn extend<I: IntoIterator<Item = Multikey>>(&mut self, iter: I) {
+        self.0.extend(iter);
+        self.0.sort();
+        self.0.dedup();
+    }
+}
+
+impl IntoIterator for FeedSet {
+    type Item = Multikey;
+    type IntoIter = std::vec::IntoIter<Multikey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FeedSet {
+    type Item = &'a Multikey;
+    type IntoIter = slice::Iter<'a, Multikey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Everything that can go wrong when deserializing a `FeedSet`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeFeedSetError {
+    /// The input ended before the announced number of keys.
+    UnexpectedEnd,
+    /// The input contained more bytes than the announced number of keys.
+    TrailingBytes,
+    /// The keys were not strictly ascending.
+    NotSorted,
+}
+
+impl fmt::Display for DecodeFeedSetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeFeedSetError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            DecodeFeedSetError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeFeedSetError::NotSorted => write!(f, "Keys not sorted"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeFeedSetError {}
+
+#[test]
+fn test_feed_set() {
+    let a = Multikey::from_ed25519(&[1; 32]);
+    let b = Multikey::from_ed25519(&[2; 32]);
+    let c = Multikey::from_ed25519(&[3; 32]);
+
+    let mut set: FeedSet = vec![c.clone(), a.clone(), c.clone()].into_iter().collect();
+    assert_eq!(set.as_slice(), &[a.clone(), c.clone()][..]);
+    assert!(set.contains(&a));
+    assert!(!set.contains(&b));
+
+    assert!(set.insert(b.clone()));
+    assert!(!set.insert(b.clone()));
+    assert_eq!(set.as_slice(), &[a.clone(), b.clone(), c.clone()][..]);
+    assert!(set.remove(&a));
+    assert!(!set.remove(&a));
+    assert_eq!(set.len(), 2);
+
+    let enc = set.to_bytes();
+    assert_eq!(enc.len(), 4 + 2 * 32);
+    assert_eq!(&enc[..4], &[0, 0, 0, 2]);
+    assert_eq!(FeedSet::from_bytes(&enc).unwrap(), set);
+    assert_eq!(
+        FeedSet::from_bytes(&FeedSet::new().to_bytes()).unwrap(),
+        FeedSet::new()
+    );
+}
+
+#[test]
+fn test_from_bytes_errors() {
+    let mut enc = vec![0, 0, 0, 2];
+    enc.extend_from_slice(&[2; 32]);
+    assert_eq!(
+        FeedSet::from_bytes(&enc),
+        Err(DecodeFeedSetError::UnexpectedEnd)
+    );
+    enc.extend_from_slice(&[1; 32]);
+    assert_eq!(
+        FeedSet::from_bytes(&enc),
+        Err(DecodeFeedSetError::NotSorted)
+    );
+    enc.push(0);
+    assert_eq!(
+        FeedSet::from_bytes(&enc),
+        Err(DecodeFeedSetError::TrailingBytes)
+    );
+
+    assert_eq!(
+        FeedSet::from_bytes(&[0, 0]),
+        Err(DecodeFeedSetError::UnexpectedEnd)
+    );
+    assert_eq!(
+        FeedSet::from_bytes(&[0xff, 0xff, 0xff, 0xff]),
+        Err(DecodeFeedSetError::UnexpectedEnd)
+    );
+}
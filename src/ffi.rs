@@ -0,0 +1,421 @@
+//! A C interface for parsing and serializing the legacy encodings.
+//!
+//! Build it as a shared library with
+//! `cargo rustc --release --features ffi --crate-type cdylib`, the matching declarations are in
+//! `include/ssb_multiformats.h` (generated with `cbindgen`).
+//!
+//! Every function returns a status code: `SSB_OK` on success, one of the other `SSB_ERR_*`
+//! constants if the arguments were unusable, or otherwise the
+//! [`error_code`](../multikey/enum.DecodeLegacyError.html#method.error_code) of the decoding
+//! error. Results are written to out-parameters, which are left untouched on failure.
+//!
+//! The one exception are output buffers: functions that write into a caller-provided buffer
+//! always store the length of the full output, so that callers can retry with a large enough
+//! buffer after receiving `SSB_ERR_BUFFER_TOO_SMALL`. Encodings are *not* null-terminated.
+use std::ptr;
+use std::slice;
+
+use super::multibox::Multibox;
+use super::multihash::{Multihash, Target};
+use super::multikey::{Multikey, Multisig};
+
+/// The operation succeeded.
+pub const SSB_OK: u16 = 0;
+/// A required pointer argument was null.
+pub const SSB_ERR_NULL: u16 = 1;
+/// The output buffer was too small to hold the result.
+pub const SSB_ERR_BUFFER_TOO_SMALL: u16 = 2;
+/// A multihash target other than `SSB_TARGET_MESSAGE` or `SSB_TARGET_BLOB` was given.
+pub const SSB_ERR_INVALID_TARGET: u16 = 3;
+
+/// The target of a multihash that refers to a message.
+pub const SSB_TARGET_MESSAGE: u8 = 0;
+/// The target of a multihash that refers to a blob.
+pub const SSB_TARGET_BLOB: u8 = 1;
+
+/// Parse a legacy multikey, writing the 32 bytes of the ed25519 public key to `out_key`, and the
+/// number of bytes the encoding took up to `out_consumed` (which may be null).
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, `out_key` to 32 writable bytes, and
+/// `out_consumed` must be null or point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multikey_from_legacy(
+    input: *const u8,
+    input_len: usize,
+    out_key: *mut u8,
+    out_consumed: *mut usize,
+) -> u16 {
+    if input.is_null() || out_key.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    let input = slice::from_raw_parts(input, input_len);
+    match Multikey::from_legacy(input) {
+        Ok((mk, tail)) => {
+            let pk = mk.into_ed25519_public_key().unwrap();
+            ptr::copy_nonoverlapping(pk[..].as_ptr(), out_key, 32);
+            write_consumed(out_consumed, input.len() - tail.len());
+            SSB_OK
+        }
+        Err(err) => err.error_code(),
+    }
+}
+
+/// Write the legacy encoding of the ed25519 public key at `key` into `out`.
+///
+/// # Safety
+///
+/// `key` must point to 32 readable bytes, `out` to `out_cap` writable bytes, and `out_len` to a
+/// writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multikey_to_legacy(
+    key: *const u8,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> u16 {
+    if key.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    let key = &*(key as *const [u8; 32]);
+    write_output(
+        &Multikey::from_ed25519(key).to_legacy_vec(),
+        out,
+        out_cap,
+        out_len,
+    )
+}
+
+/// Parse a legacy multihash, writing its target (`SSB_TARGET_MESSAGE` or `SSB_TARGET_BLOB`) to
+/// `out_target`, the 32 bytes of the sha256 digest to `out_digest`, and the number of bytes the
+/// encoding took up to `out_consumed` (which may be null).
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, `out_target` to a writable byte,
+/// `out_digest` to 32 writable bytes, and `out_consumed` must be null or point to a writable
+/// `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multihash_from_legacy(
+    input: *const u8,
+    input_len: usize,
+    out_target: *mut u8,
+    out_digest: *mut u8,
+    out_consumed: *mut usize,
+) -> u16 {
+    if input.is_null() || out_target.is_null() || out_digest.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    let input = slice::from_raw_parts(input, input_len);
+    match Multihash::from_legacy(input) {
+        Ok((mh, tail)) => {
+            *out_target = match mh.0 {
+                Target::Message => SSB_TARGET_MESSAGE,
+                Target::Blob => SSB_TARGET_BLOB,
+            };
+            ptr::copy_nonoverlapping(mh.as_sha256().unwrap().as_ptr(), out_digest, 32);
+            write_consumed(out_consumed, input.len() - tail.len());
+            SSB_OK
+        }
+        Err(err) => err.error_code(),
+    }
+}
+
+/// Write the legacy encoding of the sha256 digest at `digest` with the given target into `out`.
+///
+/// # Safety
+///
+/// `digest` must point to 32 readable bytes, `out` to `out_cap` writable bytes, and `out_len` to
+/// a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multihash_to_legacy(
+    target: u8,
+    digest: *const u8,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> u16 {
+    if digest.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    let target = match target {
+        SSB_TARGET_MESSAGE => Target::Message,
+        SSB_TARGET_BLOB => Target::Blob,
+        _ => return SSB_ERR_INVALID_TARGET,
+    };
+    let digest = *(digest as *const [u8; 32]);
+    write_output(
+        &Multihash::from_sha256(digest, target).to_legacy_vec(),
+        out,
+        out_cap,
+        out_len,
+    )
+}
+
+/// Parse a legacy ed25519 signature, writing the 64 signature bytes to `out_sig`, and the number
+/// of bytes the encoding took up to `out_consumed` (which may be null).
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, `out_sig` to 64 writable bytes, and
+/// `out_consumed` must be null or point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multisig_from_legacy(
+    input: *const u8,
+    input_len: usize,
+    out_sig: *mut u8,
+    out_consumed: *mut usize,
+) -> u16 {
+    if input.is_null() || out_sig.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    // Any ed25519 key selects the ed25519 signature format.
+    let input = slice::from_raw_parts(input, input_len);
+    match Multikey::from_ed25519(&[0; 32]).sig_from_legacy(input) {
+        Ok((sig, tail)) => {
+            ptr::copy_nonoverlapping(sig.as_ed25519().unwrap().as_ptr(), out_sig, 64);
+            write_consumed(out_consumed, input.len() - tail.len());
+            SSB_OK
+        }
+        Err(err) => err.error_code(),
+    }
+}
+
+/// Write the legacy encoding of the ed25519 signature at `sig` into `out`.
+///
+/// # Safety
+///
+/// `sig` must point to 64 readable bytes, `out` to `out_cap` writable bytes, and `out_len` to a
+/// writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multisig_to_legacy(
+    sig: *const u8,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> u16 {
+    if sig.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    let sig = &*(sig as *const [u8; 64]);
+    write_output(
+        &Multisig::from_ed25519(sig).to_legacy_vec(),
+        out,
+        out_cap,
+        out_len,
+    )
+}
+
+/// Parse a legacy multibox, writing its identifier (zero for private boxes) to `out_id`, the
+/// decoded cyphertext to `out_cyphertext`, the length of the cyphertext to
+/// `out_cyphertext_len`, and the number of bytes the encoding took up to `out_consumed` (which
+/// may be null).
+///
+/// If the cyphertext is longer than `out_cyphertext_cap`, this returns
+/// `SSB_ERR_BUFFER_TOO_SMALL` but still stores the cyphertext length.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, `out_id` to a writable `uint64_t`,
+/// `out_cyphertext` to `out_cyphertext_cap` writable bytes, `out_cyphertext_len` to a writable
+/// `size_t`, and `out_consumed` must be null or point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multibox_from_legacy(
+    input: *const u8,
+    input_len: usize,
+    out_id: *mut u64,
+    out_cyphertext: *mut u8,
+    out_cyphertext_cap: usize,
+    out_cyphertext_len: *mut usize,
+    out_consumed: *mut usize,
+) -> u16 {
+    if input.is_null() || out_id.is_null() || out_cyphertext_len.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    let input = slice::from_raw_parts(input, input_len);
+    match Multibox::from_legacy(input) {
+        Ok((mb, tail)) => {
+            let status = write_output(
+                mb.cyphertext(),
+                out_cyphertext,
+                out_cyphertext_cap,
+                out_cyphertext_len,
+            );
+            if status == SSB_OK {
+                *out_id = mb.id();
+                write_consumed(out_consumed, input.len() - tail.len());
+            }
+            status
+        }
+        Err(err) => err.error_code(),
+    }
+}
+
+/// Write the legacy encoding of a multibox with the given identifier (zero for private boxes)
+/// and cyphertext into `out`.
+///
+/// # Safety
+///
+/// `cyphertext` must point to `cyphertext_len` readable bytes, `out` to `out_cap` writable
+/// bytes, and `out_len` to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn ssb_multibox_to_legacy(
+    id: u64,
+    cyphertext: *const u8,
+    cyphertext_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> u16 {
+    if cyphertext.is_null() {
+        return SSB_ERR_NULL;
+    }
+
+    let cyphertext = slice::from_raw_parts(cyphertext, cyphertext_len).to_vec();
+    write_output(
+        &Multibox::new_multibox(id, cyphertext).to_legacy_vec(),
+        out,
+        out_cap,
+        out_len,
+    )
+}
+
+// Store the length of `data` in `out_len`, and copy `data` to `out` if it fits. `out` may be
+// null if `out_cap` is zero, to query the length.
+unsafe fn write_output(data: &[u8], out: *mut u8, out_cap: usize, out_len: *mut usize) -> u16 {
+    if out_len.is_null() || (out.is_null() && out_cap > 0) {
+        return SSB_ERR_NULL;
+    }
+
+    *out_len = data.len();
+    if data.len() > out_cap {
+        return SSB_ERR_BUFFER_TOO_SMALL;
+    }
+    if data.is_empty() {
+        // Copying to a null pointer is undefined behavior, even for zero bytes.
+        return SSB_OK;
+    }
+
+    ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    SSB_OK
+}
+
+unsafe fn write_consumed(out_consumed: *mut usize, consumed: usize) {
+    if !out_consumed.is_null() {
+        *out_consumed = consumed;
+    }
+}
+
+#[test]
+fn test_multikey() {
+    let enc = b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\"";
+    let mut key = [0u8; 32];
+    let mut consumed = 0;
+    unsafe {
+        assert_eq!(
+            ssb_multikey_from_legacy(enc.as_ptr(), enc.len(), key.as_mut_ptr(), &mut consumed),
+            SSB_OK
+        );
+    }
+    assert_eq!(consumed, enc.len() - 1);
+
+    let mut out = [0u8; 53];
+    let mut out_len = 0;
+    unsafe {
+        assert_eq!(
+            ssb_multikey_to_legacy(key.as_ptr(), out.as_mut_ptr(), 10, &mut out_len),
+            SSB_ERR_BUFFER_TOO_SMALL
+        );
+        assert_eq!(out_len, 53);
+        assert_eq!(
+            ssb_multikey_to_legacy(key.as_ptr(), out.as_mut_ptr(), out.len(), &mut out_len),
+            SSB_OK
+        );
+    }
+    assert_eq!(&out[..], &enc[..53]);
+
+    unsafe {
+        assert_eq!(
+            ssb_multikey_from_legacy(enc.as_ptr(), 10, key.as_mut_ptr(), ptr::null_mut()),
+            super::multikey::DecodeLegacyError::NoDot.error_code()
+        );
+    }
+}
+
+#[test]
+fn test_multibox() {
+    let enc = b"lA==.boxNN";
+    let mut id = 0;
+    let mut cyphertext = [0u8; 4];
+    let mut cyphertext_len = 0;
+    unsafe {
+        assert_eq!(
+            ssb_multibox_from_legacy(
+                enc.as_ptr(),
+                enc.len(),
+                &mut id,
+                cyphertext.as_mut_ptr(),
+                cyphertext.len(),
+                &mut cyphertext_len,
+                ptr::null_mut()
+            ),
+            SSB_OK
+        );
+    }
+    assert_eq!(id, 0b10101_10101);
+    assert_eq!(&cyphertext[..cyphertext_len], &[0x94]);
+
+    let mut out = [0u8; 16];
+    let mut out_len = 0;
+    unsafe {
+        assert_eq!(
+            ssb_multibox_to_legacy(
+                id,
+                cyphertext.as_ptr(),
+                cyphertext_len,
+                out.as_mut_ptr(),
+                out.len(),
+                &mut out_len
+            ),
+            SSB_OK
+        );
+    }
+    assert_eq!(&out[..out_len], &enc[..]);
+}
+
+#[test]
+fn test_null_output() {
+    let mut id = 1;
+    let mut len = 1;
+    unsafe {
+        // Querying the length of an encoding.
+        assert_eq!(
+            ssb_multibox_to_legacy(0, [0u8; 0].as_ptr(), 0, ptr::null_mut(), 0, &mut len),
+            SSB_ERR_BUFFER_TOO_SMALL
+        );
+        assert_eq!(len, 4);
+
+        // An empty cyphertext fits into an empty output.
+        assert_eq!(
+            ssb_multibox_from_legacy(
+                b".box".as_ptr(),
+                4,
+                &mut id,
+                ptr::null_mut(),
+                0,
+                &mut len,
+                ptr::null_mut()
+            ),
+            SSB_OK
+        );
+    }
+    assert_eq!((id, len), (0, 0));
+}
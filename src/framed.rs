@@ -0,0 +1,331 @@
+//! A simple container format for sequences of multiformat values.
+//!
+//! Each value is stored as a frame: its legacy encoding, preceded by the length of that encoding
+//! as a big-endian `u32`. A stream of frames can be read back one value at a time, without
+//! knowing in advance how many values it contains.
+//!
+//! Streams that are kept on disk for a long time can be written `with_checksums`, which
+//! appends the CRC-32 of the length and the encoding to every frame, as a big-endian `u32`. A
+//! reader created `with_checksums` then reports frames that were torn by an interrupted write or
+//! damaged later on, rather than decoding garbage.
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use super::LegacyEncoding;
+
+/// Writes values as length-prefixed frames into an underlying writer.
+#[derive(Debug)]
+pub struct FramedWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    checksums: bool,
+}
+
+impl<W: Write> FramedWriter<W> {
+    /// Create a `FramedWriter` that writes frames into the given writer.
+    pub fn new(inner: W) -> FramedWriter<W> {
+        FramedWriter {
+            inner,
+            buf: Vec::new(),
+            checksums: false,
+        }
+    }
+
+    /// Create a `FramedWriter` that appends a checksum to every frame it writes.
+    pub fn with_checksums(inner: W) -> FramedWriter<W> {
+        FramedWriter {
+            inner,
+            buf: Vec::new(),
+            checksums: true,
+        }
+    }
+
+    /// Write a single value as a frame.
+    pub fn write<T: LegacyEncoding>(&mut self, value: &T) -> Result<(), io::Error> {
+        self.buf.clear();
+        value.to_legacy(&mut self.buf)?;
+
+        if self.buf.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "value too long for a frame",
+            ));
+        }
+
+        let len = (self.buf.len() as u32).to_be_bytes();
+        self.inner.write_all(&len)?;
+        self.inner.write_all(&self.buf)?;
+        if self.checksums {
+            self.inner
+                .write_all(&crc32(&[&len, &self.buf]).to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush()
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Lazily reads values of type `T` from a stream of length-prefixed frames.
+///
+/// This is an iterator that ends when the underlying reader ends at a frame boundary.
+#[derive(Debug)]
+pub struct FramedReader<R, T> {
+    inner: R,
+    buf: Vec<u8>,
+    checksums: bool,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<R: Read, T: LegacyEncoding> FramedReader<R, T> {
+    /// Create a `FramedReader` that reads frames from the given reader.
+    pub fn new(inner: R) -> FramedReader<R, T> {
+        FramedReader {
+            inner,
+            buf: Vec::new(),
+            checksums: false,
+            _value: PhantomData,
+        }
+    }
+
+    /// Create a `FramedReader` for frames written `with_checksums`, which verifies the checksum
+    /// of every frame before decoding it.
+    pub fn with_checksums(inner: R) -> FramedReader<R, T> {
+        FramedReader {
+            inner,
+            buf: Vec::new(),
+            checksums: true,
+            _value: PhantomData,
+        }
+    }
+
+    /// Read the next value, or return `Ok(None)` if the reader ended at a frame boundary.
+    pub fn read(&mut self) -> Result<Option<T>, DecodeFrameError<T::Error>> {
+        let mut len = [0u8; 4];
+        let mut filled = 0;
+
+        while filled < len.len() {
+            match self.inner.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => filled += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // Read in chunks rather than allocating the announced length up front, so a corrupt
+        // length can not trigger a huge allocation. The buffer only grows as data arrives, and
+        // running out of memory is reported rather than aborting.
+        let len_bytes = len;
+        let len = u32::from_be_bytes(len) as u64;
+        self.buf.clear();
+        let mut limited = (&mut self.inner).take(len);
+        let mut chunk = [0u8; 8192];
+        loop {
+            match limited.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    self.buf
+                        .try_reserve(n)
+                        .map_err(|_| DecodeFrameError::AllocationFailed)?;
+                    self.buf.extend_from_slice(&chunk[..n]);
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if (self.buf.len() as u64) < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        if self.checksums {
+            let mut checksum = [0u8; 4];
+            self.inner.read_exact(&mut checksum)?;
+            if u32::from_be_bytes(checksum) != crc32(&[&len_bytes, &self.buf]) {
+                return Err(DecodeFrameError::ChecksumMismatch);
+            }
+        }
+
+        let (value, tail) = T::from_legacy(&self.buf).map_err(DecodeFrameError::Decode)?;
+
+        if !tail.is_empty() {
+            return Err(DecodeFrameError::TrailingBytes);
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Return the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, T: LegacyEncoding> Iterator for FramedReader<R, T> {
+    type Item = Result<T, DecodeFrameError<T::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+/// Everything that can go wrong when reading a frame.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecodeFrameError<E> {
+    /// Reading from the underlying reader failed, or it ended in the middle of a frame.
+    Io(io::Error),
+    /// The frame did not contain a valid legacy encoding.
+    Decode(E),
+    /// The frame contained more bytes than the legacy encoding of its value.
+    TrailingBytes,
+    /// The memory for the frame could not be allocated.
+    AllocationFailed,
+    /// The checksum of the frame did not match its contents.
+    ChecksumMismatch,
+}
+
+impl<E> From<io::Error> for DecodeFrameError<E> {
+    fn from(err: io::Error) -> DecodeFrameError<E> {
+        DecodeFrameError::Io(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for DecodeFrameError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeFrameError::Io(_) => write!(f, "Could not read frame"),
+            DecodeFrameError::Decode(_) => write!(f, "Invalid value in frame"),
+            DecodeFrameError::TrailingBytes => write!(f, "Trailing bytes in frame"),
+            DecodeFrameError::AllocationFailed => write!(f, "Allocation failed"),
+            DecodeFrameError::ChecksumMismatch => write!(f, "Checksum mismatch in frame"),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for DecodeFrameError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeFrameError::Io(ref err) => Some(err),
+            DecodeFrameError::Decode(ref err) => Some(err),
+            DecodeFrameError::TrailingBytes
+            | DecodeFrameError::AllocationFailed
+            | DecodeFrameError::ChecksumMismatch => None,
+        }
+    }
+}
+
+// The CRC-32 (as used by zlib and ethernet) of the concatenation of the given parts.
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for part in parts {
+        for &b in *part {
+            crc = CRC32_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+    }
+    !crc
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(&[b"123456789"]), 0xcbf4_3926);
+    assert_eq!(crc32(&[b"1234", b"", b"56789"]), 0xcbf4_3926);
+    assert_eq!(crc32(&[]), 0);
+}
+
+#[cfg(feature = "multihash")]
+#[test]
+fn test_roundtrip() {
+    use super::multihash::{Multihash, Target};
+
+    let hashes = vec![
+        Multihash::from_sha256([0; 32], Target::Message),
+        Multihash::from_sha256([1; 32], Target::Blob),
+    ];
+
+    let mut w = FramedWriter::new(Vec::new());
+    for h in &hashes {
+        w.write(h).unwrap();
+    }
+    let enc = w.into_inner();
+    assert_eq!(enc.len(), 2 * (4 + 52));
+
+    let dec: Vec<Multihash> = FramedReader::new(&enc[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(dec, hashes);
+
+    let mut r = FramedReader::<_, Multihash>::new(&enc[..enc.len() - 1]);
+    assert!(r.next().unwrap().is_ok());
+    assert_matches!(r.next(), Some(Err(DecodeFrameError::Io(_))));
+
+    let mut r = FramedReader::<_, Multihash>::new(&[0, 0, 0, 1, 0x25][..]);
+    assert_matches!(r.next(), Some(Err(DecodeFrameError::Decode(_))));
+}
+
+#[cfg(feature = "multihash")]
+#[test]
+fn test_checksums() {
+    use super::multihash::{Multihash, Target};
+
+    let hashes = vec![
+        Multihash::from_sha256([0; 32], Target::Message),
+        Multihash::from_sha256([1; 32], Target::Blob),
+    ];
+
+    let mut w = FramedWriter::with_checksums(Vec::new());
+    for h in &hashes {
+        w.write(h).unwrap();
+    }
+    let enc = w.into_inner();
+    assert_eq!(enc.len(), 2 * (4 + 52 + 4));
+
+    let dec: Vec<Multihash> = FramedReader::with_checksums(&enc[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(dec, hashes);
+
+    // Flip a bit of the base64 in the second frame, keeping it a valid encoding.
+    let mut damaged = enc.clone();
+    damaged[60 + 10] ^= 0x01;
+    let mut r = FramedReader::<_, Multihash>::with_checksums(&damaged[..]);
+    assert!(r.next().unwrap().is_ok());
+    assert_matches!(r.next(), Some(Err(DecodeFrameError::ChecksumMismatch)));
+    assert!(Multihash::from_legacy(&damaged[64..116]).is_ok());
+
+    let mut r = FramedReader::<_, Multihash>::with_checksums(&enc[..enc.len() - 2]);
+    assert!(r.next().unwrap().is_ok());
+    assert_matches!(r.next(), Some(Err(DecodeFrameError::Io(_))));
+}
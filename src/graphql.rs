@@ -0,0 +1,66 @@
+//! [async-graphql](https://docs.rs/async-graphql) scalars for keys and hashes, so GraphQL APIs
+//! accept and return them as their legacy encodings and reject invalid refs at the boundary.
+use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
+
+use super::multihash::Multihash;
+use super::multikey::Multikey;
+
+/// A `Multikey` scalar, represented as the legacy encoding of the key.
+#[Scalar(name = "Multikey")]
+impl ScalarType for Multikey {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match value {
+            Value::String(ref s) => Multikey::from_legacy_str(s).map_err(InputValueError::custom),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn is_valid(value: &Value) -> bool {
+        match *value {
+            Value::String(ref s) => Multikey::is_valid_legacy(s.as_bytes()),
+            _ => false,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.to_legacy_string())
+    }
+}
+
+/// A `Multihash` scalar, represented as the legacy encoding of the hash.
+#[Scalar(name = "Multihash")]
+impl ScalarType for Multihash {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match value {
+            Value::String(ref s) => Multihash::from_legacy_str(s).map_err(InputValueError::custom),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn is_valid(value: &Value) -> bool {
+        match *value {
+            Value::String(ref s) => Multihash::is_valid_legacy(s.as_bytes()),
+            _ => false,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.to_legacy_string())
+    }
+}
+
+#[test]
+fn test_scalars() {
+    let key = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let value = Value::String(key.to_string());
+    assert!(<Multikey as ScalarType>::is_valid(&value));
+    let parsed = <Multikey as ScalarType>::parse(value.clone()).unwrap();
+    assert_eq!(ScalarType::to_value(&parsed), value);
+
+    let hash = Value::String("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256".to_string());
+    assert!(<Multihash as ScalarType>::parse(hash).is_ok());
+
+    assert!(<Multikey as ScalarType>::parse(Value::String("@abc.ed25519".to_string())).is_err());
+    assert!(<Multihash as ScalarType>::parse(Value::Null).is_err());
+    assert!(!<Multikey as ScalarType>::is_valid(&Value::Boolean(true)));
+}
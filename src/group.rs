@@ -0,0 +1,145 @@
+//! Identifiers of [private groups](https://github.com/ssbc/private-group-spec), as they appear
+//! in `ssb:identity/group/<base64url>` URIs.
+use std::fmt;
+
+use base64;
+use serde::{
+    de::{Deserialize, Deserializer, Error},
+    ser::{Serialize, Serializer},
+};
+
+use super::{decode_base64url_32, Base64UrlError};
+
+/// The scheme and path that precede the group id in a group URI.
+pub const GROUP_URI_PREFIX: &str = "ssb:identity/group/";
+
+/// The 32 byte id of a private group.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct GroupId([u8; 32]);
+
+impl GroupId {
+    /// Wrap the raw group id.
+    pub fn from_bytes(id: [u8; 32]) -> GroupId {
+        GroupId(id)
+    }
+
+    /// The raw group id.
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Parse a `ssb:identity/group/<base64url>` URI. The padding of the url-safe base64 may be
+    /// omitted, and percent-encoded characters (such as `%3D` for the padding) are decoded.
+    pub fn from_uri(s: &str) -> Result<GroupId, DecodeGroupUriError> {
+        let data = s
+            .strip_prefix(GROUP_URI_PREFIX)
+            .ok_or(DecodeGroupUriError::NotAGroupUri)?;
+
+        decode_base64url_32(data)
+            .map(GroupId)
+            .map_err(|err| match err {
+                Base64UrlError::PercentEncoding => DecodeGroupUriError::PercentEncoding,
+                Base64UrlError::InvalidBase64(err) => DecodeGroupUriError::InvalidBase64(err),
+                Base64UrlError::WrongLength => DecodeGroupUriError::WrongLength,
+            })
+    }
+
+    /// The canonical `ssb:identity/group/<base64url>` URI of the group, without padding.
+    pub fn to_uri(&self) -> String {
+        format!(
+            "{}{}",
+            GROUP_URI_PREFIX,
+            base64::encode_config(&self.0, base64::URL_SAFE_NO_PAD)
+        )
+    }
+}
+
+/// Formats as the group URI.
+impl fmt::Display for GroupId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_uri())
+    }
+}
+
+impl Serialize for GroupId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_uri())
+    }
+}
+
+impl<'de> Deserialize<'de> for GroupId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        GroupId::from_uri(&s).map_err(|err| D::Error::custom(format!("Invalid group URI: {}", err)))
+    }
+}
+
+/// Everything that can go wrong when parsing a group URI.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeGroupUriError {
+    /// The input did not start with `ssb:identity/group/`.
+    NotAGroupUri,
+    /// The group id contained a malformed percent-encoded character.
+    PercentEncoding,
+    /// The group id was not valid url-safe base64.
+    InvalidBase64(base64::DecodeError),
+    /// The group id did not encode exactly 32 bytes.
+    WrongLength,
+}
+
+impl fmt::Display for DecodeGroupUriError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeGroupUriError::NotAGroupUri => write!(f, "Not a group URI"),
+            DecodeGroupUriError::PercentEncoding => write!(f, "Invalid percent-encoding"),
+            DecodeGroupUriError::InvalidBase64(ref err) => write!(f, "{}", err),
+            DecodeGroupUriError::WrongLength => write!(f, "Wrong length"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeGroupUriError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeGroupUriError::InvalidBase64(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_group_uri() {
+    let id = GroupId::from_bytes([0xfb; 32]);
+    let uri = id.to_uri();
+    assert_eq!(
+        uri,
+        "ssb:identity/group/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s"
+    );
+    assert_eq!(GroupId::from_uri(&uri).unwrap(), id);
+    assert_eq!(GroupId::from_uri(&format!("{}=", uri)).unwrap(), id);
+    assert_eq!(GroupId::from_uri(&format!("{}%3D", uri)).unwrap(), id);
+    assert_eq!(
+        GroupId::from_uri(&format!("{}%3", uri)),
+        Err(DecodeGroupUriError::PercentEncoding)
+    );
+
+    assert_eq!(
+        GroupId::from_uri("ssb:message/sha256/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s="),
+        Err(DecodeGroupUriError::NotAGroupUri)
+    );
+    assert_eq!(
+        GroupId::from_uri("ssb:identity/group/-_v7"),
+        Err(DecodeGroupUriError::WrongLength)
+    );
+    assert_matches!(
+        GroupId::from_uri("ssb:identity/group/+/v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s="),
+        Err(DecodeGroupUriError::InvalidBase64(_))
+    );
+}
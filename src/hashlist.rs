@@ -0,0 +1,234 @@
+//! A compact binary encoding for large sets of message and blob hashes, such as the replication
+//! state or the "have list" of a peer, which take up megabytes as JSON arrays of legacy
+//! encodings.
+//!
+//! The hashes are sorted, and each one only stores the bytes in which its digest differs from
+//! the digest before it. The encoding is the number of hashes as a big-endian `u32`, followed by
+//! one entry per hash in ascending order. An entry is a header byte followed by the digest
+//! without its first `n` bytes, where `n` is the length of the prefix it shares with the previous
+//! digest. The lower five bits of the header are `n`, the highest bit is set for blobs and the
+//! remaining bits are zero.
+use std::fmt;
+
+use super::multihash::{Multihash, Target};
+
+// The length of a digest.
+const DIGEST_LEN: usize = 32;
+
+// The bit of an entry header that marks blobs, and the bits that hold the shared prefix length.
+const BLOB_BIT: u8 = 0x80;
+const SHARED_MASK: u8 = 0x1f;
+
+/// Encode the given hashes, in any order, deduplicating them.
+pub fn encode_hash_list(hashes: &[Multihash]) -> Vec<u8> {
+    let mut sorted: Vec<&Multihash> = hashes.iter().collect();
+    sorted.sort();
+    sorted.dedup();
+
+    let mut out = Vec::with_capacity(4 + sorted.len() * (1 + DIGEST_LEN));
+    out.extend_from_slice(&(sorted.len() as u32).to_be_bytes());
+
+    let mut prev: &[u8; DIGEST_LEN] = &[0; DIGEST_LEN];
+    for (i, mh) in sorted.into_iter().enumerate() {
+        let digest = mh.as_sha256().unwrap();
+        let shared = match i {
+            0 => 0,
+            // Distinct digests share at most 31 bytes, but a message and a blob may have the
+            // same digest, which is stored as a shared prefix of 31 bytes.
+            _ => prev
+                .iter()
+                .zip(digest.iter())
+                .take_while(|(a, b)| a == b)
+                .count()
+                .min(SHARED_MASK as usize),
+        };
+
+        let blob = if mh.is_blob() { BLOB_BIT } else { 0 };
+        out.push(blob | shared as u8);
+        out.extend_from_slice(&digest[shared..]);
+        prev = digest;
+    }
+    out
+}
+
+/// Decode an encoded list of hashes. The entries must be strictly ascending and use the
+/// longest possible shared prefix, so that every set of hashes has exactly one valid encoding.
+pub fn decode_hash_list(s: &[u8]) -> Result<Vec<Multihash>, DecodeHashListError> {
+    if s.len() < 4 {
+        return Err(DecodeHashListError::UnexpectedEnd);
+    }
+
+    let mut count = [0u8; 4];
+    count.copy_from_slice(&s[..4]);
+    let count = u32::from_be_bytes(count) as usize;
+    let mut data = &s[4..];
+
+    // Every entry takes at least two bytes, so a corrupt count can not trigger a huge
+    // allocation.
+    let mut hashes: Vec<Multihash> = Vec::with_capacity(count.min(data.len() / 2));
+    let mut digest = [0u8; DIGEST_LEN];
+
+    for i in 0..count {
+        let (&header, rest) = data
+            .split_first()
+            .ok_or(DecodeHashListError::UnexpectedEnd)?;
+        if header & !(BLOB_BIT | SHARED_MASK) != 0 || (i == 0 && header & SHARED_MASK != 0) {
+            return Err(DecodeHashListError::InvalidHeader);
+        }
+
+        let shared = (header & SHARED_MASK) as usize;
+        if rest.len() < DIGEST_LEN - shared {
+            return Err(DecodeHashListError::UnexpectedEnd);
+        }
+        let prev = digest;
+        digest[shared..].copy_from_slice(&rest[..DIGEST_LEN - shared]);
+        data = &rest[DIGEST_LEN - shared..];
+
+        // The byte after the shared prefix must differ, unless the prefix is already as long
+        // as it can be.
+        if i > 0 && shared < SHARED_MASK as usize && digest[shared] == prev[shared] {
+            return Err(DecodeHashListError::NotSorted);
+        }
+
+        let target = if header & BLOB_BIT == 0 {
+            Target::Message
+        } else {
+            Target::Blob
+        };
+        let mh = Multihash::from_sha256(digest, target);
+        if let Some(last) = hashes.last() {
+            if *last >= mh {
+                return Err(DecodeHashListError::NotSorted);
+            }
+        }
+        hashes.push(mh);
+    }
+
+    if !data.is_empty() {
+        return Err(DecodeHashListError::TrailingBytes);
+    }
+    Ok(hashes)
+}
+
+/// Everything that can go wrong when decoding a list of hashes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeHashListError {
+    /// The input ended before the announced number of hashes.
+    UnexpectedEnd,
+    /// The input contained more bytes than the announced number of hashes.
+    TrailingBytes,
+    /// An entry header had reserved bits set, or the first entry claimed a shared prefix.
+    InvalidHeader,
+    /// The hashes were not strictly ascending, or an entry did not use the longest shared
+    /// prefix.
+    NotSorted,
+}
+
+impl fmt::Display for DecodeHashListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeHashListError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            DecodeHashListError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeHashListError::InvalidHeader => write!(f, "Invalid entry header"),
+            DecodeHashListError::NotSorted => write!(f, "Hashes not sorted"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeHashListError {}
+
+#[test]
+fn test_hash_list() {
+    let mut a = [0u8; 32];
+    a[31] = 1;
+    let mut b = a;
+    b[30] = 7;
+    let hashes = vec![
+        Multihash::blob_from_digest(a),
+        Multihash::message_from_digest(b),
+        Multihash::message_from_digest(a),
+        Multihash::message_from_digest(b),
+        Multihash::blob_from_digest([0xff; 32]),
+    ];
+
+    let enc = encode_hash_list(&hashes);
+    assert_eq!(&enc[..4], &[0, 0, 0, 4]);
+    // The first message in full, then the second sharing 30 bytes, then the first blob sharing
+    // 30 bytes with it as well, and the last blob sharing nothing.
+    assert_eq!(enc.len(), 4 + 33 + 3 + 3 + 33);
+    assert_eq!(enc[4 + 33], 30);
+    assert_eq!(enc[4 + 33 + 3], BLOB_BIT | 30);
+
+    let mut expected = hashes.clone();
+    expected.sort();
+    expected.dedup();
+    assert_eq!(decode_hash_list(&enc).unwrap(), expected);
+    assert_eq!(
+        decode_hash_list(&encode_hash_list(&[])).unwrap(),
+        Vec::<Multihash>::new()
+    );
+
+    // The same digest as a message and as a blob.
+    let twins = vec![
+        Multihash::message_from_digest(a),
+        Multihash::blob_from_digest(a),
+    ];
+    let enc = encode_hash_list(&twins);
+    assert_eq!(enc.len(), 4 + 33 + 2);
+    assert_eq!(decode_hash_list(&enc).unwrap(), twins);
+}
+
+#[test]
+fn test_hash_list_errors() {
+    let enc = encode_hash_list(&[
+        Multihash::message_from_digest([1; 32]),
+        Multihash::message_from_digest([2; 32]),
+    ]);
+    assert_eq!(
+        decode_hash_list(&enc[..enc.len() - 1]),
+        Err(DecodeHashListError::UnexpectedEnd)
+    );
+    assert_eq!(
+        decode_hash_list(&[0, 0]),
+        Err(DecodeHashListError::UnexpectedEnd)
+    );
+
+    let mut trailing = enc.clone();
+    trailing.push(0);
+    assert_eq!(
+        decode_hash_list(&trailing),
+        Err(DecodeHashListError::TrailingBytes)
+    );
+
+    let mut reserved = enc.clone();
+    reserved[4] = 0x40;
+    assert_eq!(
+        decode_hash_list(&reserved),
+        Err(DecodeHashListError::InvalidHeader)
+    );
+
+    let mut swapped = enc.clone();
+    swapped[5..37].copy_from_slice(&[2; 32]);
+    swapped[38..70].copy_from_slice(&[1; 32]);
+    assert_eq!(
+        decode_hash_list(&swapped),
+        Err(DecodeHashListError::NotSorted)
+    );
+
+    // A shorter shared prefix than possible.
+    let mut short = vec![0, 0, 0, 2, 0];
+    short.extend_from_slice(&[1; 32]);
+    short.push(0);
+    short.extend_from_slice(&[1; 31]);
+    short.push(2);
+    assert_eq!(
+        decode_hash_list(&short),
+        Err(DecodeHashListError::NotSorted)
+    );
+
+    assert_eq!(
+        decode_hash_list(&[0xff, 0xff, 0xff, 0xff]),
+        Err(DecodeHashListError::UnexpectedEnd)
+    );
+}
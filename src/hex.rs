@@ -0,0 +1,77 @@
+//! Hexadecimal representations of the raw bytes of keys, signatures and digests, as shown by
+//! debugging tools, database dumps and other protocols.
+use std::fmt;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+// Lowercase hex encoding of the input.
+pub(crate) fn encode_hex(input: &[u8]) -> String {
+    let mut out = String::with_capacity(input.len() * 2);
+    for &b in input {
+        out.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        out.push(HEX_DIGITS[(b & 0xf) as usize] as char);
+    }
+    out
+}
+
+// Decode hex (in either case) that must encode exactly `out.len()` bytes.
+pub(crate) fn decode_hex(s: &str, out: &mut [u8]) -> Result<(), DecodeHexError> {
+    let s = s.as_bytes();
+    if s.len() != out.len() * 2 {
+        return Err(DecodeHexError::WrongLength);
+    }
+
+    for (i, pair) in s.chunks(2).enumerate() {
+        let high = digit(pair[0]).ok_or(DecodeHexError::InvalidCharacter(i * 2))?;
+        let low = digit(pair[1]).ok_or(DecodeHexError::InvalidCharacter(i * 2 + 1))?;
+        out[i] = (high << 4) | low;
+    }
+    Ok(())
+}
+
+fn digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Everything that can go wrong when decoding raw bytes from hex.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeHexError {
+    /// The input did not encode the expected number of bytes.
+    WrongLength,
+    /// The input contained a character that is not a hex digit, at the given offset.
+    InvalidCharacter(usize),
+}
+
+impl fmt::Display for DecodeHexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeHexError::WrongLength => write!(f, "Wrong length"),
+            DecodeHexError::InvalidCharacter(i) => write!(f, "Invalid hex digit at offset {}", i),
+        }
+    }
+}
+
+impl std::error::Error for DecodeHexError {}
+
+#[test]
+fn test_hex() {
+    assert_eq!(encode_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+
+    let mut out = [0u8; 3];
+    decode_hex("00AB7f", &mut out).unwrap();
+    assert_eq!(out, [0x00, 0xab, 0x7f]);
+
+    assert_eq!(
+        decode_hex("00ab7", &mut out),
+        Err(DecodeHexError::WrongLength)
+    );
+    assert_eq!(
+        decode_hex("00ag7f", &mut out),
+        Err(DecodeHexError::InvalidCharacter(3))
+    );
+    assert_eq!(
+        decode_hex("+1ab7f", &mut out),
+        Err(DecodeHexError::InvalidCharacter(0))
+    );
+}
@@ -0,0 +1,113 @@
+//! Blob references in HTTP request paths, as served by blob gateways.
+//!
+//! Gateways serve blobs under paths like `/blobs/get/&Abc...=.sha256`. Clients usually
+//! percent-encode the `&`, `+`, `/` and `=` of the legacy encoding, so the path segment has to
+//! be decoded before it can be parsed.
+use std::fmt;
+
+use super::multihash::{self, Multihash};
+use super::{percent_decode, percent_encode};
+
+/// The path prefix under which blobs are served.
+pub const BLOB_PATH_PREFIX: &str = "/blobs/get/";
+
+/// Extract the blob hash from a request path of the form `/blobs/get/<blob id>`. The blob id
+/// may be percent-encoded, a query string following it is ignored.
+pub fn parse_blob_path(path: &str) -> Result<Multihash, DecodeBlobPathError> {
+    let rest = path
+        .strip_prefix(BLOB_PATH_PREFIX)
+        .ok_or(DecodeBlobPathError::Prefix)?;
+    let segment = match rest.find('?') {
+        Some(i) => &rest[..i],
+        None => rest,
+    };
+
+    let decoded = percent_decode(segment.as_bytes()).ok_or(DecodeBlobPathError::PercentEncoding)?;
+    let mh = Multihash::from_legacy_exact(&decoded).map_err(DecodeBlobPathError::Decode)?;
+
+    if !mh.is_blob() {
+        return Err(DecodeBlobPathError::NotABlob);
+    }
+    Ok(mh)
+}
+
+/// Build the request path under which the given blob is served, percent-encoding every
+/// character of the legacy encoding that is not unreserved in a URL path.
+pub fn blob_path(mh: &Multihash) -> String {
+    let mut out = String::from(BLOB_PATH_PREFIX);
+    percent_encode(&mh.to_legacy_vec(), &mut out);
+    out
+}
+
+/// Everything that can go wrong when parsing a blob request path.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeBlobPathError {
+    /// The path did not start with `/blobs/get/`.
+    Prefix,
+    /// The blob id contained a malformed percent-encoded character.
+    PercentEncoding,
+    /// The decoded blob id was not exactly a valid legacy encoding of a multihash.
+    Decode(multihash::DecodeLegacyError),
+    /// The decoded multihash was a message hash rather than a blob hash.
+    NotABlob,
+}
+
+impl fmt::Display for DecodeBlobPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeBlobPathError::Prefix => write!(f, "Not a blob path"),
+            DecodeBlobPathError::PercentEncoding => write!(f, "Invalid percent-encoding"),
+            DecodeBlobPathError::Decode(_) => write!(f, "Invalid blob id"),
+            DecodeBlobPathError::NotABlob => write!(f, "Not a blob id"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeBlobPathError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeBlobPathError::Decode(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_blob_path() {
+    let mh = Multihash::from_legacy_exact(b"&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0/8=.sha256")
+        .unwrap();
+
+    let path = blob_path(&mh);
+    assert_eq!(
+        path,
+        "/blobs/get/%26MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc%2B0%2F8%3D.sha256"
+    );
+    assert_eq!(parse_blob_path(&path).unwrap(), mh);
+    assert_eq!(
+        parse_blob_path("/blobs/get/&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0/8=.sha256?size=3")
+            .unwrap(),
+        mh
+    );
+
+    assert_eq!(
+        parse_blob_path("/blob/&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0/8=.sha256"),
+        Err(DecodeBlobPathError::Prefix)
+    );
+    assert_eq!(
+        parse_blob_path("/blobs/get/%2"),
+        Err(DecodeBlobPathError::PercentEncoding)
+    );
+    assert_eq!(
+        parse_blob_path("/blobs/get/%+6Mwjd"),
+        Err(DecodeBlobPathError::PercentEncoding)
+    );
+    assert_eq!(
+        parse_blob_path("/blobs/get/%25MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc%2B0%2F8%3D.sha256"),
+        Err(DecodeBlobPathError::NotABlob)
+    );
+    assert_matches!(
+        parse_blob_path("/blobs/get/&Mwjd.sha256"),
+        Err(DecodeBlobPathError::Decode(_))
+    );
+}
@@ -0,0 +1,373 @@
+//! Invite codes, for apps that accept more than one kind of invite in a single entry point.
+//!
+//! Classic pub invites have the form `<host>:<port>:<feed id>~<seed>`, where the seed is the
+//! base64 encoding of 32 bytes. DHT invites have the form `dht:<channel>:<feed id>`. Room
+//! invites are links like `https://room.example/join?invite=<code>`, or the equivalent
+//! `ssb:experimental?action=claim-http-invite&invite=<code>&postTo=<url>` URI.
+use std::fmt;
+
+use super::multikey::{self, Multikey};
+use super::{fmt_base64, percent_decode, percent_encode};
+
+/// Any of the supported kinds of invite codes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum Invite {
+    /// A classic pub invite.
+    Pub(PubInvite),
+    /// A DHT invite.
+    Dht(DhtInvite),
+    /// A room invite link.
+    Room(RoomInvite),
+}
+
+/// A classic pub invite, `<host>:<port>:<feed id>~<seed>`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PubInvite {
+    /// The host name or IP address of the pub.
+    pub host: String,
+    /// The port of the pub.
+    pub port: u16,
+    /// The feed id of the pub.
+    pub key: Multikey,
+    /// The seed of the key pair that redeems the invite.
+    pub seed: [u8; 32],
+}
+
+/// A DHT invite, `dht:<channel>:<feed id>`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DhtInvite {
+    /// The DHT channel to meet the inviter in.
+    pub channel: String,
+    /// The feed id of the inviter.
+    pub key: Multikey,
+}
+
+/// A room invite link, `https://<host>/join?invite=<code>`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RoomInvite {
+    /// The host name of the room server.
+    pub host: String,
+    /// The invite code to claim at the room server.
+    pub invite: String,
+    /// The feed id of the room server, if the link included it as a `key` parameter.
+    pub key: Option<Multikey>,
+}
+
+/// Parse an invite code of any supported kind.
+pub fn parse_invite(s: &str) -> Result<Invite, DecodeInviteError> {
+    if s.starts_with("dht:") {
+        DhtInvite::parse(s).map(Invite::Dht)
+    } else if s.starts_with("https://") || s.starts_with("ssb:") {
+        RoomInvite::parse(s).map(Invite::Room)
+    } else {
+        PubInvite::parse(s).map(Invite::Pub)
+    }
+}
+
+// Split `<rest>:<feed id>` at the colon before the feed id, which itself contains no colons.
+fn split_key(s: &str) -> Result<(&str, Multikey), DecodeInviteError> {
+    let i = s.rfind(':').ok_or(DecodeInviteError::Malformed)?;
+    let key = Multikey::from_legacy_exact(&s.as_bytes()[i + 1..])
+        .map_err(DecodeInviteError::InvalidKey)?;
+    Ok((&s[..i], key))
+}
+
+impl PubInvite {
+    /// Parse a classic pub invite.
+    pub fn parse(s: &str) -> Result<PubInvite, DecodeInviteError> {
+        let tilde = s.rfind('~').ok_or(DecodeInviteError::Malformed)?;
+        let (address, key) = split_key(&s[..tilde])?;
+
+        let colon = address.rfind(':').ok_or(DecodeInviteError::Malformed)?;
+        let host = &address[..colon];
+        if host.is_empty() {
+            return Err(DecodeInviteError::Malformed);
+        }
+        let port = address[colon + 1..]
+            .parse()
+            .map_err(|_| DecodeInviteError::InvalidPort)?;
+
+        // A seed is 44 characters of base64, which decode to up to 33 bytes without padding.
+        // Check the length first and decode into a buffer with room for all of them, decoding
+        // into a too small buffer would panic.
+        let encoded_seed = &s[tilde + 1..];
+        let mut decoded = [0u8; 33];
+        if encoded_seed.len() != 44 {
+            return Err(DecodeInviteError::InvalidSeed);
+        }
+        match base64::decode_config_slice(encoded_seed, base64::STANDARD, &mut decoded) {
+            Ok(32) => {}
+            _ => return Err(DecodeInviteError::InvalidSeed),
+        }
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&decoded[..32]);
+
+        Ok(PubInvite {
+            host: host.to_string(),
+            port,
+            key,
+            seed,
+        })
+    }
+}
+
+impl DhtInvite {
+    /// Parse a DHT invite.
+    pub fn parse(s: &str) -> Result<DhtInvite, DecodeInviteError> {
+        let rest = s.strip_prefix("dht:").ok_or(DecodeInviteError::Malformed)?;
+        let (channel, key) = split_key(rest)?;
+        if channel.is_empty() {
+            return Err(DecodeInviteError::Malformed);
+        }
+
+        Ok(DhtInvite {
+            channel: channel.to_string(),
+            key,
+        })
+    }
+}
+
+impl RoomInvite {
+    /// Parse a room invite link, either as an https link or as an
+    /// `ssb:experimental?action=claim-http-invite` URI.
+    pub fn parse(s: &str) -> Result<RoomInvite, DecodeInviteError> {
+        let (url, params) = match s.find('?') {
+            Some(i) => (&s[..i], query_params(&s[i + 1..])?),
+            None => return Err(DecodeInviteError::Malformed),
+        };
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+
+        let host = if url == "ssb:experimental" {
+            if param("action") != Some("claim-http-invite") {
+                return Err(DecodeInviteError::Malformed);
+            }
+            url_host(param("postTo").ok_or(DecodeInviteError::Malformed)?)?
+        } else {
+            url_host(url)?
+        };
+
+        let invite = match param("invite") {
+            Some(invite) if !invite.is_empty() => invite.to_string(),
+            _ => return Err(DecodeInviteError::Malformed),
+        };
+
+        let key = match param("key") {
+            Some(key) => Some(
+                Multikey::from_legacy_exact(key.as_bytes())
+                    .map_err(DecodeInviteError::InvalidKey)?,
+            ),
+            None => None,
+        };
+
+        Ok(RoomInvite { host, invite, key })
+    }
+}
+
+// Split a query string into percent-decoded name/value pairs.
+fn query_params(query: &str) -> Result<Vec<(String, String)>, DecodeInviteError> {
+    let decode = |s: &str| {
+        percent_decode(s.as_bytes())
+            .and_then(|b| String::from_utf8(b).ok())
+            .ok_or(DecodeInviteError::Malformed)
+    };
+
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(i) => Ok((decode(&pair[..i])?, decode(&pair[i + 1..])?)),
+            None => Ok((decode(pair)?, String::new())),
+        })
+        .collect()
+}
+
+// The host of an https url.
+fn url_host(url: &str) -> Result<String, DecodeInviteError> {
+    let rest = url
+        .strip_prefix("https://")
+        .ok_or(DecodeInviteError::Malformed)?;
+    let host = match rest.find(['/', '?', '#']) {
+        Some(i) => &rest[..i],
+        None => rest,
+    };
+
+    if host.is_empty() {
+        return Err(DecodeInviteError::Malformed);
+    }
+    Ok(host.to_string())
+}
+
+impl fmt::Display for Invite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Invite::Pub(ref invite) => invite.fmt(f),
+            Invite::Dht(ref invite) => invite.fmt(f),
+            Invite::Room(ref invite) => invite.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for PubInvite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}~", self.host, self.port, self.key)?;
+        fmt_base64(f, &self.seed)
+    }
+}
+
+impl fmt::Display for DhtInvite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dht:{}:{}", self.channel, self.key)
+    }
+}
+
+/// Formats as an https link.
+impl fmt::Display for RoomInvite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut query = String::from("invite=");
+        percent_encode(self.invite.as_bytes(), &mut query);
+        if let Some(ref key) = self.key {
+            query.push_str("&key=");
+            percent_encode(&key.to_legacy_vec(), &mut query);
+        }
+        write!(f, "https://{}/join?{}", self.host, query)
+    }
+}
+
+/// Everything that can go wrong when parsing an invite code.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeInviteError {
+    /// The invite lacked one of its parts, or a part was empty.
+    Malformed,
+    /// The port was not a number between 0 and 65535.
+    InvalidPort,
+    /// The feed id was not exactly a valid legacy encoding.
+    InvalidKey(multikey::DecodeLegacyError),
+    /// The seed was not the base64 encoding of 32 bytes.
+    InvalidSeed,
+}
+
+impl fmt::Display for DecodeInviteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeInviteError::Malformed => write!(f, "Malformed invite"),
+            DecodeInviteError::InvalidPort => write!(f, "Invalid port"),
+            DecodeInviteError::InvalidKey(_) => write!(f, "Invalid feed id"),
+            DecodeInviteError::InvalidSeed => write!(f, "Invalid seed"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeInviteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeInviteError::InvalidKey(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_parse_invite() {
+    let key = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let mk = Multikey::from_legacy_exact(key.as_bytes()).unwrap();
+
+    let pub_code = format!("pub.example.com:8008:{}~{}", key, base64::encode(&[7; 32]));
+    match parse_invite(&pub_code).unwrap() {
+        Invite::Pub(invite) => {
+            assert_eq!(invite.host, "pub.example.com");
+            assert_eq!(invite.port, 8008);
+            assert_eq!(invite.key, mk);
+            assert_eq!(invite.seed, [7; 32]);
+            assert_eq!(invite.to_string(), pub_code);
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let dht_code = format!("dht:c0ffee:{}", key);
+    assert_eq!(
+        parse_invite(&dht_code).unwrap(),
+        Invite::Dht(DhtInvite {
+            channel: "c0ffee".to_string(),
+            key: mk,
+        })
+    );
+    assert_eq!(parse_invite(&dht_code).unwrap().to_string(), dht_code);
+
+    assert_eq!(
+        parse_invite(&format!("dht::{}", key)),
+        Err(DecodeInviteError::Malformed)
+    );
+    assert_eq!(
+        parse_invite(&format!("host:80800:{}~{}", key, base64::encode(&[7; 32]))),
+        Err(DecodeInviteError::InvalidPort)
+    );
+    assert_eq!(
+        parse_invite(&format!("host:8008:{}~AAAA", key)),
+        Err(DecodeInviteError::InvalidSeed)
+    );
+    assert_eq!(
+        parse_invite(&format!("host:8008:{}~{}", key, base64::encode(&[7; 48]))),
+        Err(DecodeInviteError::InvalidSeed)
+    );
+    // Unpadded, these decode to 33 bytes.
+    assert_eq!(
+        parse_invite(&format!("host:1:{}~{}", key, "A".repeat(44))),
+        Err(DecodeInviteError::InvalidSeed)
+    );
+    assert_matches!(
+        parse_invite("dht:c0ffee:@abc.ed25519"),
+        Err(DecodeInviteError::InvalidKey(_))
+    );
+}
+
+#[test]
+fn test_parse_room_invite() {
+    let expected = RoomInvite {
+        host: "room.example".to_string(),
+        invite: "39c0ac1850ec9af14f1bb73".to_string(),
+        key: None,
+    };
+
+    let link = "https://room.example/join?invite=39c0ac1850ec9af14f1bb73";
+    assert_eq!(parse_invite(link).unwrap(), Invite::Room(expected.clone()));
+    assert_eq!(expected.to_string(), link);
+    assert_eq!(
+        RoomInvite::parse(
+            "ssb:experimental?action=claim-http-invite&invite=39c0ac1850ec9af14f1bb73\
+             &postTo=https%3A%2F%2Froom.example%2Finvite%2Fconsume"
+        )
+        .unwrap(),
+        expected
+    );
+
+    let key = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let with_key = RoomInvite {
+        key: Some(Multikey::from_legacy_exact(key.as_bytes()).unwrap()),
+        ..expected
+    };
+    assert_eq!(RoomInvite::parse(&with_key.to_string()).unwrap(), with_key);
+
+    assert_eq!(
+        RoomInvite::parse("https://room.example/join"),
+        Err(DecodeInviteError::Malformed)
+    );
+    assert_eq!(
+        RoomInvite::parse("https:///join?invite=abc"),
+        Err(DecodeInviteError::Malformed)
+    );
+    assert_eq!(
+        RoomInvite::parse("ssb:experimental?action=consume-alias&invite=abc&postTo=https://a"),
+        Err(DecodeInviteError::Malformed)
+    );
+    assert_matches!(
+        RoomInvite::parse("https://room.example/join?invite=abc&key=%40abc"),
+        Err(DecodeInviteError::InvalidKey(_))
+    );
+}
@@ -0,0 +1,436 @@
+//! Parse legacy encodings directly out of JSON text, and write them into it.
+//!
+//! In legacy messages, multiformats appear as JSON strings. These may legally contain escape
+//! sequences (`"\u0040..."` is a perfectly valid way of writing a feed id), so the raw bytes
+//! between the quotes are not necessarily the legacy encoding itself.
+//!
+//! With the `serde_json` feature, values can also be moved in and out of `serde_json::Value`s.
+use std::borrow::Cow;
+use std::char;
+use std::fmt;
+use std::io::{self, Write};
+
+#[cfg(feature = "serde_json")]
+use serde_json::Value;
+
+use super::{encode_legacy, LegacyEncoding};
+
+/// Parse a complete JSON string token, starting with its opening quote, from the start of the
+/// input. Its unescaped contents must be exactly the legacy encoding of a `T`. Returns the value
+/// and the input after the closing quote.
+pub fn parse_json_string<T: LegacyEncoding>(
+    s: &[u8],
+) -> Result<(T, &[u8]), DecodeJsonError<T::Error>> {
+    let (contents, tail) = unescape(s)?;
+    let value = T::from_legacy_exact(&contents).map_err(DecodeJsonError::Decode)?;
+    Ok((value, tail))
+}
+
+/// Convert a value into a `serde_json::Value` string holding its legacy encoding.
+#[cfg(feature = "serde_json")]
+pub fn to_json_value<T: LegacyEncoding>(value: &T) -> Value {
+    // Legacy encodings are always ASCII.
+    Value::String(String::from_utf8(encode_legacy(value)).unwrap())
+}
+
+/// Convert a `serde_json::Value` back into a value, it must be a string that is exactly a valid
+/// legacy encoding of a `T`.
+#[cfg(feature = "serde_json")]
+pub fn from_json_value<T: LegacyEncoding>(
+    value: &Value,
+) -> Result<T, DecodeJsonValueError<T::Error>> {
+    match *value {
+        Value::String(ref s) => {
+            T::from_legacy_exact(s.as_bytes()).map_err(DecodeJsonValueError::Decode)
+        }
+        _ => Err(DecodeJsonValueError::NotAString),
+    }
+}
+
+/// Convert a `serde_json::Value` array of refs (e.g. the `recps` or `mentions` of a message)
+/// into values. Each element must either be a string holding exactly a valid legacy encoding of
+/// a `T`, or an object whose `link` field is such a string. An error reports the index of the
+/// first invalid element.
+#[cfg(feature = "serde_json")]
+pub fn from_json_array<T: LegacyEncoding>(
+    value: &Value,
+) -> Result<Vec<T>, DecodeJsonArrayError<T::Error>> {
+    let elements = value.as_array().ok_or(DecodeJsonArrayError::NotAnArray)?;
+
+    elements
+        .iter()
+        .enumerate()
+        .map(|(i, element)| {
+            let link = match *element {
+                Value::Object(ref object) => object.get("link").unwrap_or(&Value::Null),
+                _ => element,
+            };
+            from_json_value(link).map_err(|err| DecodeJsonArrayError::Element(i, err))
+        })
+        .collect()
+}
+
+/// Write the legacy encoding of a value as a complete JSON string token, including both
+/// quotes, escaped exactly like `JSON.stringify` escapes strings in the
+/// [legacy message encoding](https://spec.scuttlebutt.nz/messages.html#legacy-json-encoding).
+///
+/// Signing encoders can splice the output into canonical JSON byte for byte, and
+/// `parse_json_string` reads it back.
+pub fn write_json_string<T: LegacyEncoding, W: Write>(value: &T, w: &mut W) -> io::Result<()> {
+    w.write_all(b"\"")?;
+    escape(&encode_legacy(value), w)?;
+    w.write_all(b"\"")
+}
+
+// Write the input with the escape sequences `JSON.stringify` uses: the short forms where they
+// exist, `\u00xx` (lowercase hex) for all other control characters, everything else verbatim.
+fn escape<W: Write>(s: &[u8], w: &mut W) -> io::Result<()> {
+    let mut start = 0;
+
+    for (i, &b) in s.iter().enumerate() {
+        let short: Option<&[u8]> = match b {
+            b'"' => Some(b"\\\""),
+            b'\\' => Some(b"\\\\"),
+            0x08 => Some(b"\\b"),
+            0x0C => Some(b"\\f"),
+            b'\n' => Some(b"\\n"),
+            b'\r' => Some(b"\\r"),
+            b'\t' => Some(b"\\t"),
+            0x00..=0x1F => None,
+            _ => continue,
+        };
+
+        w.write_all(&s[start..i])?;
+        match short {
+            Some(short) => w.write_all(short)?,
+            None => write!(w, "\\u{:04x}", b)?,
+        }
+        start = i + 1;
+    }
+
+    w.write_all(&s[start..])
+}
+
+// The unescaped contents of a JSON string token, and the input following the token.
+type Unescaped<'a> = (Cow<'a, [u8]>, &'a [u8]);
+
+// Split a JSON string token off the start of the input, and unescape its contents. The contents
+// are only copied if they contain escape sequences.
+fn unescape<E>(s: &[u8]) -> Result<Unescaped<'_>, DecodeJsonError<E>> {
+    if s.first() != Some(&b'"') {
+        return Err(DecodeJsonError::NoString);
+    }
+
+    let mut out: Option<Vec<u8>> = None;
+    let mut i = 1;
+
+    loop {
+        match s.get(i) {
+            None => return Err(DecodeJsonError::Unterminated),
+            Some(&b'"') => {
+                let contents = match out {
+                    Some(out) => Cow::Owned(out),
+                    None => Cow::Borrowed(&s[1..i]),
+                };
+                return Ok((contents, &s[i + 1..]));
+            }
+            Some(&b'\\') => {
+                let out = out.get_or_insert_with(|| s[1..i].to_vec());
+                let (c, len) =
+                    unescape_sequence(&s[i + 1..]).ok_or(DecodeJsonError::InvalidEscape)?;
+                let mut utf8 = [0u8; 4];
+                out.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                i += 1 + len;
+            }
+            Some(&b) if b < 0x20 => return Err(DecodeJsonError::ControlCharacter),
+            Some(&b) => {
+                if let Some(ref mut out) = out {
+                    out.push(b);
+                }
+                i += 1;
+            }
+        }
+    }
+}
+
+// Decode the escape sequence following a backslash, returning the character and the number of
+// bytes after the backslash that the sequence took up.
+fn unescape_sequence(s: &[u8]) -> Option<(char, usize)> {
+    let c = match *s.first()? {
+        b'"' => '"',
+        b'\\' => '\\',
+        b'/' => '/',
+        b'b' => '\u{8}',
+        b'f' => '\u{c}',
+        b'n' => '\n',
+        b'r' => '\r',
+        b't' => '\t',
+        b'u' => {
+            let high = hex4(s.get(1..5)?)?;
+
+            if !(0xD800..0xDC00).contains(&high) {
+                return char::from_u32(high).map(|c| (c, 5));
+            }
+
+            // A high surrogate, which must be followed by an escaped low surrogate.
+            if s.get(5..7)? != b"\\u" {
+                return None;
+            }
+            let low = hex4(s.get(7..11)?)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return None;
+            }
+
+            return char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                .map(|c| (c, 11));
+        }
+        _ => return None,
+    };
+
+    Some((c, 1))
+}
+
+// Parse exactly four hex digits.
+fn hex4(s: &[u8]) -> Option<u32> {
+    s.iter()
+        .try_fold(0, |acc, &b| Some((acc << 4) | (b as char).to_digit(16)?))
+}
+
+/// Everything that can go wrong when parsing a legacy encoding out of a JSON string.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeJsonError<E> {
+    /// The input did not start with a `"`.
+    NoString,
+    /// The input ended before the closing `"`.
+    Unterminated,
+    /// The string contained an invalid escape sequence, or an escaped lone surrogate.
+    InvalidEscape,
+    /// The string contained a control character that was not escaped.
+    ControlCharacter,
+    /// The unescaped string was not exactly a valid legacy encoding.
+    Decode(E),
+}
+
+impl<E: fmt::Display> fmt::Display for DecodeJsonError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeJsonError::NoString => write!(f, "No string"),
+            DecodeJsonError::Unterminated => write!(f, "Unterminated string"),
+            DecodeJsonError::InvalidEscape => write!(f, "Invalid escape sequence"),
+            DecodeJsonError::ControlCharacter => write!(f, "Unescaped control character"),
+            DecodeJsonError::Decode(_) => write!(f, "Invalid value in string"),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for DecodeJsonError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeJsonError::Decode(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that can go wrong when converting a `serde_json::Value` into a value.
+#[cfg(feature = "serde_json")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeJsonValueError<E> {
+    /// The JSON value was not a string.
+    NotAString,
+    /// The string was not exactly a valid legacy encoding.
+    Decode(E),
+}
+
+#[cfg(feature = "serde_json")]
+impl<E: fmt::Display> fmt::Display for DecodeJsonValueError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeJsonValueError::NotAString => write!(f, "Not a string"),
+            DecodeJsonValueError::Decode(_) => write!(f, "Invalid value in string"),
+        }
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<E: std::error::Error + 'static> std::error::Error for DecodeJsonValueError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeJsonValueError::Decode(ref err) => Some(err),
+            DecodeJsonValueError::NotAString => None,
+        }
+    }
+}
+
+/// Everything that can go wrong when converting a `serde_json::Value` array into values.
+#[cfg(feature = "serde_json")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeJsonArrayError<E> {
+    /// The JSON value was not an array.
+    NotAnArray,
+    /// The element at the given index was invalid.
+    Element(usize, DecodeJsonValueError<E>),
+}
+
+#[cfg(feature = "serde_json")]
+impl<E: fmt::Display> fmt::Display for DecodeJsonArrayError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeJsonArrayError::NotAnArray => write!(f, "Not an array"),
+            DecodeJsonArrayError::Element(i, _) => write!(f, "Invalid element at index {}", i),
+        }
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<E: std::error::Error + 'static> std::error::Error for DecodeJsonArrayError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeJsonArrayError::Element(_, ref err) => Some(err),
+            DecodeJsonArrayError::NotAnArray => None,
+        }
+    }
+}
+
+#[cfg(feature = "multikey")]
+#[test]
+fn test_parse_json_string() {
+    use super::multikey::{self, Multikey};
+
+    let expected =
+        Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+            .unwrap();
+
+    assert_eq!(
+        parse_json_string::<Multikey>(
+            b"\"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\",\"@"
+        )
+        .unwrap(),
+        (expected.clone(), &b",\"@"[..])
+    );
+    assert_eq!(
+        parse_json_string::<Multikey>(
+            b"\"\\u0040zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA\\u003d\\u002eed25519\""
+        )
+        .unwrap(),
+        (expected, &[][..])
+    );
+
+    assert_eq!(
+        parse_json_string::<Multikey>(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"),
+        Err(DecodeJsonError::NoString)
+    );
+    assert_eq!(
+        parse_json_string::<Multikey>(b"\"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"),
+        Err(DecodeJsonError::Unterminated)
+    );
+    assert_eq!(
+        parse_json_string::<Multikey>(b"\"\\u00zz\""),
+        Err(DecodeJsonError::InvalidEscape)
+    );
+    assert_eq!(
+        parse_json_string::<Multikey>(b"\"\\ud800\\u0040\""),
+        Err(DecodeJsonError::InvalidEscape)
+    );
+    assert_eq!(
+        parse_json_string::<Multikey>(b"\"@\n\""),
+        Err(DecodeJsonError::ControlCharacter)
+    );
+    assert_eq!(
+        parse_json_string::<Multikey>(
+            b"\"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\\n\""
+        ),
+        Err(DecodeJsonError::Decode(
+            multikey::DecodeLegacyError::TrailingBytes
+        ))
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[cfg(feature = "multihash")]
+#[test]
+fn test_json_value() {
+    use super::multihash::{self, Multihash};
+
+    let enc = "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256";
+    let mh = Multihash::from_legacy_exact(enc.as_bytes()).unwrap();
+
+    assert_eq!(to_json_value(&mh), Value::String(enc.to_string()));
+    assert_eq!(
+        from_json_value::<Multihash>(&to_json_value(&mh)).unwrap(),
+        mh
+    );
+    assert_eq!(
+        from_json_value::<Multihash>(&Value::Null),
+        Err(DecodeJsonValueError::NotAString)
+    );
+    assert_eq!(
+        from_json_value::<Multihash>(&Value::String(format!("{} ", enc))),
+        Err(DecodeJsonValueError::Decode(
+            multihash::DecodeLegacyError::TrailingBytes
+        ))
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[cfg(feature = "multikey")]
+#[test]
+fn test_json_array() {
+    use super::multifeed::Multifeed;
+
+    let a = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let b = "@AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519";
+    let mut link = serde_json::Map::new();
+    link.insert("link".to_string(), Value::String(b.to_string()));
+    link.insert("name".to_string(), Value::String("bob".to_string()));
+
+    let feeds = from_json_array::<Multifeed>(&Value::Array(vec![
+        Value::String(a.to_string()),
+        Value::Object(link),
+    ]))
+    .unwrap();
+    assert_eq!(feeds.len(), 2);
+    assert_eq!(feeds[1].to_legacy_string(), b);
+
+    assert_eq!(
+        from_json_array::<Multifeed>(&Value::Array(vec![
+            Value::String(a.to_string()),
+            Value::Bool(true),
+        ])),
+        Err(DecodeJsonArrayError::Element(
+            1,
+            DecodeJsonValueError::NotAString
+        ))
+    );
+    assert_eq!(
+        from_json_array::<Multifeed>(&Value::String(a.to_string())),
+        Err(DecodeJsonArrayError::NotAnArray)
+    );
+}
+
+#[cfg(feature = "multikey")]
+#[test]
+fn test_write_json_string() {
+    use super::multikey::Multikey;
+
+    let key =
+        Multikey::from_legacy_str("@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519").unwrap();
+    let mut out = Vec::new();
+    write_json_string(&key, &mut out).unwrap();
+    assert_eq!(
+        out,
+        &b"\"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\""[..]
+    );
+    out.extend_from_slice(b",");
+    assert_eq!(
+        parse_json_string::<Multikey>(&out).unwrap(),
+        (key, &b","[..])
+    );
+
+    let mut out = Vec::new();
+    escape(b"a\"b\\c\n\x01\x7f", &mut out).unwrap();
+    assert_eq!(out, &b"a\\\"b\\\\c\\n\\u0001\x7f"[..]);
+}
@@ -0,0 +1,90 @@
+//! Conversions between the types of this crate and the key, signature and digest types of
+//! [kuska-ssb](https://docs.rs/kuska-ssb), so that projects can adopt this crate gradually.
+//!
+//! kuska-ssb represents feed ids and message and blob hashes as strings (`SsbId` and
+//! `SsbHash`), which the `from_legacy_str` functions parse. A bare `sha256::Digest` does not say
+//! whether it is the hash of a message or of a blob, so a `Multihash` is created from one with
+//! `Multihash::from_sha256` and the intended `Target`.
+use kuska_ssb::crypto::ed25519;
+#[cfg(feature = "multihash")]
+use kuska_ssb::crypto::sha256;
+
+#[cfg(feature = "multihash")]
+use super::multihash::Multihash;
+#[cfg(feature = "multisig")]
+use super::multikey::Multisig;
+use super::multikey::{Multikey, Multisecret};
+
+impl From<&ed25519::PublicKey> for Multikey {
+    fn from(pk: &ed25519::PublicKey) -> Multikey {
+        Multikey::from_ed25519(&pk.0)
+    }
+}
+
+impl From<&Multikey> for ed25519::PublicKey {
+    fn from(mk: &Multikey) -> ed25519::PublicKey {
+        ed25519::PublicKey::from_slice(mk.as_ed25519().unwrap()).unwrap()
+    }
+}
+
+impl From<&ed25519::SecretKey> for Multisecret {
+    fn from(sk: &ed25519::SecretKey) -> Multisecret {
+        Multisecret::from_ed25519(&sk.0)
+    }
+}
+
+impl From<&Multisecret> for ed25519::SecretKey {
+    fn from(secret: &Multisecret) -> ed25519::SecretKey {
+        ed25519::SecretKey::from_slice(secret.as_ed25519().unwrap()).unwrap()
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl From<&ed25519::Signature> for Multisig {
+    fn from(sig: &ed25519::Signature) -> Multisig {
+        Multisig::from_ed25519(&sig.0)
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl From<&Multisig> for ed25519::Signature {
+    fn from(sig: &Multisig) -> ed25519::Signature {
+        ed25519::Signature::from_slice(sig.as_ed25519().unwrap()).unwrap()
+    }
+}
+
+#[cfg(feature = "multihash")]
+impl From<&Multihash> for sha256::Digest {
+    fn from(mh: &Multihash) -> sha256::Digest {
+        sha256::Digest(*mh.as_sha256().unwrap())
+    }
+}
+
+#[test]
+fn test_kuska_conversions() {
+    use kuska_ssb::crypto::ToSsbId;
+
+    let (pk, sk) = ed25519::gen_keypair();
+    let mk = Multikey::from(&pk);
+    assert_eq!(format!("@{}", pk.to_ssb_id()), mk.to_legacy_string());
+    assert_eq!(ed25519::PublicKey::from(&mk), pk);
+
+    let secret = Multisecret::from(&sk);
+    assert_eq!(ed25519::SecretKey::from(&secret), sk);
+
+    #[cfg(feature = "multisig")]
+    {
+        let sig = ed25519::sign_detached(b"hello", &sk);
+        let ms = Multisig::from(&sig);
+        assert!(mk.is_signature_correct(b"hello", &ms));
+        assert_eq!(ed25519::Signature::from(&ms), sig);
+    }
+
+    #[cfg(feature = "multihash")]
+    {
+        let digest = sha256::hash(b"hello");
+        let mh = Multihash::from_sha256(digest.0, super::multihash::Target::Message);
+        assert_eq!(format!("%{}", digest.to_ssb_id()), mh.to_legacy_string());
+        assert_eq!(sha256::Digest::from(&mh), digest);
+    }
+}
@@ -1,40 +1,1267 @@
 //! Implementations of the [ssb multiformats](https://spec.scuttlebutt.nz/datatypes.html).
+//!
+//! Each multiformat has a cargo feature of the same name (`multikey`, `multihash`, `multibox`
+//! and `multisig`), and the `uri` feature adds the `ssb:` URIs, all enabled by default. Building
+//! with `default-features = false` and only the needed ones keeps e.g. a validator of message ids
+//! free of the box and signature code. The features of the integrations with other crates enable
+//! the multiformats they need.
+//!
+//! Encoding never allocates, except where an owned value is returned, as by `to_legacy_vec`,
+//! `to_legacy_string` and `short`. Keys, hashes and signatures also encode into fixed-size arrays
+//! with `to_legacy_array`, and everything encodes into a byte slice with `to_legacy` or into a
+//! `fmt::Write` with `fmt_legacy`. Decoding bytes with the strict default options only allocates
+//! for the cyphertext of a multibox that does not fit inline.
 // #![warn(missing_docs)]
 
 extern crate base64;
 extern crate serde;
 
-#[cfg(test)]
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+use engine::{Base64Engine, DefaultEngine};
+#[cfg(any(feature = "multikey", feature = "multihash"))]
+use parse::base64_decoded_len;
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+use parse::{normalize_base64, skip_prefix, skip_suffix, split_at_byte};
+
+#[cfg(all(
+    feature = "bytes",
+    any(feature = "multikey", feature = "multihash", feature = "multibox")
+))]
+use bytes::{Buf, BufMut};
+#[cfg(feature = "async-hash")]
+use futures::io::{AsyncRead, AsyncReadExt};
+#[cfg(all(
+    feature = "futures",
+    any(feature = "multikey", feature = "multihash", feature = "multibox")
+))]
+use futures::io::{AsyncWrite, AsyncWriteExt};
+#[cfg(all(
+    feature = "test-util",
+    any(feature = "multikey", feature = "multihash")
+))]
+use rand_core::Rng;
+
+#[cfg(all(
+    test,
+    any(feature = "multikey", feature = "multihash", feature = "multibox")
+))]
 #[macro_use]
 extern crate matches;
 
+#[cfg(feature = "clap")]
+pub mod args;
+#[cfg(feature = "multikey")]
+pub mod base32;
+#[cfg(feature = "rayon")]
+pub mod batch;
+#[cfg(any(
+    feature = "node",
+    feature = "pyo3",
+    feature = "uniffi",
+    feature = "wasm-bindgen"
+))]
+mod bindings;
+#[cfg(feature = "multihash")]
+pub mod blobs;
+#[cfg(feature = "bloom")]
+pub mod bloom;
+#[cfg(feature = "miette")]
+pub mod diagnostic;
+pub mod engine;
+#[cfg(feature = "multikey")]
+pub mod feedidx;
+#[cfg(feature = "multikey")]
+pub mod feedset;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod framed;
+#[cfg(feature = "async-graphql")]
+pub mod graphql;
+#[cfg(feature = "uri")]
+pub mod group;
+#[cfg(feature = "multihash")]
+pub mod hashlist;
+#[cfg(any(feature = "multikey", feature = "multihash"))]
+pub mod hex;
+#[cfg(feature = "multihash")]
+pub mod http;
+#[cfg(feature = "multikey")]
+pub mod invite;
+pub mod json;
+#[cfg(feature = "kuska-ssb")]
+pub mod kuska;
+pub mod lines;
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+pub mod link;
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+#[cfg(feature = "multibox")]
 pub mod multibox;
+#[cfg(feature = "multikey")]
 pub mod multifeed;
+#[cfg(feature = "multihash")]
 pub mod multihash;
+#[cfg(feature = "multikey")]
 pub mod multikey;
+#[cfg(feature = "multikey")]
+pub mod multiserver;
+#[cfg(feature = "node")]
+pub mod node;
+#[cfg(feature = "multikey")]
+pub mod openssh;
+pub mod parse;
+#[cfg(feature = "pem")]
+pub mod pem;
+#[cfg(feature = "pyo3")]
+pub mod python;
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+pub mod scan;
+pub mod suggest;
+#[cfg(feature = "uri")]
+pub mod uri;
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+/// A type that has a [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html), for code
+/// that wants to be generic over the different multiformats.
+pub trait LegacyEncoding: Sized {
+    /// Everything that can go wrong when decoding a value of this type.
+    type Error;
+
+    /// Parses a legacy encoding into a value, also returning the remaining input on success.
+    fn from_legacy(s: &[u8]) -> Result<(Self, &[u8]), Self::Error>;
+
+    /// Parses a legacy encoding into a value like `from_legacy`, but tolerating the deviations
+    /// from the canonical encoding that the options allow.
+    fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Self, &'a [u8]), Self::Error>;
+
+    /// Parses a legacy encoding into a value, failing if the input contains anything after the
+    /// encoding.
+    fn from_legacy_exact(s: &[u8]) -> Result<Self, Self::Error>;
+
+    /// Parses a legacy encoding that makes up the whole string into a value.
+    fn from_legacy_str(s: &str) -> Result<Self, Self::Error> {
+        Self::from_legacy_exact(s.as_bytes())
+    }
+
+    /// Parses a legacy encoding into a value, also returning the remaining input on success. On
+    /// failure, the error reports the offset in the input at which decoding failed.
+    fn from_legacy_located(s: &[u8]) -> Result<(Self, &[u8]), DecodeError<Self::Error>>;
+
+    /// Parses a legacy encoding into a value, also returning the number of bytes of the input
+    /// that the encoding took up.
+    fn from_legacy_counted(s: &[u8]) -> Result<(Self, usize), Self::Error> {
+        Self::from_legacy(s).map(|(value, tail)| (value, s.len() - tail.len()))
+    }
+
+    /// Serialize a value into a writer, using the legacy encoding.
+    fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error>;
+}
+
+/// An error that occured while decoding, together with the position in the input at which it
+/// occured.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DecodeError<K> {
+    /// What went wrong.
+    pub kind: K,
+    /// The offset in the input at which decoding failed.
+    pub offset: usize,
+}
+
+impl<K: fmt::Display> fmt::Display for DecodeError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)
+    }
+}
+
+impl<K: std::error::Error> std::error::Error for DecodeError<K> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+/// Options for how much legacy decoding via the `from_legacy_with` functions tolerates.
+///
+/// The default options are the strict ones, accepting exactly what `from_legacy` accepts. Much
+/// historical data was produced by implementations that were less careful, so consumers of old
+/// feeds may want to relax them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseOptions {
+    /// Accept base64 with missing or superfluous padding, and base64 whose unused trailing bits
+    /// are not zero.
+    pub lenient_base64: bool,
+    /// Accept the url-safe base64 alphabet (`-` and `_` in place of `+` and `/`) as well.
+    pub url_safe_alphabet: bool,
+    /// Match suffixes such as `.ed25519` or `.box` without regard to ASCII case.
+    pub case_insensitive_suffix: bool,
+    /// Reject encodings whose base64 portion is longer than this many bytes, before decoding
+    /// any of it. Defaults to `DEFAULT_MAX_LEN`, `None` disables the limit.
+    pub max_len: Option<usize>,
+}
+
+/// The default limit on the length of the base64 portion of a legacy encoding, in bytes.
+///
+/// Keys, signatures and hashes are far shorter, so this only ever affects multiboxes. It is
+/// much larger than any box that fits into a message, but still bounds the work an attacker
+/// can cause per field.
+pub const DEFAULT_MAX_LEN: usize = 1 << 20;
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            lenient_base64: false,
+            url_safe_alphabet: false,
+            case_insensitive_suffix: false,
+            max_len: Some(DEFAULT_MAX_LEN),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The strict options, the same as `ParseOptions::default()`.
+    pub fn strict() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Options that tolerate every supported deviation from the canonical encoding, keeping
+    /// the default length limit.
+    pub fn lenient() -> ParseOptions {
+        ParseOptions {
+            lenient_base64: true,
+            url_safe_alphabet: true,
+            case_insensitive_suffix: true,
+            max_len: Some(DEFAULT_MAX_LEN),
+        }
+    }
+}
+
+/// A deviation from the canonical legacy encoding that relaxed `ParseOptions` tolerated, as
+/// reported by [`decode_legacy_with_deviations`](fn.decode_legacy_with_deviations.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Deviation {
+    /// The base64 had missing or superfluous padding, or nonzero unused trailing bits.
+    NoncanonicBase64,
+    /// The base64 used the url-safe alphabet.
+    UrlSafeAlphabet,
+    /// The suffix did not have the canonical ASCII case.
+    SuffixCase,
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Deviation::NoncanonicBase64 => write!(f, "Noncanonic base64"),
+            Deviation::UrlSafeAlphabet => write!(f, "Url-safe base64 alphabet"),
+            Deviation::SuffixCase => write!(f, "Suffix case"),
+        }
+    }
+}
+
+/// Parse a legacy encoding of a value of type `T` from the start of the input like
+/// `T::from_legacy_with`, also appending the deviations the input actually relied on to
+/// `deviations`. Appending nothing means the encoding was canonical.
+///
+/// This is meant for migration and linting tools that want to log or repair non-canonical
+/// data rather than silently normalize it. It parses the input once more for every deviation
+/// the options allow, so plain decoding should use `from_legacy_with`.
+pub fn decode_legacy_with_deviations<'a, T: LegacyEncoding>(
+    s: &'a [u8],
+    opts: &ParseOptions,
+    deviations: &mut Vec<Deviation>,
+) -> Result<(T, &'a [u8]), T::Error> {
+    let (value, tail) = T::from_legacy_with(s, opts)?;
+
+    // A deviation was relied on if parsing without tolerating it fails, or parses something
+    // else.
+    let needs = |stricter: ParseOptions| match T::from_legacy_with(s, &stricter) {
+        Ok((_, stricter_tail)) => stricter_tail.len() != tail.len(),
+        Err(_) => true,
+    };
+
+    if opts.lenient_base64 {
+        let stricter = ParseOptions {
+            lenient_base64: false,
+            ..*opts
+        };
+        if needs(stricter) {
+            deviations.push(Deviation::NoncanonicBase64);
+        }
+    }
+    if opts.url_safe_alphabet {
+        let stricter = ParseOptions {
+            url_safe_alphabet: false,
+            ..*opts
+        };
+        if needs(stricter) {
+            deviations.push(Deviation::UrlSafeAlphabet);
+        }
+    }
+    if opts.case_insensitive_suffix {
+        let stricter = ParseOptions {
+            case_insensitive_suffix: false,
+            ..*opts
+        };
+        if needs(stricter) {
+            deviations.push(Deviation::SuffixCase);
+        }
+    }
+
+    Ok((value, tail))
+}
+
+/// Parse a legacy encoding of a value of type `T` from the start of the input, also returning
+/// the remaining input. Useful where the type to decode is only known as a type parameter, e.g.
+/// in deserializers: `decode_legacy::<Multihash>(field)`.
+pub fn decode_legacy<T: LegacyEncoding>(s: &[u8]) -> Result<(T, &[u8]), T::Error> {
+    T::from_legacy(s)
+}
+
+/// Parse a legacy encoding of a value of type `T` that makes up the whole input.
+pub fn decode_legacy_exact<T: LegacyEncoding>(s: &[u8]) -> Result<T, T::Error> {
+    T::from_legacy_exact(s)
+}
+
+/// Serialize a value into an owned byte vector, using the legacy encoding.
+pub fn encode_legacy<T: LegacyEncoding>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.to_legacy(&mut out).unwrap();
+    out
+}
+
+/// Return whether the input is exactly the canonical legacy encoding of a value of type `T`.
+///
+/// Encodings that can only be parsed with relaxed `ParseOptions` are not canonical. Message
+/// hashes and signatures are computed over the exact bytes, so validators must not treat them
+/// as equivalent.
+pub fn is_canonical<T: LegacyEncoding>(s: &[u8]) -> bool {
+    T::from_legacy_exact(s).is_ok()
+}
+
+/// Parse a legacy encoding of a value of type `T` from the start of the input as leniently as
+/// `ParseOptions::lenient()` allows, returning the canonical legacy encoding of that value and
+/// the remaining input.
+pub fn canonicalize<T: LegacyEncoding>(s: &[u8]) -> Result<(Vec<u8>, &[u8]), T::Error> {
+    let (value, tail) = T::from_legacy_with(s, &ParseOptions::lenient())?;
+    Ok((encode_legacy(&value), tail))
+}
+
+/// Return whether the inputs are both exactly legacy encodings of the same value of type `T`,
+/// parsing them as leniently as `ParseOptions::lenient()` allows. Encodings that only differ in
+/// padding, base64 alphabet or suffix case are considered the same.
+pub fn same_legacy<T: LegacyEncoding + PartialEq>(a: &[u8], b: &[u8]) -> bool {
+    let opts = ParseOptions::lenient();
+    match (T::from_legacy_with(a, &opts), T::from_legacy_with(b, &opts)) {
+        (Ok((a, a_tail)), Ok((b, b_tail))) => a_tail.is_empty() && b_tail.is_empty() && a == b,
+        _ => false,
+    }
+}
+
+/// Return whether two textual refs (feed ids, message or blob hashes, or boxes) denote the same
+/// value, as decided by [`same_legacy`](fn.same_legacy.html). Useful for deduplicating refs
+/// that were not necessarily produced by a canonical encoder.
+///
+/// With the `uri` feature, either ref may also be an `ssb:` URI, which is parsed with
+/// [`SsbUri::from_legacy_uri`](uri/enum.SsbUri.html#method.from_legacy_uri). A URI and a legacy
+/// encoding are the same ref if the URI refers to the decoded value.
+///
+/// Only the kinds of refs whose features are enabled are considered.
+// Without any of them, nothing is the same ref and the refs go unused.
+#[cfg_attr(
+    not(any(feature = "multikey", feature = "multihash", feature = "multibox")),
+    allow(unused_variables)
+)]
+pub fn same_ref(a: &str, b: &str) -> bool {
+    #[cfg(feature = "uri")]
+    {
+        if a.starts_with(uri::URI_SCHEME) || b.starts_with(uri::URI_SCHEME) {
+            return match (uri_ref(a), uri_ref(b)) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            };
+        }
+    }
+
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+
+    #[cfg(feature = "multikey")]
+    {
+        if same_legacy::<multikey::Multikey>(a, b) {
+            return true;
+        }
+    }
+    #[cfg(feature = "multihash")]
+    {
+        if same_legacy::<multihash::Multihash>(a, b) {
+            return true;
+        }
+    }
+    #[cfg(feature = "multibox")]
+    {
+        if same_legacy::<multibox::Multibox>(a, b) {
+            return true;
+        }
+    }
+
+    false
+}
+
+// The value that a ref refers to, as an `ssb:` URI of a known kind or as a lenient legacy
+// encoding of a feed id or a message or blob hash.
+#[cfg(feature = "uri")]
+fn uri_ref(s: &str) -> Option<uri::SsbUri> {
+    if s.starts_with(uri::URI_SCHEME) {
+        return match uri::SsbUri::from_legacy_uri(s) {
+            Ok(uri::SsbUri::Unknown { .. }) | Err(_) => None,
+            Ok(uri) => Some(uri),
+        };
+    }
+
+    let opts = ParseOptions::lenient();
+    if let Ok((mk, [])) = multikey::Multikey::from_legacy_with(s.as_bytes(), &opts) {
+        return Some(uri::SsbUri::Feed(mk));
+    }
+    match multihash::Multihash::from_legacy_with(s.as_bytes(), &opts) {
+        Ok((mh, [])) if mh.is_blob() => Some(uri::SsbUri::Blob(mh)),
+        Ok((mh, [])) => Some(uri::SsbUri::Message(mh)),
+        _ => None,
+    }
+}
+
+/// Compare two values by their legacy encodings, exactly like JavaScript compares the strings
+/// of those encodings. This is the order of existing JS databases (e.g. ssb-db query cursors).
+///
+/// Legacy encodings are ASCII, so the UTF-16 code unit order of JavaScript coincides with the
+/// byte order of the encodings. This differs from the `Ord` impls of this crate, which compare
+/// the raw bytes: the base64 alphabet is not in ASCII order, so e.g. a key starting with byte
+/// `0xf8` (encoded as `+...`) sorts before one starting with byte `0x00` (encoded as `A...`).
+///
+/// This does not allocate, so it can be used as a sort comparator. The encodings are compared
+/// through a stack buffer that holds a whole key, hash or signature. Longer multiboxes are
+/// encoded again for every further window of the buffer's size.
+pub fn cmp_legacy<T: LegacyEncoding>(a: &T, b: &T) -> Ordering {
+    let mut skip = 0;
+    loop {
+        let a = CmpWindow::of(a, skip);
+        let b = CmpWindow::of(b, skip);
+        match a.bytes().cmp(b.bytes()) {
+            Ordering::Equal if a.len == CMP_WINDOW_LEN => skip += CMP_WINDOW_LEN,
+            ord => return ord,
+        }
+    }
+}
+
+// How many bytes of the two encodings `cmp_legacy` compares at a time.
+const CMP_WINDOW_LEN: usize = 128;
+
+// The bytes of an encoding from offset `skip` on, up to `CMP_WINDOW_LEN` of them.
+struct CmpWindow {
+    skip: usize,
+    written: usize,
+    buf: [u8; CMP_WINDOW_LEN],
+    len: usize,
+}
+
+impl CmpWindow {
+    fn of<T: LegacyEncoding>(value: &T, skip: usize) -> CmpWindow {
+        let mut window = CmpWindow {
+            skip,
+            written: 0,
+            buf: [0; CMP_WINDOW_LEN],
+            len: 0,
+        };
+        value.to_legacy(&mut window).unwrap();
+        window
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl Write for CmpWindow {
+    fn write(&mut self, data: &[u8]) -> Result<usize, io::Error> {
+        // The part of `data` that lies at or after `skip` and still fits into the buffer.
+        let from = self.skip.saturating_sub(self.written).min(data.len());
+        let take = (CMP_WINDOW_LEN - self.len).min(data.len() - from);
+        self.buf[self.len..self.len + take].copy_from_slice(&data[from..from + take]);
+        self.len += take;
+        self.written += data.len();
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+/// Iterate over the legacy encodings of values of type `T` that directly follow each other in
+/// the input, yielding each value together with the offset at which its encoding starts.
+///
+/// The iterator ends at the end of the input. After yielding an error, it yields nothing more.
+/// Multiboxes can not be delimited this way, as their suffix may be followed by further
+/// base32 characters.
+pub fn decode_legacy_iter<T: LegacyEncoding>(s: &[u8]) -> LegacyIter<'_, T> {
+    LegacyIter {
+        input: s,
+        offset: 0,
+        _value: PhantomData,
+    }
+}
+
+/// An iterator over concatenated legacy encodings, created by
+/// [`decode_legacy_iter`](fn.decode_legacy_iter.html).
+#[derive(Debug, Clone)]
+pub struct LegacyIter<'a, T> {
+    input: &'a [u8],
+    offset: usize,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<'a, T: LegacyEncoding> Iterator for LegacyIter<'a, T> {
+    type Item = Result<(T, usize), T::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.input.is_empty() {
+            return None;
+        }
+
+        match T::from_legacy_counted(self.input) {
+            Ok((value, consumed)) => {
+                let offset = self.offset;
+                self.offset += consumed;
+                self.input = &self.input[consumed..];
+                Some(Ok((value, offset)))
+            }
+            Err(err) => {
+                self.input = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
 
 ///////////////////////////////////////////////////////////////////////////////
 // A bunch of helper functions used throughout the crate for parsing legacy encodings.
 ////////////////////////////////////////////////////////////////////////////////
 
-// Split the input slice at the first occurence o the given byte, the byte itself is not
-// part of any of the returned slices. Return `None` if the byte is not found in the input.
-pub(crate) fn split_at_byte(input: &[u8], byte: u8) -> Option<(&[u8], &[u8])> {
-    for i in 0..input.len() {
-        if unsafe { *input.get_unchecked(i) } == byte {
-            let (start, end) = input.split_at(i);
-            return Some((start, &end[1..]));
+// Whether the base64 portion of a legacy encoding exceeds the length limit of the options.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn exceeds_max_len(data: &[u8], opts: &ParseOptions) -> bool {
+    match opts.max_len {
+        Some(max) => data.len() > max,
+        None => false,
+    }
+}
+
+// Append the input to the string, percent-encoding everything but unreserved characters.
+#[cfg(any(feature = "multikey", feature = "multihash"))]
+pub(crate) fn percent_encode(s: &[u8], out: &mut String) {
+    for &b in s {
+        if b.is_ascii_alphanumeric() || b == b'.' || b == b'-' || b == b'_' || b == b'~' {
+            out.push(b as char);
+        } else {
+            out.push('%');
+            out.push(HEX_UPPER[(b >> 4) as usize] as char);
+            out.push(HEX_UPPER[(b & 0xf) as usize] as char);
+        }
+    }
+}
+
+// The digits of percent-encoded bytes.
+#[cfg(any(feature = "multikey", feature = "multihash"))]
+const HEX_UPPER: &[u8; 16] = b"0123456789ABCDEF";
+
+// Why `decode_base64url_32` failed.
+#[cfg(feature = "uri")]
+pub(crate) enum Base64UrlError {
+    // The data contained a malformed percent-encoded character.
+    PercentEncoding,
+    // The data was not valid url-safe base64.
+    InvalidBase64(base64::DecodeError),
+    // The data did not encode exactly 32 bytes.
+    WrongLength,
+}
+
+// Decode exactly 32 bytes of url-safe base64 with optional padding, as used in ssb URIs.
+// Percent-encoded characters, such as `%3D` for the padding, are decoded first.
+#[cfg(feature = "uri")]
+pub(crate) fn decode_base64url_32(data: &str) -> Result<[u8; 32], Base64UrlError> {
+    let data = percent_decode(data.as_bytes()).ok_or(Base64UrlError::PercentEncoding)?;
+    let unpadded = data.strip_suffix(b"=").unwrap_or(&data);
+    if unpadded.len() != 43 {
+        return Err(Base64UrlError::WrongLength);
+    }
+
+    let mut buf = [0u8; 33];
+    let len = base64::decode_config_slice(unpadded, base64::URL_SAFE, &mut buf)
+        .map_err(Base64UrlError::InvalidBase64)?;
+    if len != 32 {
+        return Err(Base64UrlError::WrongLength);
+    }
+
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&buf[..32]);
+    Ok(out)
+}
+
+// Decode `%XX` escapes, or return `None` if the input contains a malformed escape.
+#[cfg(any(feature = "multikey", feature = "multihash"))]
+pub(crate) fn percent_decode(s: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut i = 0;
+
+    while i < s.len() {
+        if s[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            out.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(s[i]);
+            i += 1;
         }
     }
 
-    return None;
+    Some(out)
 }
 
-// If the slice begins with the given prefix, return everything after that prefix.
-pub(crate) fn skip_prefix<'a>(input: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
-    if input.starts_with(prefix) {
-        Some(&input[prefix.len()..])
+// Whether the input is valid standard base64, as accepted by `base64::decode_config`. This
+// decodes chunk by chunk into a fixed-size stack buffer, so it never allocates. Chunks are a
+// multiple of four bytes long, so only the last one may contain padding.
+pub(crate) fn is_valid_base64(data: &[u8]) -> bool {
+    let mut buf = [0u8; BASE64_CHUNK_LEN];
+    let mut chunks = data.chunks(BASE64_CHUNK_LEN / 3 * 4).peekable();
+
+    while let Some(chunk) = chunks.next() {
+        if chunks.peek().is_some() && chunk.contains(&b'=') {
+            return false;
+        }
+
+        if base64::decode_config_slice(chunk, base64::STANDARD, &mut buf).is_err() {
+            return false;
+        }
+    }
+
+    true
+}
+
+// The value of a symbol of the standard base64 alphabet.
+fn base64_value(symbol: u8) -> Option<u8> {
+    BASE64_ALPHABET
+        .iter()
+        .position(|&b| b == symbol)
+        .map(|i| i as u8)
+}
+
+// The offset at which the suffix of a legacy encoding begins, i.e. the position right after the
+// first dot. If there is no dot, this is the length of the input.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn suffix_offset(input: &[u8]) -> usize {
+    match split_at_byte(input, 0x2E) {
+        Some((data, _)) => data.len() + 1,
+        None => input.len(),
+    }
+}
+
+// The 64 bit FNV-1a hash of the concatenation of the given parts.
+#[cfg(any(feature = "bloom", feature = "multikey", feature = "multihash"))]
+pub(crate) fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for &byte in *part {
+            h ^= byte as u64;
+            h = h.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    h
+}
+
+// The offset into the base64 input at which decoding failed with the given error.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn base64_error_offset(err: &base64::DecodeError) -> usize {
+    match *err {
+        base64::DecodeError::InvalidByte(offset, _) => offset,
+        base64::DecodeError::InvalidLastSymbol(offset, _) => offset,
+        base64::DecodeError::InvalidLength => 0,
+    }
+}
+
+// Serialize a decode error as a map of its kind (the name of the variant), its code and its
+// message, followed by the given fields of the variant.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn serialize_error<S: serde::Serializer, E: fmt::Display>(
+    serializer: S,
+    kind: &str,
+    code: u16,
+    err: &E,
+    fields: &[(&str, usize)],
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeMap;
+
+    let mut map = serializer.serialize_map(Some(3 + fields.len()))?;
+    map.serialize_entry("kind", kind)?;
+    map.serialize_entry("code", &code)?;
+    map.serialize_entry("message", &err.to_string())?;
+    for &(key, value) in fields {
+        map.serialize_entry(key, &value)?;
+    }
+    map.end()
+}
+
+// Turn the result of a slice-based decoder into an error if it did not consume the whole input.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn exact<T, E>(decoded: Result<(T, &[u8]), E>, trailing: E) -> Result<T, E> {
+    match decoded {
+        Ok((value, [])) => Ok(value),
+        Ok(_) => Err(trailing),
+        Err(err) => Err(err),
+    }
+}
+
+// The most chunks of a `Buf` that `decode_buf` looks at.
+#[cfg(all(
+    feature = "bytes",
+    any(feature = "multikey", feature = "multihash", feature = "multibox")
+))]
+const MAX_BUF_CHUNKS: usize = 16;
+
+// Run a slice-based decoder on the start of a `Buf`, and advance the buffer past the decoded
+// value on success. The buffer is left untouched on failure.
+//
+// Contiguous buffers are decoded in place. Otherwise the encoding may end in any of the chunks,
+// so they are copied one at a time until the decoder succeeds on a prefix of the copy, or there
+// are no more chunks (or more than any default length limit allows) to copy. A success that
+// consumes the whole copy is not final while there are more chunks, as in a multibox suffix
+// that continues in the next chunk.
+#[cfg(all(
+    feature = "bytes",
+    any(feature = "multikey", feature = "multihash", feature = "multibox")
+))]
+pub(crate) fn decode_buf<B, T, E, F>(buf: &mut B, decode: F) -> Result<T, E>
+where
+    B: Buf,
+    F: for<'a> Fn(&'a [u8]) -> Result<(T, &'a [u8]), E>,
+{
+    let chunk = buf.chunk();
+    if chunk.len() == buf.remaining() {
+        let (value, tail) = decode(chunk)?;
+        let consumed = chunk.len() - tail.len();
+        buf.advance(consumed);
+        return Ok(value);
+    }
+
+    let mut slices = [io::IoSlice::new(&[]); MAX_BUF_CHUNKS];
+    let count = buf.chunks_vectored(&mut slices);
+    let mut copy = Vec::new();
+    let mut decoded = None;
+    for (i, slice) in slices[..count].iter().enumerate() {
+        copy.extend_from_slice(slice);
+        let last = i + 1 == count || copy.len() > DEFAULT_MAX_LEN;
+        match decode(&copy) {
+            Ok((value, tail)) if last || !tail.is_empty() => {
+                decoded = Some(Ok((value, copy.len() - tail.len())));
+                break;
+            }
+            Err(err) if last => {
+                decoded = Some(Err(err));
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    // `chunks_vectored` yields at least the nonempty current chunk, so something was decoded.
+    let (value, consumed) = decoded.unwrap()?;
+    buf.advance(consumed);
+    Ok(value)
+}
+
+// Write the base64 (standard alphabet, with padding) encoding of the input into the writer,
+// using the given engine.
+// This encodes through a fixed-size stack buffer, so it never allocates. Chunks are a multiple
+// of three bytes long, so only the last chunk can introduce padding.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn write_base64<W: Write, E: Base64Engine + ?Sized>(
+    w: &mut W,
+    input: &[u8],
+    engine: &E,
+) -> Result<(), io::Error> {
+    let mut buf = [0u8; BASE64_CHUNK_LEN / 3 * 4];
+
+    for chunk in input.chunks(BASE64_CHUNK_LEN) {
+        let len = engine.encode_slice(chunk, &mut buf);
+        w.write_all(&buf[..len])?;
+    }
+
+    Ok(())
+}
+
+// Version of `write_base64` for `fmt::Write`.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn fmt_base64<W: fmt::Write>(w: &mut W, input: &[u8]) -> fmt::Result {
+    let mut buf = [0u8; BASE64_CHUNK_LEN / 3 * 4];
+
+    for chunk in input.chunks(BASE64_CHUNK_LEN) {
+        let len = base64::encode_config_slice(chunk, base64::STANDARD, &mut buf);
+        w.write_str(std::str::from_utf8(&buf[..len]).unwrap())?;
+    }
+
+    Ok(())
+}
+
+// Write the short form of a legacy encoding: the sigil, the first `n` characters of the base64
+// data, an ellipsis and the suffix (including its dot). If the base64 data is at most `n`
+// characters long, it is written in full. Only as much of the data as needed is encoded.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) fn fmt_short<W: fmt::Write>(
+    w: &mut W,
+    sigil: &str,
+    data: &[u8],
+    suffix: &str,
+    n: usize,
+) -> fmt::Result {
+    w.write_str(sigil)?;
+
+    if data.len().div_ceil(3) * 4 <= n {
+        fmt_base64(w, data)?;
     } else {
-        None
+        let prefix = &data[..data.len().min(n.div_ceil(4) * 3)];
+        let mut enc = String::with_capacity(prefix.len() / 3 * 4 + 4);
+        fmt_base64(&mut enc, prefix)?;
+        w.write_str(&enc[..n])?;
+        w.write_str("…")?;
+    }
+
+    w.write_str(".")?;
+    w.write_str(suffix)
+}
+
+// Asynchronous version of `write_base64`.
+#[cfg(all(feature = "futures", feature = "multibox"))]
+pub(crate) async fn write_base64_async<W: AsyncWrite + Unpin>(
+    w: &mut W,
+    input: &[u8],
+) -> Result<(), io::Error> {
+    let mut buf = [0u8; BASE64_CHUNK_LEN / 3 * 4];
+
+    for chunk in input.chunks(BASE64_CHUNK_LEN) {
+        let len = base64::encode_config_slice(chunk, base64::STANDARD, &mut buf);
+        w.write_all(&buf[..len]).await?;
+    }
+
+    Ok(())
+}
+
+// The symbols of the standard base64 alphabet, in order of their value.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// How many characters of base64 data the `short` methods keep.
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+pub(crate) const SHORT_LEN: usize = 6;
+
+// How many bytes of input `write_base64` encodes at a time. Must be a multiple of three.
+const BASE64_CHUNK_LEN: usize = 192;
+
+#[cfg(any(feature = "bloom", feature = "multikey", feature = "multihash"))]
+#[test]
+fn test_fnv1a() {
+    // Test vectors from the FNV reference implementation.
+    assert_eq!(fnv1a(&[]), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(&[b"a"]), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(fnv1a(&[b"fo", b"obar"]), 0x8594_4171_f739_67e8);
+}
+
+#[cfg(any(feature = "multikey", feature = "multihash", feature = "multibox"))]
+#[test]
+fn test_write_base64() {
+    for len in 0..(BASE64_CHUNK_LEN * 3 + 2) {
+        let input: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let mut out = Vec::new();
+        write_base64(&mut out, &input, &DefaultEngine).unwrap();
+        assert_eq!(
+            out,
+            base64::encode_config(&input, base64::STANDARD).as_bytes()
+        );
+    }
+}
+
+#[test]
+fn test_is_valid_base64() {
+    let mut inputs = vec![
+        b"".to_vec(),
+        b"lA==".to_vec(),
+        b"lA=".to_vec(),
+        b"l===".to_vec(),
+    ];
+    for len in (BASE64_CHUNK_LEN - 3)..(BASE64_CHUNK_LEN * 2 + 3) {
+        let input: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let enc = base64::encode_config(&input, base64::STANDARD).into_bytes();
+
+        let mut padded_early = enc.clone();
+        padded_early[enc.len() / 2] = b'=';
+        let mut invalid_last = enc.clone();
+        invalid_last.pop();
+        if let Some(last) = invalid_last.last_mut() {
+            *last = b'!';
+        }
+
+        inputs.push(enc);
+        inputs.push(padded_early);
+        inputs.push(invalid_last);
     }
+
+    for input in inputs {
+        assert_eq!(
+            is_valid_base64(&input),
+            base64::decode_config(&input, base64::STANDARD).is_ok()
+        );
+    }
+}
+
+#[cfg(feature = "multihash")]
+#[test]
+fn test_decode_legacy_iter() {
+    use multihash::{Multihash, Target};
+
+    let a = Multihash::from_sha256([0; 32], Target::Message);
+    let b = Multihash::from_sha256([1; 32], Target::Blob);
+    let mut enc = a.to_legacy_vec();
+    enc.extend_from_slice(&b.to_legacy_vec());
+
+    let decoded: Vec<_> = decode_legacy_iter::<Multihash>(&enc)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(decoded, vec![(a, 0), (b, 52)]);
+
+    enc.push(b'%');
+    let mut iter = decode_legacy_iter::<Multihash>(&enc);
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
+
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+#[test]
+fn test_decode_legacy() {
+    use multifeed::Multifeed;
+    use multihash::Multihash;
+
+    fn roundtrip<T: LegacyEncoding>(s: &[u8]) -> Vec<u8> {
+        encode_legacy(&decode_legacy_exact::<T>(s).ok().unwrap())
+    }
+
+    let feed = b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let hash = b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256";
+    assert_eq!(roundtrip::<Multifeed>(feed), feed.to_vec());
+    assert_eq!(roundtrip::<Multihash>(hash), hash.to_vec());
+
+    let (_, tail) =
+        decode_legacy::<Multihash>(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256\"")
+            .unwrap();
+    assert_eq!(tail, b"\"");
+    assert!(decode_legacy::<Multifeed>(hash).is_err());
+}
+
+#[cfg(all(feature = "multikey", feature = "multibox"))]
+#[test]
+fn test_canonicalize() {
+    use multibox::Multibox;
+    use multikey::Multikey;
+
+    let canonical = b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    assert!(is_canonical::<Multikey>(canonical));
+    assert!(!is_canonical::<Multikey>(
+        b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB=.ed25519"
+    ));
+    assert!(!is_canonical::<Multikey>(
+        b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\""
+    ));
+
+    assert_eq!(
+        canonicalize::<Multikey>(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB.ED25519\"")
+            .unwrap(),
+        (canonical.to_vec(), &b"\""[..])
+    );
+    assert_eq!(
+        canonicalize::<Multibox>(b"lB.BOX").unwrap(),
+        (b"lA==.box".to_vec(), &[][..])
+    );
+    assert!(canonicalize::<Multikey>(b"@zurF8X68.ed25519").is_err());
+}
+
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+#[test]
+fn test_decode_legacy_with_deviations() {
+    use multihash::Multihash;
+    use multikey::Multikey;
+
+    let lenient = ParseOptions::lenient();
+    let mut deviations = Vec::new();
+
+    let canonical = b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let (key, tail) =
+        decode_legacy_with_deviations::<Multikey>(canonical, &lenient, &mut deviations).unwrap();
+    assert_eq!(key, Multikey::from_legacy_exact(canonical).unwrap());
+    assert_eq!(deviations, vec![]);
+    assert_eq!(tail, &b""[..]);
+
+    let (other, tail) = decode_legacy_with_deviations::<Multikey>(
+        b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB.ED25519\"",
+        &lenient,
+        &mut deviations,
+    )
+    .unwrap();
+    assert_eq!(other, key);
+    assert_eq!(
+        deviations,
+        vec![Deviation::NoncanonicBase64, Deviation::SuffixCase]
+    );
+    assert_eq!(tail, &b"\""[..]);
+
+    deviations.clear();
+    decode_legacy_with_deviations::<Multihash>(
+        b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0_8=.sha256",
+        &lenient,
+        &mut deviations,
+    )
+    .unwrap();
+    assert_eq!(deviations, vec![Deviation::UrlSafeAlphabet]);
+
+    assert!(decode_legacy_with_deviations::<Multikey>(
+        b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA.ed25519",
+        &ParseOptions::strict(),
+        &mut deviations,
+    )
+    .is_err());
+}
+
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+#[test]
+fn test_cmp_legacy() {
+    use multihash::Multihash;
+    use multikey::Multikey;
+
+    // `[0xf8; 32]` encodes to "+P...", which sorts before the "A..." of `[0; 32]`.
+    let low = Multikey::from_ed25519(&[0; 32]);
+    let high = Multikey::from_ed25519(&[0xf8; 32]);
+    assert_eq!(low.cmp(&high), Ordering::Less);
+    assert_eq!(cmp_legacy(&low, &high), Ordering::Greater);
+    assert_eq!(cmp_legacy(&low, &low), Ordering::Equal);
+
+    let message = Multihash::message_from_digest([0; 32]);
+    let blob = Multihash::blob_from_digest([0; 32]);
+    assert_eq!(cmp_legacy(&message, &blob), Ordering::Less);
+}
+
+#[cfg(feature = "multibox")]
+#[test]
+fn test_cmp_legacy_long() {
+    use multibox::Multibox;
+
+    // Boxes whose encodings only differ after several windows, or only in their length.
+    let mut boxes = Vec::new();
+    for &len in &[0, 95, 96, 97, 300, 301] {
+        for &last in &[0, 0xf8] {
+            let mut cyphertext = vec![7; len];
+            cyphertext.push(last);
+            boxes.push(Multibox::new_private_box(cyphertext));
+        }
+    }
+    for a in &boxes {
+        for b in &boxes {
+            assert_eq!(cmp_legacy(a, b), encode_legacy(a).cmp(&encode_legacy(b)));
+        }
+    }
+}
+
+#[cfg(all(feature = "multihash", feature = "multibox"))]
+#[test]
+fn test_same_ref() {
+    let canonical = "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256";
+    assert!(same_ref(canonical, canonical));
+    assert!(same_ref(
+        canonical,
+        "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0rc.SHA256"
+    ));
+    assert!(same_ref("lA==.box", "lB.box"));
+    assert!(!same_ref(
+        canonical,
+        "&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256"
+    ));
+    assert!(!same_ref(canonical, &format!("{} ", canonical)));
+    assert!(!same_ref("nonsense", "nonsense"));
+}
+
+#[cfg(feature = "uri")]
+#[test]
+fn test_same_ref_uri() {
+    let legacy = "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256";
+    let uri = "ssb:message/classic/MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0rc=";
+    assert!(same_ref(legacy, uri));
+    assert!(same_ref(
+        uri,
+        "ssb:message/sha256/MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc="
+    ));
+    assert!(same_ref(
+        "ssb:%25MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc%2B0rc%3D.sha256",
+        "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0rc.SHA256"
+    ));
+    assert!(!same_ref(
+        uri,
+        "&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256"
+    ));
+    assert!(!same_ref(
+        "ssb:thread/classic/abc",
+        "ssb:thread/classic/abc"
+    ));
+    assert!(!same_ref(uri, "lA==.box"));
+}
+
+#[cfg(all(feature = "serde_json", feature = "multikey", feature = "multibox"))]
+#[test]
+fn test_serialize_errors() {
+    use serde_json::json;
+
+    let err =
+        multikey::Multikey::from_legacy(b"@zur!8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+            .unwrap_err();
+    assert_eq!(
+        serde_json::to_value(&err).unwrap(),
+        json!({"kind": "InvalidBase64", "code": 103, "message": "Invalid base64", "base64_offset": 3})
+    );
+
+    let err =
+        multikey::Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8h.ed25519")
+            .unwrap_err();
+    assert_eq!(serde_json::to_value(&err).unwrap()["actual"], 31);
+
+    let err = multifeed::Multifeed::from_legacy(b"@abc.ed25519").unwrap_err();
+    assert_eq!(
+        serde_json::to_value(&err).unwrap()["code"],
+        err.error_code()
+    );
+
+    let err = multibox::Multibox::from_legacy(b"abc").unwrap_err();
+    assert_eq!(
+        serde_json::to_value(&err).unwrap(),
+        json!({"kind": "NoDot", "code": 401, "message": "No dot"})
+    );
+}
+
+#[cfg(all(feature = "multisig", feature = "multihash", feature = "multibox"))]
+#[test]
+fn test_error_codes_unique() {
+    use std::collections::HashSet;
+
+    let b64 = base64::DecodeError::InvalidLength;
+    let codes = vec![
+        multikey::DecodeLegacyError::Sigil.error_code(),
+        multikey::DecodeLegacyError::NoDot.error_code(),
+        multikey::DecodeLegacyError::InvalidBase64(b64.clone()).error_code(),
+        multikey::DecodeLegacyError::UnknownSuffix.error_code(),
+        multikey::DecodeLegacyError::WrongLength {
+            expected: 32,
+            actual: 31,
+        }
+        .error_code(),
+        multikey::DecodeLegacyError::TrailingBytes.error_code(),
+        multikey::DecodeLegacyError::TooLong.error_code(),
+        multikey::DecodeSignatureError::NoDot.error_code(),
+        multikey::DecodeSignatureError::NoDotSig.error_code(),
+        multikey::DecodeSignatureError::InvalidBase64(b64.clone()).error_code(),
+        multikey::DecodeSignatureError::UnknownSuffix.error_code(),
+        multikey::DecodeSignatureError::WrongLength {
+            expected: 32,
+            actual: 31,
+        }
+        .error_code(),
+        multikey::DecodeSignatureError::TrailingBytes.error_code(),
+        multikey::DecodeSignatureError::TooLong.error_code(),
+        multihash::DecodeLegacyError::Sigil.error_code(),
+        multihash::DecodeLegacyError::NoDot.error_code(),
+        multihash::DecodeLegacyError::InvalidBase64(b64.clone()).error_code(),
+        multihash::DecodeLegacyError::UnknownSuffix.error_code(),
+        multihash::DecodeLegacyError::WrongLength {
+            expected: 32,
+            actual: 31,
+        }
+        .error_code(),
+        multihash::DecodeLegacyError::TrailingBytes.error_code(),
+        multihash::DecodeLegacyError::TooLong.error_code(),
+        multibox::DecodeLegacyError::NoDot.error_code(),
+        multibox::DecodeLegacyError::InvalidBase64(b64).error_code(),
+        multibox::DecodeLegacyError::NoncanonicPadding.error_code(),
+        multibox::DecodeLegacyError::InvalidSuffix.error_code(),
+        multibox::DecodeLegacyError::TrailingBytes.error_code(),
+        multibox::DecodeLegacyError::TooLong.error_code(),
+        multibox::DecodeLegacyError::AllocationFailed.error_code(),
+        multibox::DecodeLegacyError::BufferTooSmall(1).error_code(),
+        multifeed::DecodeLegacyError::UnknownKind.error_code(),
+        multifeed::DecodeLegacyError::TrailingBytes.error_code(),
+    ];
+
+    let unique: HashSet<_> = codes.iter().collect();
+    assert_eq!(unique.len(), codes.len());
+}
+
+#[cfg(all(feature = "test-util", feature = "multisig", feature = "multihash"))]
+#[test]
+fn test_random() {
+    use std::convert::Infallible;
+
+    use multifeed::Multifeed;
+    use multihash::Multihash;
+    use multikey::{Multikey, Multisig};
+
+    // A deterministic xorshift generator.
+    struct TestRng(u64);
+
+    impl rand_core::TryRng for TestRng {
+        type Error = Infallible;
+
+        fn try_next_u32(&mut self) -> Result<u32, Infallible> {
+            self.try_next_u64().map(|x| x as u32)
+        }
+
+        fn try_next_u64(&mut self) -> Result<u64, Infallible> {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            Ok(self.0)
+        }
+
+        fn try_fill_bytes(&mut self, dst: &mut [u8]) -> Result<(), Infallible> {
+            for b in dst {
+                *b = self.try_next_u64()? as u8;
+            }
+            Ok(())
+        }
+    }
+
+    let mut a = TestRng(1);
+    let mut b = TestRng(1);
+    assert_eq!(Multikey::random(&mut a), Multikey::random(&mut b));
+    assert_ne!(Multikey::random(&mut a), Multikey::random(&mut a));
+    assert!(is_canonical::<Multifeed>(&encode_legacy(
+        &Multifeed::random(&mut a)
+    )));
+    assert!(Multihash::random_message(&mut a).is_message());
+    assert!(Multihash::random_blob(&mut a).is_blob());
+    assert_eq!(Multisig::random(&mut a).to_legacy_string().len(), 100);
 }
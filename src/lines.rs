@@ -0,0 +1,116 @@
+//! Parse text dumps that contain one legacy encoding per line.
+use std::fmt;
+use std::io::{self, BufRead};
+use std::marker::PhantomData;
+
+use super::LegacyEncoding;
+
+/// Iterate over the lines of a reader, parsing each line as the complete legacy encoding of a
+/// `T`. Both `\n` and `\r\n` line endings are accepted, empty lines are skipped.
+pub fn parse_lines<T: LegacyEncoding, R: BufRead>(r: R) -> LegacyLines<R, T> {
+    LegacyLines {
+        inner: r,
+        line: 0,
+        buf: Vec::new(),
+        _value: PhantomData,
+    }
+}
+
+/// An iterator over the values in a line-oriented text dump, created by
+/// [`parse_lines`](fn.parse_lines.html).
+#[derive(Debug)]
+pub struct LegacyLines<R, T> {
+    inner: R,
+    line: usize,
+    buf: Vec<u8>,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<R: BufRead, T: LegacyEncoding> Iterator for LegacyLines<R, T> {
+    type Item = Result<T, DecodeLineError<T::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            self.line += 1;
+
+            match self.inner.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(DecodeLineError::Io(self.line, err))),
+            }
+
+            let mut line = &self.buf[..];
+            if line.ends_with(b"\n") {
+                line = &line[..line.len() - 1];
+            }
+            if line.ends_with(b"\r") {
+                line = &line[..line.len() - 1];
+            }
+
+            if !line.is_empty() {
+                return Some(
+                    T::from_legacy_exact(line)
+                        .map_err(|err| DecodeLineError::Decode(self.line, err)),
+                );
+            }
+        }
+    }
+}
+
+/// Everything that can go wrong when parsing a line-oriented text dump. Each variant carries the
+/// (one-based) number of the line at which the error occured.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecodeLineError<E> {
+    /// Reading from the underlying reader failed.
+    Io(usize, io::Error),
+    /// The line was not a valid legacy encoding.
+    Decode(usize, E),
+}
+
+impl<E> DecodeLineError<E> {
+    /// The (one-based) number of the line at which the error occured.
+    pub fn line(&self) -> usize {
+        match *self {
+            DecodeLineError::Io(line, _) => line,
+            DecodeLineError::Decode(line, _) => line,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for DecodeLineError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeLineError::Io(line, _) => write!(f, "Could not read line {}", line),
+            DecodeLineError::Decode(line, _) => write!(f, "Invalid value in line {}", line),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for DecodeLineError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeLineError::Io(_, ref err) => Some(err),
+            DecodeLineError::Decode(_, ref err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "multikey")]
+#[test]
+fn test_parse_lines() {
+    use super::multikey::{DecodeLegacyError, Multikey};
+
+    let dump = &b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\n\r\n@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\r\n@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed2551\n@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"[..];
+
+    let results: Vec<_> = parse_lines::<Multikey, _>(dump).collect();
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_ok());
+    assert_matches!(
+        results[2],
+        Err(DecodeLineError::Decode(4, DecodeLegacyError::UnknownSuffix))
+    );
+    assert!(results[3].is_ok());
+}
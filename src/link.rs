@@ -0,0 +1,221 @@
+//! Typed links for modeling message content, e.g. the `contact` of a contact message or the
+//! `link` of a vote.
+//!
+//! The wrappers serialize to the legacy encoding and validate on deserialization, so a struct
+//! that uses them as fields only deserializes if every link is exactly a valid ref of the right
+//! kind.
+use std::fmt;
+
+use serde::{
+    de::{Deserialize, Deserializer, Error},
+    ser::{Serialize, Serializer},
+};
+
+use super::multihash::{self, Multihash, Target};
+use super::multikey::{self, Multikey};
+
+/// A link to a feed, serialized as the legacy encoding of its key.
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+pub struct FeedLink(Multikey);
+
+impl FeedLink {
+    /// Wrap the key of a feed.
+    pub fn new(key: Multikey) -> FeedLink {
+        FeedLink(key)
+    }
+
+    /// The key of the linked feed.
+    pub fn as_multikey(&self) -> &Multikey {
+        &self.0
+    }
+
+    /// Unwrap the key of the linked feed.
+    pub fn into_multikey(self) -> Multikey {
+        self.0
+    }
+}
+
+impl From<Multikey> for FeedLink {
+    fn from(key: Multikey) -> FeedLink {
+        FeedLink(key)
+    }
+}
+
+impl From<FeedLink> for Multikey {
+    fn from(link: FeedLink) -> Multikey {
+        link.0
+    }
+}
+
+impl fmt::Display for FeedLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for FeedLink {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FeedLink {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Multikey::from_legacy_str(&s)
+            .map(FeedLink)
+            .map_err(multikey::DecodeLegacyError::into_de_error)
+    }
+}
+
+/// A link to a message, serialized as the legacy encoding of its hash.
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+pub struct MsgLink(Multihash);
+
+impl MsgLink {
+    /// Wrap the hash of a message, or return `None` if the hash refers to a blob.
+    pub fn new(mh: Multihash) -> Option<MsgLink> {
+        if mh.target() == Target::Message {
+            Some(MsgLink(mh))
+        } else {
+            None
+        }
+    }
+
+    /// The hash of the linked message.
+    pub fn as_multihash(&self) -> &Multihash {
+        &self.0
+    }
+
+    /// Unwrap the hash of the linked message.
+    pub fn into_multihash(self) -> Multihash {
+        self.0
+    }
+}
+
+impl From<MsgLink> for Multihash {
+    fn from(link: MsgLink) -> Multihash {
+        link.0
+    }
+}
+
+impl fmt::Display for MsgLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for MsgLink {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MsgLink {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mh = deserialize_multihash(deserializer)?;
+        MsgLink::new(mh).ok_or_else(|| D::Error::custom("Not a message id"))
+    }
+}
+
+/// A link to a blob, serialized as the legacy encoding of its hash.
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+pub struct BlobLink(Multihash);
+
+impl BlobLink {
+    /// Wrap the hash of a blob, or return `None` if the hash refers to a message.
+    pub fn new(mh: Multihash) -> Option<BlobLink> {
+        if mh.target() == Target::Blob {
+            Some(BlobLink(mh))
+        } else {
+            None
+        }
+    }
+
+    /// The hash of the linked blob.
+    pub fn as_multihash(&self) -> &Multihash {
+        &self.0
+    }
+
+    /// Unwrap the hash of the linked blob.
+    pub fn into_multihash(self) -> Multihash {
+        self.0
+    }
+}
+
+impl From<BlobLink> for Multihash {
+    fn from(link: BlobLink) -> Multihash {
+        link.0
+    }
+}
+
+impl fmt::Display for BlobLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for BlobLink {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlobLink {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mh = deserialize_multihash(deserializer)?;
+        BlobLink::new(mh).ok_or_else(|| D::Error::custom("Not a blob id"))
+    }
+}
+
+// Deserialize a string that is exactly the legacy encoding of a multihash.
+fn deserialize_multihash<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Multihash, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Multihash::from_legacy_str(&s).map_err(multihash::DecodeLegacyError::into_de_error)
+}
+
+#[test]
+fn test_links() {
+    use serde::de::value::{Error as ValueError, StrDeserializer};
+
+    fn de<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T, ValueError> {
+        T::deserialize(StrDeserializer::<ValueError>::new(s))
+    }
+
+    let feed = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let msg = "%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256";
+    let blob = "&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256";
+
+    assert_eq!(de::<FeedLink>(feed).unwrap().to_string(), feed);
+    assert_eq!(de::<MsgLink>(msg).unwrap().to_string(), msg);
+    assert_eq!(de::<BlobLink>(blob).unwrap().to_string(), blob);
+
+    assert!(de::<MsgLink>(blob).is_err());
+    assert!(de::<BlobLink>(msg).is_err());
+    assert!(de::<FeedLink>(msg).is_err());
+    assert!(de::<FeedLink>(&format!("{}trailing", feed)).is_err());
+
+    let mh = Multihash::from_legacy_str(blob).unwrap();
+    assert_eq!(MsgLink::new(mh.clone()), None);
+    assert_eq!(BlobLink::new(mh.clone()).unwrap().into_multihash(), mh);
+}
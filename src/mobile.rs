@@ -0,0 +1,199 @@
+//! Kotlin and Swift bindings via [UniFFI](https://mozilla.github.io/uniffi-rs/), so that mobile
+//! clients can apply exactly the same validation rules as Rust code using this crate.
+//!
+//! All functions take and return legacy encodings as strings, and raw keys and digests as byte
+//! arrays.
+use std::fmt;
+
+use super::bindings::{self, BindingError};
+use super::multihash;
+use super::multikey;
+
+/// Everything that can go wrong in the functions of this module.
+#[derive(Debug, PartialEq, Eq, Clone, uniffi::Error)]
+#[non_exhaustive]
+pub enum RefError {
+    /// The string was not a valid legacy encoding, for the given reason.
+    Invalid {
+        /// A description of what was wrong with the encoding.
+        reason: String,
+    },
+    /// The raw bytes were not of the length the kind of value requires.
+    WrongLength {
+        /// The required number of bytes.
+        expected: u32,
+        /// The number of bytes that were given.
+        actual: u32,
+    },
+}
+
+impl fmt::Display for RefError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RefError::Invalid { ref reason } => write!(f, "Invalid encoding: {}", reason),
+            RefError::WrongLength { expected, actual } => {
+                write!(
+                    f,
+                    "Wrong length: expected {} bytes, got {}",
+                    expected, actual
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RefError {}
+
+impl From<multikey::DecodeLegacyError> for RefError {
+    fn from(err: multikey::DecodeLegacyError) -> RefError {
+        RefError::Invalid {
+            reason: err.to_string(),
+        }
+    }
+}
+
+impl From<multihash::DecodeLegacyError> for RefError {
+    fn from(err: multihash::DecodeLegacyError) -> RefError {
+        RefError::Invalid {
+            reason: err.to_string(),
+        }
+    }
+}
+
+impl From<multikey::DecodeSignatureError> for RefError {
+    fn from(err: multikey::DecodeSignatureError) -> RefError {
+        RefError::Invalid {
+            reason: err.to_string(),
+        }
+    }
+}
+
+// Convert an error of the shared binding logic.
+fn ref_error(err: BindingError) -> RefError {
+    match err {
+        BindingError::Invalid(reason) => RefError::Invalid { reason },
+        BindingError::WrongLength {
+            expected, actual, ..
+        } => RefError::WrongLength {
+            expected: expected as u32,
+            actual: actual as u32,
+        },
+    }
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multikey.
+#[uniffi::export]
+pub fn is_multikey(s: &str) -> bool {
+    bindings::is_multikey(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multifeed.
+#[uniffi::export]
+pub fn is_multifeed(s: &str) -> bool {
+    bindings::is_multifeed(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// message.
+#[uniffi::export]
+pub fn is_message_hash(s: &str) -> bool {
+    bindings::is_message_hash(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// blob.
+#[uniffi::export]
+pub fn is_blob_hash(s: &str) -> bool {
+    bindings::is_blob_hash(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multibox.
+#[uniffi::export]
+pub fn is_multibox(s: &str) -> bool {
+    bindings::is_multibox(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of an ed25519 signature.
+#[uniffi::export]
+pub fn is_signature(s: &str) -> bool {
+    bindings::is_signature(s)
+}
+
+/// Determine what kind of value the string is the legacy encoding of: `"feed"`, `"message"`,
+/// `"blob"` or `"box"`. Returns `null` in Kotlin and `nil` in Swift if it is none of these.
+#[uniffi::export]
+pub fn classify(s: &str) -> Option<String> {
+    bindings::classify(s).map(str::to_string)
+}
+
+/// Bring a feed id, message or blob hash, or box that may deviate from the canonical encoding
+/// (in padding, base64 alphabet or suffix case) into its canonical legacy encoding. Returns
+/// `null` in Kotlin and `nil` in Swift if the string is none of these, even leniently.
+#[uniffi::export]
+pub fn canonical_ref(s: &str) -> Option<String> {
+    bindings::canonical_ref(s)
+}
+
+/// Decode the legacy encoding of an ed25519 multikey into the 32 bytes of the public key.
+#[uniffi::export]
+pub fn decode_multikey(s: &str) -> Result<Vec<u8>, RefError> {
+    bindings::decode_multikey(s).map_err(ref_error)
+}
+
+/// Encode the 32 bytes of an ed25519 public key as a legacy multikey.
+#[uniffi::export]
+pub fn encode_multikey(pk: &[u8]) -> Result<String, RefError> {
+    bindings::encode_multikey(pk).map_err(ref_error)
+}
+
+/// Decode the legacy encoding of a sha256 multihash (referring to either a message or a blob)
+/// into the 32 bytes of the digest.
+#[uniffi::export]
+pub fn decode_multihash(s: &str) -> Result<Vec<u8>, RefError> {
+    bindings::decode_multihash(s).map_err(ref_error)
+}
+
+/// Encode the 32 bytes of a sha256 digest as a legacy multihash referring to a message if
+/// `blob` is false, or to a blob if it is true.
+#[uniffi::export]
+pub fn encode_multihash(digest: &[u8], blob: bool) -> Result<String, RefError> {
+    bindings::encode_multihash(digest, blob).map_err(ref_error)
+}
+
+/// Decode the legacy encoding of an ed25519 signature into the 64 signature bytes.
+#[uniffi::export]
+pub fn decode_signature(s: &str) -> Result<Vec<u8>, RefError> {
+    bindings::decode_signature(s).map_err(ref_error)
+}
+
+#[test]
+fn test_mobile() {
+    let feed = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    assert!(is_multikey(feed));
+    assert_eq!(
+        encode_multikey(&decode_multikey(feed).unwrap()).unwrap(),
+        feed
+    );
+    assert_eq!(
+        encode_multikey(&[0; 31]),
+        Err(RefError::WrongLength {
+            expected: 32,
+            actual: 31
+        })
+    );
+    assert_matches!(
+        decode_multikey("@abc.ed25519"),
+        Err(RefError::Invalid { .. })
+    );
+
+    assert_eq!(
+        canonical_ref("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0rc.SHA256").as_deref(),
+        Some("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256")
+    );
+    assert_eq!(canonical_ref("lB.box").as_deref(), Some("lA==.box"));
+    assert_eq!(canonical_ref("nonsense"), None);
+
+    let blob = encode_multihash(&[3; 32], true).unwrap();
+    assert!(is_blob_hash(&blob));
+    assert_eq!(decode_multihash(&blob).unwrap(), vec![3; 32]);
+}
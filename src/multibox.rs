@@ -1,73 +1,240 @@
 //! Implementation of [ssb multiboxes](https://spec.scuttlebutt.nz/datatypes.html#multibox).
+use std::borrow::Cow;
 use std::fmt;
 use std::io::{self, Write};
 
 use base64;
+use serde::ser::{Serialize, Serializer};
+use smallvec::SmallVec;
 
 use super::*;
 
-#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+#[derive(PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
 /// A multibox that owns its data. This does no decryption, it stores cyphertext.
 pub struct Multibox(_Multibox);
 
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
 enum _Multibox {
     // https://ssbc.github.io/scuttlebutt-protocol-guide/#private-messages
-    PrivateBox(Vec<u8>),
-    Other(u64, Vec<u8>),
+    PrivateBox(Cyphertext),
+    Other(u64, Cyphertext),
 }
 
+// Cyphertexts up to this many bytes are stored inline. Most private messages are a few hundred
+// bytes long, so decoding them during log scans does not need a heap allocation.
+const INLINE_CYPHERTEXT_LEN: usize = 256;
+
+type Cyphertext = SmallVec<[u8; INLINE_CYPHERTEXT_LEN]>;
+
 impl Multibox {
     /// Creates a new private box multibox with the given secret text (*not* base64 encoded).
     pub fn new_private_box(secret: Vec<u8>) -> Multibox {
-        Multibox(_Multibox::PrivateBox(secret))
+        Multibox::from_cyphertext(0, SmallVec::from_vec(secret))
     }
 
     /// Creates a multibox with from the given identifier and the given secret text (*not* base64 encoded).
     pub fn new_multibox(id: u64, secret: Vec<u8>) -> Multibox {
+        Multibox::from_cyphertext(id, SmallVec::from_vec(secret))
+    }
+
+    fn from_cyphertext(id: u64, secret: Cyphertext) -> Multibox {
         match id {
-            0 => Multibox::new_private_box(secret),
+            0 => Multibox(_Multibox::PrivateBox(secret)),
             _ => Multibox(_Multibox::Other(id, secret)),
         }
     }
 
+    /// The identifier of the kind of box, zero for private boxes.
+    pub fn id(&self) -> u64 {
+        match self.0 {
+            _Multibox::PrivateBox(_) => 0,
+            _Multibox::Other(id, _) => id,
+        }
+    }
+
+    /// The secret text (*not* base64 encoded).
+    pub fn cyphertext(&self) -> &[u8] {
+        match self.0 {
+            _Multibox::PrivateBox(ref bytes) => bytes,
+            _Multibox::Other(_, ref bytes) => bytes,
+        }
+    }
+
+    /// The suffix of the legacy encoding (without the dot): `"box"` for private boxes, followed
+    /// by the base32 identifier for other kinds of boxes. Multiboxes have no sigil.
+    pub fn suffix_str(&self) -> Cow<'static, str> {
+        match self.0 {
+            _Multibox::PrivateBox(_) => Cow::Borrowed("box"),
+            _Multibox::Other(id, _) => Cow::Owned(format!(
+                "box{}",
+                std::str::from_utf8(&encode_base32_id(id)).unwrap()
+            )),
+        }
+    }
+
     /// Parses a
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
     /// into a `Multibox`, also returning the remaining input on success.
     pub fn from_legacy(s: &[u8]) -> Result<(Multibox, &[u8]), DecodeLegacyError> {
-        let (data, suffix) = split_at_byte(s, 0x2E).ok_or_else(|| DecodeLegacyError::NoDot)?;
+        Multibox::from_legacy_with(s, &ParseOptions::default())
+    }
 
-        base64::decode_config(data, base64::STANDARD)
-            .map_err(|base64_err| DecodeLegacyError::InvalidBase64(base64_err))
-            .and_then(|cypher_raw| {
-                if data.len() % 4 != 0 {
-                    return Err(DecodeLegacyError::NoncanonicPadding);
-                }
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// into a `Multibox` like `from_legacy`, but tolerating the deviations from the canonical
+    /// encoding that the options allow.
+    pub fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multibox, &'a [u8]), DecodeLegacyError> {
+        Multibox::from_legacy_with_engine(s, opts, &DefaultEngine)
+    }
 
-                let tail =
-                    skip_prefix(suffix, b"box").ok_or_else(|| DecodeLegacyError::InvalidSuffix)?;
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// into a `Multibox` like `from_legacy_with`, decoding the base64 with the given engine.
+    pub fn from_legacy_with_engine<'a, E: Base64Engine + ?Sized>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+        engine: &E,
+    ) -> Result<(Multibox, &'a [u8]), DecodeLegacyError> {
+        let (data, suffix) = split_legacy(s, opts)?;
+        let mut cypher_raw = alloc_cyphertext(data.len().div_ceil(4) * 3)?;
+        let (id, len, tail) = decode_legacy_parts(&data, suffix, opts, engine, &mut cypher_raw)?;
+        cypher_raw.truncate(len);
+        if len <= INLINE_CYPHERTEXT_LEN {
+            // The estimate for the decoded length can exceed the inline capacity by two bytes.
+            cypher_raw.shrink_to_fit();
+        }
+        Ok((Multibox::from_cyphertext(id, cypher_raw), tail))
+    }
 
-                match decode_base32_id(tail).ok_or_else(|| DecodeLegacyError::InvalidSuffix)? {
-                    (0, tail) => Ok((Multibox(_Multibox::PrivateBox(cypher_raw)), tail)),
-                    (id, tail) => Ok((Multibox(_Multibox::Other(id, cypher_raw)), tail)),
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// like `from_legacy`, but allocates the cyphertext in the given arena instead of the global
+    /// allocator. Bulk ingestion can then free all decoded boxes at once by resetting the arena.
+    #[cfg(feature = "bumpalo")]
+    pub fn from_legacy_in<'a, 'b>(
+        s: &'a [u8],
+        bump: &'b bumpalo::Bump,
+    ) -> Result<(MultiboxRef<'b>, &'a [u8]), DecodeLegacyError> {
+        let opts = ParseOptions::default();
+        let (data, suffix) = split_legacy(s, &opts)?;
+        let cypher_raw = bump
+            .try_alloc_slice_fill_copy(data.len().div_ceil(4) * 3, 0u8)
+            .map_err(|_| DecodeLegacyError::AllocationFailed)?;
+        let (id, len, tail) =
+            decode_legacy_parts(&data, suffix, &opts, &DefaultEngine, cypher_raw)?;
+        Ok((MultiboxRef::new_multibox(id, &cypher_raw[..len]), tail))
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// like `from_legacy`, but writes the cyphertext into the start of the given buffer instead
+    /// of allocating it, e.g. for reusing pooled buffers. Fails with
+    /// `DecodeLegacyError::BufferTooSmall` before decoding anything if the buffer can not hold
+    /// three bytes for every four bytes of base64.
+    ///
+    /// Keys, hashes and signatures have a fixed size and never allocate when decoded.
+    pub fn from_legacy_into<'a, 'b>(
+        s: &'a [u8],
+        out: &'b mut [u8],
+    ) -> Result<(MultiboxRef<'b>, &'a [u8]), DecodeLegacyError> {
+        let opts = ParseOptions::default();
+        let (data, suffix) = split_legacy(s, &opts)?;
+        let needed = data.len().div_ceil(4) * 3;
+        if out.len() < needed {
+            return Err(DecodeLegacyError::BufferTooSmall(needed));
+        }
+
+        let (id, len, tail) = decode_legacy_parts(&data, suffix, &opts, &DefaultEngine, out)?;
+        Ok((MultiboxRef::new_multibox(id, &out[..len]), tail))
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// into a `Multibox`, failing with `DecodeLegacyError::TrailingBytes` if the input
+    /// contains anything after the encoding.
+    pub fn from_legacy_exact(s: &[u8]) -> Result<Multibox, DecodeLegacyError> {
+        exact(Multibox::from_legacy(s), DecodeLegacyError::TrailingBytes)
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// that makes up the whole string into a `Multibox`.
+    pub fn from_legacy_str(s: &str) -> Result<Multibox, DecodeLegacyError> {
+        Multibox::from_legacy_exact(s.as_bytes())
+    }
+
+    /// Read a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// of a `Multibox` from a reader, decoding the base64 while reading rather than buffering
+    /// it. The reader is left positioned right after the encoding.
+    pub fn from_legacy_reader<R: io::BufRead>(r: R) -> Result<Multibox, io::Error> {
+        let mut reader = LegacyReader::new(r);
+        let mut cyphertext = Cyphertext::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            match io::Read::read(&mut reader, &mut buf)? {
+                0 => break,
+                len => {
+                    cyphertext
+                        .try_reserve(len)
+                        .map_err(|_| DecodeLegacyError::AllocationFailed)?;
+                    cyphertext.extend_from_slice(&buf[..len]);
                 }
-            })
+            }
+        }
+        let id = reader
+            .id()
+            .expect("reading until the end consumes the suffix");
+        Ok(Multibox::from_cyphertext(id, cyphertext))
+    }
+
+    /// Return whether the input is exactly a valid legacy encoding of a `Multibox`, i.e. whether
+    /// `from_legacy_exact` would accept it, including its default length limit. Unlike decoding,
+    /// this checks the base64 without producing (or allocating) the cyphertext.
+    pub fn is_valid_legacy(s: &[u8]) -> bool {
+        let (data, suffix) = match split_at_byte(s, 0x2E) {
+            Some(split) => split,
+            None => return false,
+        };
+
+        if exceeds_max_len(data, &ParseOptions::default())
+            || !data.len().is_multiple_of(4)
+            || !is_valid_base64(data)
+        {
+            return false;
+        }
+
+        match skip_prefix(suffix, b"box").and_then(decode_base32_id) {
+            Some((_, tail)) => tail.is_empty(),
+            None => false,
+        }
     }
 
     /// Serialize a `Multibox` into a writer, using the
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding).
     pub fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        self.to_legacy_with_engine(w, &DefaultEngine)
+    }
+
+    /// Serialize a `Multibox` into a writer like `to_legacy`, encoding the base64 with the given
+    /// engine.
+    pub fn to_legacy_with_engine<W: Write, E: Base64Engine + ?Sized>(
+        &self,
+        w: &mut W,
+        engine: &E,
+    ) -> Result<(), io::Error> {
         match self.0 {
             _Multibox::PrivateBox(ref bytes) => {
-                let data = base64::encode_config(bytes, base64::STANDARD);
-                w.write_all(data.as_bytes())?;
+                write_base64(w, bytes, engine)?;
 
                 w.write_all(b".box")
             }
 
             _Multibox::Other(id, ref bytes) => {
-                let data = base64::encode_config(bytes, base64::STANDARD);
-                w.write_all(data.as_bytes())?;
+                write_base64(w, bytes, engine)?;
 
                 w.write_all(b".box")?;
                 w.write_all(&encode_base32_id(id)[..])
@@ -75,8 +242,99 @@ impl Multibox {
         }
     }
 
+    /// Write a `Multibox` into a `fmt::Write`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding).
+    pub fn fmt_legacy<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        match self.0 {
+            _Multibox::PrivateBox(ref bytes) => {
+                fmt_base64(w, bytes)?;
+
+                w.write_str(".box")
+            }
+
+            _Multibox::Other(id, ref bytes) => {
+                fmt_base64(w, bytes)?;
+
+                w.write_str(".box")?;
+                w.write_str(std::str::from_utf8(&encode_base32_id(id)).unwrap())
+            }
+        }
+    }
+
+    /// Asynchronously serialize a `Multibox` into a writer, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding).
+    #[cfg(feature = "futures")]
+    pub async fn to_legacy_async<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<(), io::Error> {
+        match self.0 {
+            _Multibox::PrivateBox(ref bytes) => {
+                write_base64_async(w, bytes).await?;
+
+                w.write_all(b".box").await
+            }
+
+            _Multibox::Other(id, ref bytes) => {
+                write_base64_async(w, bytes).await?;
+
+                w.write_all(b".box").await?;
+                w.write_all(&encode_base32_id(id)[..]).await
+            }
+        }
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+    /// from the start of a `Buf` into a `Multibox`, advancing the buffer past it on success.
+    ///
+    /// The encoding may be split across the chunks of the buffer, as in a `Chain`. Contiguous
+    /// buffers such as `BytesMut` are decoded in place, others are copied first.
+    #[cfg(feature = "bytes")]
+    pub fn from_legacy_buf<B: Buf>(buf: &mut B) -> Result<Multibox, DecodeLegacyError> {
+        decode_buf(buf, Multibox::from_legacy)
+    }
+
+    /// Serialize a `Multibox` into a `BufMut`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding).
+    ///
+    /// Panics if the buffer does not have enough remaining capacity, like `BufMut::put_slice`.
+    #[cfg(feature = "bytes")]
+    pub fn to_legacy_buf<B: BufMut>(&self, buf: &mut B) {
+        self.to_legacy(&mut buf.writer()).unwrap()
+    }
+
+    /// A short form of the legacy encoding for logs and user interfaces, keeping the first few
+    /// characters of the base64 data and the suffix, e.g. `AAECAw….box`.
+    pub fn short(&self) -> String {
+        self.short_n(SHORT_LEN)
+    }
+
+    /// Like `short`, but keeping the first `n` characters of the base64 data.
+    pub fn short_n(&self, n: usize) -> String {
+        let mut s = String::new();
+        match self.0 {
+            _Multibox::PrivateBox(ref bytes) => fmt_short(&mut s, "", bytes, "box", n).unwrap(),
+            _Multibox::Other(_, ref bytes) => {
+                fmt_short(&mut s, "", bytes, &self.suffix_str(), n).unwrap()
+            }
+        }
+        s
+    }
+
+    /// Check that the cyphertext could structurally be a box of its kind, without decrypting
+    /// anything. This rejects obviously garbage boxes early, e.g. during ingestion.
+    ///
+    /// A private box consists of a nonce, a one-time public key, a slot for every recipient and
+    /// the encrypted body, so it must be large enough for a single recipient and an empty body.
+    /// The recipient count is part of the encrypted slots, so nothing more can be checked.
+    /// Boxes of other kinds are not checked.
+    pub fn validate_structure(&self) -> Result<(), InvalidStructureError> {
+        validate_structure(self.id(), self.cyphertext())
+    }
+
     /// Serialize a `Multibox` into an owned byte vector, using the
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding).
+    ///
+    /// This allocates, to encode without allocating use `to_legacy` with a byte slice as the
+    /// writer, `fmt_legacy` or `to_legacy_buf`.
     pub fn to_legacy_vec(&self) -> Vec<u8> {
         let capacity = match self.0 {
             _Multibox::PrivateBox(ref cyphertext) => ((cyphertext.len() * 4) / 3) + 4,
@@ -97,8 +355,247 @@ impl Multibox {
     }
 }
 
+impl LegacyEncoding for Multibox {
+    type Error = DecodeLegacyError;
+
+    fn from_legacy(s: &[u8]) -> Result<(Multibox, &[u8]), DecodeLegacyError> {
+        Multibox::from_legacy(s)
+    }
+
+    fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multibox, &'a [u8]), DecodeLegacyError> {
+        Multibox::from_legacy_with(s, opts)
+    }
+
+    fn from_legacy_exact(s: &[u8]) -> Result<Multibox, DecodeLegacyError> {
+        Multibox::from_legacy_exact(s)
+    }
+
+    fn from_legacy_located(s: &[u8]) -> Result<(Multibox, &[u8]), DecodeError<DecodeLegacyError>> {
+        Multibox::from_legacy(s).map_err(|err| DecodeError {
+            offset: legacy_error_offset(&err, s),
+            kind: err,
+        })
+    }
+
+    fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        Multibox::to_legacy(self, w)
+    }
+}
+
+/// Formats the kind of box and its legacy encoding, e.g. `Multibox(Other(27), "lA==.boxV")`.
+impl fmt::Debug for Multibox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut t = f.debug_tuple("Multibox");
+        match self.0 {
+            _Multibox::PrivateBox(_) => t.field(&format_args!("PrivateBox")),
+            _Multibox::Other(id, _) => t.field(&format_args!("Other({})", id)),
+        };
+        t.field(&self.to_legacy_string()).finish()
+    }
+}
+
+/// Formats as the legacy encoding, without allocating.
+impl fmt::Display for Multibox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_legacy(f)
+    }
+}
+
+impl From<&Multibox> for String {
+    fn from(value: &Multibox) -> String {
+        value.to_legacy_string()
+    }
+}
+
+impl From<&Multibox> for Vec<u8> {
+    fn from(value: &Multibox) -> Vec<u8> {
+        value.to_legacy_vec()
+    }
+}
+
+/// A multibox that borrows its cyphertext, e.g. from an arena or a memory-mapped log. It
+/// compares and orders exactly like the `Multibox` it corresponds to.
+#[derive(PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct MultiboxRef<'a>(_MultiboxRef<'a>);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+enum _MultiboxRef<'a> {
+    PrivateBox(&'a [u8]),
+    Other(u64, &'a [u8]),
+}
+
+impl<'a> MultiboxRef<'a> {
+    /// Borrow the given secret text (*not* base64 encoded) as a multibox with the given
+    /// identifier.
+    pub fn new_multibox(id: u64, secret: &'a [u8]) -> MultiboxRef<'a> {
+        match id {
+            0 => MultiboxRef(_MultiboxRef::PrivateBox(secret)),
+            _ => MultiboxRef(_MultiboxRef::Other(id, secret)),
+        }
+    }
+
+    /// The identifier of the kind of box, zero for private boxes.
+    pub fn id(&self) -> u64 {
+        match self.0 {
+            _MultiboxRef::PrivateBox(_) => 0,
+            _MultiboxRef::Other(id, _) => id,
+        }
+    }
+
+    /// The cyphertext of the box.
+    pub fn cyphertext(&self) -> &'a [u8] {
+        match self.0 {
+            _MultiboxRef::PrivateBox(secret) | _MultiboxRef::Other(_, secret) => secret,
+        }
+    }
+
+    /// Copy the box into an owned `Multibox`.
+    pub fn to_multibox(&self) -> Multibox {
+        Multibox::new_multibox(self.id(), self.cyphertext().to_vec())
+    }
+
+    /// Check that the cyphertext could structurally be a box of its kind, like
+    /// `Multibox::validate_structure`.
+    pub fn validate_structure(&self) -> Result<(), InvalidStructureError> {
+        validate_structure(self.id(), self.cyphertext())
+    }
+}
+
+impl Multibox {
+    /// Borrow the box as a `MultiboxRef`.
+    pub fn as_multibox_ref(&self) -> MultiboxRef<'_> {
+        MultiboxRef::new_multibox(self.id(), self.cyphertext())
+    }
+}
+
+/// Formats like the `Debug` of a `Multibox`, e.g. `MultiboxRef(Other(27), "lA==.boxV")`.
+impl fmt::Debug for MultiboxRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut t = f.debug_tuple("MultiboxRef");
+        match self.0 {
+            _MultiboxRef::PrivateBox(_) => t.field(&format_args!("PrivateBox")),
+            _MultiboxRef::Other(id, _) => t.field(&format_args!("Other({})", id)),
+        };
+        t.field(&self.to_multibox().to_legacy_string()).finish()
+    }
+}
+
+impl From<MultiboxRef<'_>> for Multibox {
+    fn from(value: MultiboxRef<'_>) -> Multibox {
+        value.to_multibox()
+    }
+}
+
+impl PartialEq<Multibox> for MultiboxRef<'_> {
+    fn eq(&self, other: &Multibox) -> bool {
+        *self == other.as_multibox_ref()
+    }
+}
+
+impl PartialEq<MultiboxRef<'_>> for Multibox {
+    fn eq(&self, other: &MultiboxRef<'_>) -> bool {
+        self.as_multibox_ref() == *other
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Multibox {
+    fn format(&self, f: defmt::Formatter) {
+        match self.0 {
+            _Multibox::PrivateBox(ref bytes) => {
+                defmt::write!(f, "Multibox(PrivateBox, {} bytes)", bytes.len())
+            }
+            _Multibox::Other(id, ref bytes) => {
+                defmt::write!(f, "Multibox(Other({}), {} bytes)", id, bytes.len())
+            }
+        }
+    }
+}
+
+// The sizes of the parts of a private box: the nonce, the one-time public key, the slot of a
+// single recipient (an encrypted key and recipient count) and the authenticator of the body.
+const PRIVATE_BOX_NONCE_LEN: usize = 24;
+const PRIVATE_BOX_KEY_LEN: usize = 32;
+const PRIVATE_BOX_SLOT_LEN: usize = 33 + 16;
+const PRIVATE_BOX_MAC_LEN: usize = 16;
+
+/// The smallest possible cyphertext of a private box: one recipient and an empty body.
+pub const PRIVATE_BOX_MIN_LEN: usize = private_box_cyphertext_len(0, 1);
+
+/// The most recipients a private box can have, as the count is stored in a single byte.
+/// Clients usually limit boxes to far fewer (seven in ssb-server).
+pub const PRIVATE_BOX_MAX_RECIPIENTS: usize = 255;
+
+/// The length of the cyphertext of a private box with the given number of recipients around a
+/// plaintext of the given length.
+pub const fn private_box_cyphertext_len(plaintext_len: usize, recipients: usize) -> usize {
+    PRIVATE_BOX_NONCE_LEN
+        + PRIVATE_BOX_KEY_LEN
+        + recipients * PRIVATE_BOX_SLOT_LEN
+        + PRIVATE_BOX_MAC_LEN
+        + plaintext_len
+}
+
+/// The length of the legacy encoding (`<base64>.box`) of a private box with the given number of
+/// recipients around a plaintext of the given length.
+pub const fn private_box_legacy_len(plaintext_len: usize, recipients: usize) -> usize {
+    private_box_cyphertext_len(plaintext_len, recipients).div_ceil(3) * 4 + ".box".len()
+}
+
+/// The length of the longest plaintext that a private box with the given number of recipients
+/// can hold without its cyphertext exceeding `max_cyphertext_len` bytes, or `None` if not even
+/// an empty plaintext fits.
+pub const fn private_box_max_plaintext_len(
+    max_cyphertext_len: usize,
+    recipients: usize,
+) -> Option<usize> {
+    max_cyphertext_len.checked_sub(private_box_cyphertext_len(0, recipients))
+}
+
+// Check the structure of the cyphertext of a box with the given identifier.
+fn validate_structure(id: u64, cyphertext: &[u8]) -> Result<(), InvalidStructureError> {
+    if id == 0 && cyphertext.len() < PRIVATE_BOX_MIN_LEN {
+        return Err(InvalidStructureError::TooShort {
+            min: PRIVATE_BOX_MIN_LEN,
+            actual: cyphertext.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Everything that can be structurally wrong with the cyphertext of a box.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum InvalidStructureError {
+    /// The cyphertext is shorter than any box of its kind.
+    TooShort {
+        /// The length of the smallest possible cyphertext.
+        min: usize,
+        /// The length of the cyphertext.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for InvalidStructureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InvalidStructureError::TooShort { min, actual } => write!(
+                f,
+                "Cyphertext too short: expected at least {} bytes, got {}",
+                min, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidStructureError {}
+
 /// Everything that can go wrong when decoding a `Multibox` from the legacy encoding.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum DecodeLegacyError {
     /// Input did not contain a `"."` to separate the data from the suffix.
     NoDot,
@@ -108,34 +605,492 @@ pub enum DecodeLegacyError {
     NoncanonicPadding,
     /// The suffix is not well-formed.
     InvalidSuffix,
+    /// The input contained more than just the encoding.
+    TrailingBytes,
+    /// The base64 portion of the box was longer than the length limit of the `ParseOptions`.
+    TooLong,
+    /// The memory for the cyphertext could not be allocated.
+    AllocationFailed,
+    /// The buffer for the cyphertext was too small, it needs to hold at least this many bytes.
+    BufferTooSmall(usize),
+}
+
+// The offset into `s` at which decoding a multibox failed with the given error.
+fn legacy_error_offset(err: &DecodeLegacyError, s: &[u8]) -> usize {
+    match *err {
+        DecodeLegacyError::NoDot => s.len(),
+        DecodeLegacyError::InvalidBase64(ref err) => base64_error_offset(err),
+        DecodeLegacyError::NoncanonicPadding => suffix_offset(s) - 1,
+        DecodeLegacyError::InvalidSuffix => suffix_offset(s),
+        DecodeLegacyError::TrailingBytes => Multibox::from_legacy(s)
+            .map(|(_, tail)| s.len() - tail.len())
+            .unwrap_or_else(|_| s.len()),
+        DecodeLegacyError::TooLong
+        | DecodeLegacyError::AllocationFailed
+        | DecodeLegacyError::BufferTooSmall(_) => 0,
+    }
+}
+
+impl DecodeLegacyError {
+    /// A stable numeric code identifying the kind of this error. Codes are unique across all
+    /// error types of this crate, and are never changed or reused.
+    pub fn error_code(&self) -> u16 {
+        match *self {
+            DecodeLegacyError::NoDot => 401,
+            DecodeLegacyError::InvalidBase64(_) => 402,
+            DecodeLegacyError::NoncanonicPadding => 403,
+            DecodeLegacyError::InvalidSuffix => 404,
+            DecodeLegacyError::TrailingBytes => 405,
+            DecodeLegacyError::TooLong => 406,
+            DecodeLegacyError::AllocationFailed => 407,
+            DecodeLegacyError::BufferTooSmall(_) => 408,
+        }
+    }
+
+    /// Convert into a serde deserialization error, for `Deserialize` impls that parse legacy
+    /// encodings.
+    pub fn into_de_error<E: serde::de::Error>(self) -> E {
+        E::custom(format!("Invalid multibox: {}", self))
+    }
 }
 
 impl fmt::Display for DecodeLegacyError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &DecodeLegacyError::InvalidBase64(ref err) => write!(f, "{}", err),
-            &DecodeLegacyError::NoncanonicPadding => write!(f, "Incorrect number of padding '='s"),
-            &DecodeLegacyError::NoDot => write!(f, "No dot"),
-            &DecodeLegacyError::InvalidSuffix => write!(f, "Invalid suffix"),
+        match *self {
+            DecodeLegacyError::InvalidBase64(_) => write!(f, "Invalid base64"),
+            DecodeLegacyError::NoncanonicPadding => write!(f, "Incorrect number of padding '='s"),
+            DecodeLegacyError::NoDot => write!(f, "No dot"),
+            DecodeLegacyError::InvalidSuffix => write!(f, "Invalid suffix"),
+            DecodeLegacyError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeLegacyError::TooLong => write!(f, "Too long"),
+            DecodeLegacyError::AllocationFailed => write!(f, "Allocation failed"),
+            DecodeLegacyError::BufferTooSmall(len) => {
+                write!(f, "Buffer too small, {} bytes needed", len)
+            }
         }
     }
 }
 
-impl std::error::Error for DecodeLegacyError {}
+/// Serializes as a map with the `kind` of the error (the name of its variant), its numeric `code`
+/// and its `message`, plus the fields of the variant: the `base64_offset` for
+/// `InvalidBase64`, which counts from the start of the base64 data rather than from the start of
+/// the input (`from_legacy_located` reports the latter), and `needed` for `BufferTooSmall`.
+impl Serialize for DecodeLegacyError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            DecodeLegacyError::NoDot => {
+                serialize_error(serializer, "NoDot", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::InvalidBase64(ref err) => serialize_error(
+                serializer,
+                "InvalidBase64",
+                self.error_code(),
+                self,
+                &[("base64_offset", base64_error_offset(err))],
+            ),
+            DecodeLegacyError::NoncanonicPadding => serialize_error(
+                serializer,
+                "NoncanonicPadding",
+                self.error_code(),
+                self,
+                &[],
+            ),
+            DecodeLegacyError::InvalidSuffix => {
+                serialize_error(serializer, "InvalidSuffix", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::TrailingBytes => {
+                serialize_error(serializer, "TrailingBytes", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::TooLong => {
+                serialize_error(serializer, "TooLong", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::AllocationFailed => {
+                serialize_error(serializer, "AllocationFailed", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::BufferTooSmall(len) => serialize_error(
+                serializer,
+                "BufferTooSmall",
+                self.error_code(),
+                self,
+                &[("needed", len)],
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeLegacyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeLegacyError::InvalidBase64(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeLegacyError> for io::Error {
+    fn from(err: DecodeLegacyError) -> io::Error {
+        match err {
+            DecodeLegacyError::AllocationFailed => io::Error::new(io::ErrorKind::OutOfMemory, err),
+            _ => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DecodeLegacyError {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            DecodeLegacyError::InvalidBase64(_) => defmt::write!(f, "Invalid base64"),
+            DecodeLegacyError::NoncanonicPadding => {
+                defmt::write!(f, "Incorrect number of padding '='s")
+            }
+            DecodeLegacyError::NoDot => defmt::write!(f, "No dot"),
+            DecodeLegacyError::InvalidSuffix => defmt::write!(f, "Invalid suffix"),
+            DecodeLegacyError::TrailingBytes => defmt::write!(f, "Trailing bytes"),
+            DecodeLegacyError::TooLong => defmt::write!(f, "Too long"),
+            DecodeLegacyError::AllocationFailed => defmt::write!(f, "Allocation failed"),
+            DecodeLegacyError::BufferTooSmall(len) => {
+                defmt::write!(f, "Buffer too small, {} bytes needed", len)
+            }
+        }
+    }
+}
+
+/// The result of feeding a chunk of input to a [`LegacyDecoder`](struct.LegacyDecoder.html).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Progress<T> {
+    /// A value has been decoded. The `usize` is the number of bytes of the *last* chunk that
+    /// belong to the value, any further bytes of that chunk were not consumed.
+    Complete(T, usize),
+    /// The input so far is a valid prefix, but does not determine a value yet. The `usize` is
+    /// a lower bound on the number of additional bytes needed.
+    NeedMoreBytes(usize),
+}
+
+/// A resumable decoder for the
+/// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+/// of a `Multibox`, for input that arrives in chunks.
+///
+/// The legacy encoding is not self-delimiting: a multibox is only complete once the decoder has
+/// seen the first byte following it (a `"` when parsing legacy messages), or once the identifier
+/// reached its maximum length. If the input ends right after a multibox, call
+/// [`finish`](struct.LegacyDecoder.html#method.finish) instead.
+#[derive(Debug, Default, Clone)]
+pub struct LegacyDecoder {
+    buf: Vec<u8>,
+}
+
+impl LegacyDecoder {
+    /// Create a decoder that has not consumed any input yet.
+    pub fn new() -> LegacyDecoder {
+        LegacyDecoder { buf: Vec::new() }
+    }
+
+    /// Feed the next chunk of input to the decoder. After returning `Progress::Complete` or an
+    /// error, the decoder is reset and can be used to decode the next multibox.
+    ///
+    /// Like `Multibox::from_legacy`, this fails with `DecodeLegacyError::TooLong` once the base64
+    /// is longer than `DEFAULT_MAX_LEN`, so the decoder never buffers much more than that.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Progress<Multibox>, DecodeLegacyError> {
+        let result = self.push_buffered(chunk);
+        if !matches!(result, Ok(Progress::NeedMoreBytes(_))) {
+            self.buf.clear();
+        }
+        result
+    }
+
+    // Like `push`, but leaving the buffer for `push` to reset.
+    fn push_buffered(&mut self, chunk: &[u8]) -> Result<Progress<Multibox>, DecodeLegacyError> {
+        // No more than the longest valid encoding and the byte that follows it can belong to the
+        // multibox, there is no need to buffer the rest of the chunk.
+        let prev_len = self.buf.len();
+        let max_len = DEFAULT_MAX_LEN + b".box".len() + MAX_ID_LEN + 1;
+        let take = chunk.len().min(max_len.saturating_sub(prev_len));
+        self.buf.extend_from_slice(&chunk[..take]);
+
+        let suffix = match split_at_byte(&self.buf, 0x2E) {
+            Some((data, _)) if exceeds_max_len(data, &ParseOptions::default()) => {
+                return Err(DecodeLegacyError::TooLong)
+            }
+            Some((_, suffix)) => suffix,
+            None if exceeds_max_len(&self.buf, &ParseOptions::default()) => {
+                return Err(DecodeLegacyError::TooLong)
+            }
+            // The base64 data is a multiple of four bytes long, and ".box" is still missing.
+            None => return Ok(Progress::NeedMoreBytes(((4 - self.buf.len() % 4) % 4) + 4)),
+        };
+
+        if suffix.len() < 3 {
+            if !b"box".starts_with(suffix) {
+                return Err(DecodeLegacyError::InvalidSuffix);
+            }
+            return Ok(Progress::NeedMoreBytes(3 - suffix.len()));
+        }
+
+        let id = skip_prefix(suffix, b"box").ok_or(DecodeLegacyError::InvalidSuffix)?;
+        match decode_base32_id(id).ok_or(DecodeLegacyError::InvalidSuffix)? {
+            (_, tail) if tail.is_empty() && id.len() < MAX_ID_LEN => {
+                return Ok(Progress::NeedMoreBytes(1))
+            }
+            _ => {}
+        }
+
+        Multibox::from_legacy(&self.buf)
+            .map(|(mb, tail)| Progress::Complete(mb, self.buf.len() - tail.len() - prev_len))
+    }
+
+    /// Signal that the input has ended, and decode everything pushed since the last complete
+    /// multibox.
+    pub fn finish(self) -> Result<Multibox, DecodeLegacyError> {
+        Multibox::from_legacy(&self.buf).map(|(mb, _)| mb)
+    }
+}
+
+/// An `io::Read` adapter that streams the cyphertext of a
+/// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multibox-legacy-encoding)
+/// of a `Multibox` out of the wrapped reader, decoding the base64 as it goes.
+///
+/// Reading yields the cyphertext and then signals end of input once the suffix has been
+/// consumed, leaving the wrapped reader positioned right after the encoding. Decoding errors
+/// are reported as `io::ErrorKind::InvalidData` errors wrapping a `DecodeLegacyError`.
+#[derive(Debug)]
+pub struct LegacyReader<R> {
+    inner: R,
+    max_len: Option<usize>,
+    // The base64 bytes read so far, excluding those of the current quad.
+    data_len: usize,
+    quad: [u8; 4],
+    quad_len: usize,
+    // Decoded bytes that have not been handed out yet.
+    decoded: [u8; 3],
+    decoded_start: usize,
+    decoded_end: usize,
+    // Set once a quad contained padding, after which only the suffix may follow.
+    padded: bool,
+    // Set once the suffix has been read.
+    id: Option<u64>,
+}
+
+impl<R: io::BufRead> LegacyReader<R> {
+    /// Wrap a reader that is positioned at the start of a legacy encoding, rejecting base64
+    /// longer than `DEFAULT_MAX_LEN`.
+    pub fn new(inner: R) -> LegacyReader<R> {
+        LegacyReader::with_max_len(inner, Some(DEFAULT_MAX_LEN))
+    }
+
+    /// Wrap a reader like `new`, with the given limit on the length of the base64 instead.
+    pub fn with_max_len(inner: R, max_len: Option<usize>) -> LegacyReader<R> {
+        LegacyReader {
+            inner,
+            max_len,
+            data_len: 0,
+            quad: [0; 4],
+            quad_len: 0,
+            decoded: [0; 3],
+            decoded_start: 0,
+            decoded_end: 0,
+            padded: false,
+            id: None,
+        }
+    }
+
+    /// The identifier of the kind of box, once the whole encoding has been read.
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    /// Unwrap the reader. After the whole encoding has been read, it is positioned right after
+    /// the encoding.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Read the next byte without consuming it.
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.inner.fill_buf()?.first().copied())
+    }
+
+    // Read and consume the next byte.
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.peek()?;
+        if byte.is_some() {
+            self.inner.consume(1);
+        }
+        Ok(byte)
+    }
+
+    // Consume input until either some cyphertext has been decoded or the suffix has been read.
+    fn decode_step(&mut self) -> io::Result<()> {
+        loop {
+            let c = self.next_byte()?.ok_or(DecodeLegacyError::NoDot)?;
+
+            if c == 0x2E {
+                if self.quad_len != 0 {
+                    return Err(DecodeLegacyError::NoncanonicPadding.into());
+                }
+                return self.read_suffix();
+            }
+
+            if self.padded {
+                return Err(
+                    DecodeLegacyError::InvalidBase64(base64::DecodeError::InvalidByte(
+                        self.data_len - 1,
+                        0x3D,
+                    ))
+                    .into(),
+                );
+            }
+
+            if let Some(max) = self.max_len {
+                if self.data_len + self.quad_len >= max {
+                    return Err(DecodeLegacyError::TooLong.into());
+                }
+            }
+
+            self.quad[self.quad_len] = c;
+            self.quad_len += 1;
+
+            if self.quad_len == 4 {
+                let len = DefaultEngine
+                    .decode_slice(&self.quad, &mut self.decoded)
+                    .map_err(|err| {
+                        DecodeLegacyError::InvalidBase64(offset_error(err, self.data_len))
+                    })?;
+                self.data_len += 4;
+                self.quad_len = 0;
+                self.padded = len < 3;
+                self.decoded_start = 0;
+                self.decoded_end = len;
+
+                if len > 0 {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Read the `box` suffix and the identifier following the dot.
+    fn read_suffix(&mut self) -> io::Result<()> {
+        for &expected in b"box" {
+            if self.next_byte()? != Some(expected) {
+                return Err(DecodeLegacyError::InvalidSuffix.into());
+            }
+        }
+
+        let mut id = Vec::with_capacity(MAX_ID_LEN);
+        while id.len() < MAX_ID_LEN {
+            let c = match self.peek()? {
+                Some(c) => c,
+                None => break,
+            };
+
+            id.push(c);
+            match decode_base32_id(&id).ok_or(DecodeLegacyError::InvalidSuffix)? {
+                (_, []) => self.inner.consume(1),
+                _ => {
+                    // Not part of the identifier, leave it in the reader.
+                    id.pop();
+                    break;
+                }
+            }
+        }
+
+        let (id, _) = decode_base32_id(&id).ok_or(DecodeLegacyError::InvalidSuffix)?;
+        self.id = Some(id);
+        Ok(())
+    }
+}
+
+impl<R: io::BufRead> io::Read for LegacyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.decoded_start == self.decoded_end {
+            if self.id.is_some() || buf.is_empty() {
+                return Ok(0);
+            }
+            self.decode_step()?;
+        }
+
+        let len = buf.len().min(self.decoded_end - self.decoded_start);
+        buf[..len].copy_from_slice(&self.decoded[self.decoded_start..self.decoded_start + len]);
+        self.decoded_start += len;
+        Ok(len)
+    }
+}
+
+// Shift the offset of a base64 error in a single quad to the offset in the whole data.
+fn offset_error(err: base64::DecodeError, offset: usize) -> base64::DecodeError {
+    match err {
+        base64::DecodeError::InvalidByte(i, b) => base64::DecodeError::InvalidByte(offset + i, b),
+        base64::DecodeError::InvalidLastSymbol(i, b) => {
+            base64::DecodeError::InvalidLastSymbol(offset + i, b)
+        }
+        err => err,
+    }
+}
+
+// Allocate a zeroed cyphertext buffer of the given length, whose size is chosen by the input,
+// failing instead of aborting if there is not enough memory.
+fn alloc_cyphertext(len: usize) -> Result<Cyphertext, DecodeLegacyError> {
+    let mut cyphertext = Cyphertext::new();
+    cyphertext
+        .try_reserve_exact(len)
+        .map_err(|_| DecodeLegacyError::AllocationFailed)?;
+    cyphertext.resize(len, 0);
+    Ok(cyphertext)
+}
+
+// Split a legacy encoding into its (normalized) base64 data and everything after the dot.
+fn split_legacy<'a>(
+    s: &'a [u8],
+    opts: &ParseOptions,
+) -> Result<(Cow<'a, [u8]>, &'a [u8]), DecodeLegacyError> {
+    let (data, suffix) = split_at_byte(s, 0x2E).ok_or(DecodeLegacyError::NoDot)?;
+
+    if exceeds_max_len(data, opts) {
+        return Err(DecodeLegacyError::TooLong);
+    }
+
+    Ok((normalize_base64(data, opts), suffix))
+}
+
+// Decode the base64 data of a legacy encoding into `out`, which must hold at least three bytes
+// per four bytes of data, and parse the suffix. Returns the id, the length of the cyphertext
+// and the remaining input.
+fn decode_legacy_parts<'a, E: Base64Engine + ?Sized>(
+    data: &[u8],
+    suffix: &'a [u8],
+    opts: &ParseOptions,
+    engine: &E,
+    out: &mut [u8],
+) -> Result<(u64, usize, &'a [u8]), DecodeLegacyError> {
+    let len = engine
+        .decode_slice(data, out)
+        .map_err(DecodeLegacyError::InvalidBase64)?;
+
+    if !data.len().is_multiple_of(4) {
+        return Err(DecodeLegacyError::NoncanonicPadding);
+    }
+
+    let tail = skip_suffix(suffix, b"box", opts).ok_or(DecodeLegacyError::InvalidSuffix)?;
+    let (id, tail) = decode_base32_id(tail).ok_or(DecodeLegacyError::InvalidSuffix)?;
+    Ok((id, len, tail))
+}
 
 // Decode the legacy format id of a multibox (canonic crockford base32, no leading zeros, at most 2^64 - 1).
 // Stops decoding when encounterig end of input, a non-base32 character, or at the maximum identifier length.
 // In all these cases, it returns `Some(decoded)`, `None` is only returned if the first input
 // character is a zero or if a large identifier has a non-canonical first character.
 fn decode_base32_id(s: &[u8]) -> Option<(u64, &[u8])> {
-    if s.get(0) == Some(&0x30) {
+    if s.first() == Some(&0x30) {
         return None; // Id may not begin with a zero.
     }
 
     let mut acc: u64 = 0; // The id is built up in this variable.
 
-    for i in 0..13 {
-        // 13 is the maximum length of an identifier
+    for i in 0..MAX_ID_LEN {
         match s.get(i) {
             None => return Some((acc, &[][..])), // end of input
             Some(c) => {
@@ -186,17 +1141,21 @@ fn decode_base32_id(s: &[u8]) -> Option<(u64, &[u8])> {
     }
 
     // Reached maximum length of an identifier, return the decoded value and the remainig input.
-    return Some((acc, &s[13..]));
+    Some((acc, &s[MAX_ID_LEN..]))
 }
 
+// The maximum length of the base32 encoding of a multibox identifier.
+const MAX_ID_LEN: usize = 13;
+
 fn id_len_base32(id: u64) -> usize {
     (68 - id.leading_zeros() as usize) / 5
 }
 
-// Produces the canonical base32 encoding used for legacy multibox identifiers.
-fn encode_base32_id(id: u64) -> Vec<u8> {
+// Produces the canonical base32 encoding used for legacy multibox identifiers. No identifier
+// is longer than `MAX_ID_LEN`, so this never allocates.
+fn encode_base32_id(id: u64) -> SmallVec<[u8; MAX_ID_LEN]> {
     let len = id_len_base32(id); // how many bytes of output will this create?
-    let mut out = Vec::with_capacity(len);
+    let mut out = SmallVec::new();
 
     for i in 0..len {
         let offset = ((len - 1) - i) * 5; // offset to the least-significant bit of the five bits to encode.
@@ -242,7 +1201,7 @@ fn encode_base32_id(id: u64) -> Vec<u8> {
         out.push(symbol);
     }
 
-    return out;
+    out
 }
 
 #[test]
@@ -292,6 +1251,39 @@ fn test_from_legacy() {
     );
 }
 
+#[test]
+fn test_is_valid_legacy() {
+    let inputs: &[&[u8]] = &[
+        b"lA==.box",
+        b"lA==.boxNN",
+        b".box",
+        b"lA==.box\"",
+        b"lA=.box",
+        b"lA==.boxN0",
+        b"lA==.bo",
+        b"lA==",
+        b"l!==.box",
+    ];
+
+    for s in inputs {
+        assert_eq!(
+            Multibox::is_valid_legacy(s),
+            Multibox::from_legacy_exact(s).is_ok()
+        );
+    }
+
+    // Exactly at the default length limit, and just above it.
+    for &len in &[DEFAULT_MAX_LEN, DEFAULT_MAX_LEN + 4] {
+        let mut s = vec![b'A'; len];
+        s.extend_from_slice(b".box");
+        assert_eq!(
+            Multibox::is_valid_legacy(&s),
+            Multibox::from_legacy_exact(&s).is_ok()
+        );
+        assert_eq!(Multibox::is_valid_legacy(&s), len == DEFAULT_MAX_LEN);
+    }
+}
+
 #[test]
 fn test_to_legacy() {
     assert_eq!(Multibox::new_private_box(vec![]).to_legacy_vec(), b".box");
@@ -299,4 +1291,323 @@ fn test_to_legacy() {
         Multibox::new_multibox(0b10101_10101, vec![]).to_legacy_vec(),
         b".boxNN"
     );
+
+    // Even the longest identifier is encoded without allocating.
+    let id = encode_base32_id(u64::MAX);
+    assert_eq!(id.len(), MAX_ID_LEN);
+    assert!(!id.spilled());
+}
+
+#[test]
+fn test_fmt_legacy() {
+    for mb in &[
+        Multibox::new_private_box(vec![0x94]),
+        Multibox::new_multibox(0b10101_10101, (0..200).collect()),
+    ] {
+        assert_eq!(mb.to_string(), mb.to_legacy_string());
+    }
+}
+
+#[test]
+fn test_short() {
+    assert_eq!(Multibox::new_private_box(vec![0x94]).short(), "lA==.box");
+    assert_eq!(
+        Multibox::new_multibox(0b10101_10101, (0..200).collect()).short(),
+        "AAECAw….boxNN"
+    );
+}
+
+#[test]
+fn test_debug() {
+    assert_eq!(
+        format!("{:?}", Multibox::new_private_box(vec![0x94])),
+        "Multibox(PrivateBox, \"lA==.box\")"
+    );
+    assert_eq!(
+        format!("{:?}", Multibox::new_multibox(27, vec![0x94])),
+        "Multibox(Other(27), \"lA==.boxV\")"
+    );
+    assert_eq!(
+        format!("{:?}", MultiboxRef::new_multibox(27, &[0x94])),
+        "MultiboxRef(Other(27), \"lA==.boxV\")"
+    );
+}
+
+#[test]
+fn test_suffix_str() {
+    assert_eq!(Multibox::new_private_box(vec![]).suffix_str(), "box");
+    assert_eq!(Multibox::new_multibox(27, vec![]).suffix_str(), "boxV");
+}
+
+#[test]
+fn test_legacy_decoder() {
+    let mut dec = LegacyDecoder::new();
+    assert_eq!(dec.push(b"lA").unwrap(), Progress::NeedMoreBytes(6));
+    assert_eq!(dec.push(b"==").unwrap(), Progress::NeedMoreBytes(4));
+    assert_eq!(dec.push(b".b").unwrap(), Progress::NeedMoreBytes(2));
+    assert_eq!(dec.push(b"ox").unwrap(), Progress::NeedMoreBytes(1));
+    assert_eq!(dec.push(b"N").unwrap(), Progress::NeedMoreBytes(1));
+    assert_eq!(
+        dec.push(b"N\"lA==").unwrap(),
+        Progress::Complete(Multibox::new_multibox(0b10101_10101, vec![0x94]), 1)
+    );
+
+    // The decoder was reset, and can decode the next multibox.
+    assert_eq!(
+        dec.push(b"lA==.box\"").unwrap(),
+        Progress::Complete(Multibox::new_private_box(vec![0x94]), 8)
+    );
+    assert_eq!(
+        dec.push(b".boxF0123456789AB").unwrap(),
+        Progress::Complete(Multibox::from_legacy(b".boxF0123456789AB").unwrap().0, 17)
+    );
+
+    assert_eq!(dec.push(b"lA==.bo").unwrap(), Progress::NeedMoreBytes(1));
+    assert_eq!(dec.finish(), Err(DecodeLegacyError::InvalidSuffix));
+
+    let mut dec = LegacyDecoder::new();
+    assert_eq!(dec.push(b"lA==.box").unwrap(), Progress::NeedMoreBytes(1));
+    assert_eq!(dec.finish().unwrap(), Multibox::new_private_box(vec![0x94]));
+
+    assert!(LegacyDecoder::new().push(b"lA==.bax").is_err());
+    assert!(LegacyDecoder::new().push(b"lA==.box0").is_err());
+    assert!(LegacyDecoder::new().push(b"lB==.box\"").is_err());
+
+    // An error resets the decoder, too.
+    let mut dec = LegacyDecoder::new();
+    assert!(dec.push(b"lA==.bax").is_err());
+    assert_eq!(
+        dec.push(b"lA==.box\"").unwrap(),
+        Progress::Complete(Multibox::new_private_box(vec![0x94]), 8)
+    );
+}
+
+#[test]
+fn test_legacy_decoder_limit() {
+    // Without a dot, buffering stops at the length limit.
+    let mut dec = LegacyDecoder::new();
+    let chunk = vec![b'A'; 4096];
+    let mut result = Ok(Progress::NeedMoreBytes(0));
+    for _ in 0..=DEFAULT_MAX_LEN / chunk.len() {
+        result = dec.push(&chunk);
+        if result.is_err() {
+            break;
+        }
+    }
+    assert_eq!(result, Err(DecodeLegacyError::TooLong));
+    assert!(dec.buf.is_empty());
+
+    // A single large chunk is not buffered beyond the longest encoding.
+    let mut dec = LegacyDecoder::new();
+    assert_eq!(
+        dec.push(&vec![b'A'; 4 * DEFAULT_MAX_LEN]),
+        Err(DecodeLegacyError::TooLong)
+    );
+    assert!(dec.buf.capacity() < 2 * DEFAULT_MAX_LEN);
+
+    // The longest valid encoding is still decoded.
+    let mut enc = vec![b'A'; DEFAULT_MAX_LEN];
+    enc.extend_from_slice(b".box\"");
+    enc.extend_from_slice(&vec![b'A'; DEFAULT_MAX_LEN]);
+    let mut dec = LegacyDecoder::new();
+    assert_matches!(
+        dec.push(&enc),
+        Ok(Progress::Complete(_, len)) if len == DEFAULT_MAX_LEN + 4
+    );
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_to_legacy_async() {
+    for mb in &[
+        Multibox::new_private_box(vec![]),
+        Multibox::new_private_box((0..1000).map(|i| i as u8).collect()),
+        Multibox::new_multibox(0b10101_10101, vec![1, 2, 3, 4]),
+    ] {
+        let mut out = Vec::new();
+        futures::executor::block_on(mb.to_legacy_async(&mut out)).unwrap();
+        assert_eq!(out, mb.to_legacy_vec());
+    }
+}
+
+#[test]
+fn test_default_max_len() {
+    let mut enc = vec![b'A'; DEFAULT_MAX_LEN];
+    enc.extend_from_slice(b".box");
+    assert_eq!(
+        Multibox::from_legacy_exact(&enc).unwrap(),
+        Multibox::new_private_box(vec![0; DEFAULT_MAX_LEN / 4 * 3])
+    );
+
+    let mut enc = vec![b'A'; DEFAULT_MAX_LEN + 4];
+    enc.extend_from_slice(b".box");
+    assert_eq!(
+        Multibox::from_legacy_exact(&enc),
+        Err(DecodeLegacyError::TooLong)
+    );
+    let opts = ParseOptions {
+        max_len: None,
+        ..ParseOptions::default()
+    };
+    assert!(Multibox::from_legacy_with(&enc, &opts).is_ok());
+}
+
+#[test]
+fn test_legacy_reader() {
+    use std::io::Read;
+
+    for mb in &[
+        Multibox::new_private_box(vec![]),
+        Multibox::new_private_box((0..1000).map(|i| i as u8).collect()),
+        Multibox::new_multibox(0b10101_10101, vec![1, 2, 3, 4]),
+        Multibox::new_multibox(u64::MAX, vec![5]),
+    ] {
+        let mut enc = mb.to_legacy_vec();
+        enc.extend_from_slice(b"\"rest");
+        let mut r = &enc[..];
+        assert_eq!(&Multibox::from_legacy_reader(&mut r).unwrap(), mb);
+        assert_eq!(r, b"\"rest");
+    }
+
+    let mut reader = LegacyReader::new(io::BufReader::with_capacity(1, &b"AQID.boxN"[..]));
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).unwrap();
+    assert_eq!(out, [1, 2, 3]);
+    assert_eq!(reader.id(), Some(21));
+
+    let err = |s: &[u8]| {
+        Multibox::from_legacy_reader(s)
+            .unwrap_err()
+            .into_inner()
+            .unwrap()
+            .downcast::<DecodeLegacyError>()
+            .unwrap()
+    };
+    assert_eq!(*err(b"AQID"), DecodeLegacyError::NoDot);
+    assert_eq!(*err(b"AQ.box"), DecodeLegacyError::NoncanonicPadding);
+    assert_eq!(*err(b"AQID.bax"), DecodeLegacyError::InvalidSuffix);
+    assert_eq!(*err(b"lA==.box0"), DecodeLegacyError::InvalidSuffix);
+    assert_matches!(*err(b"lB==.box"), DecodeLegacyError::InvalidBase64(_));
+    assert_matches!(*err(b"lA==AQID.box"), DecodeLegacyError::InvalidBase64(_));
+
+    let mut reader = LegacyReader::with_max_len(&b"AQIDBAUG.box"[..], Some(4));
+    let mut out = Vec::new();
+    assert!(reader.read_to_end(&mut out).is_err());
+}
+
+#[cfg(feature = "bumpalo")]
+#[test]
+fn test_from_legacy_in() {
+    let bump = bumpalo::Bump::new();
+
+    let (mb, tail) = Multibox::from_legacy_in(b"AQID.boxN\"", &bump).unwrap();
+    assert_eq!(mb, Multibox::new_multibox(21, vec![1, 2, 3]));
+    assert_eq!(tail, b"\"");
+
+    let (mb, _) = Multibox::from_legacy_in(b"lA==.box", &bump).unwrap();
+    assert_eq!(mb.cyphertext(), [0x94]);
+    assert_eq!(mb.to_multibox(), Multibox::new_private_box(vec![0x94]));
+
+    assert_eq!(
+        Multibox::from_legacy_in(b"lA==.bax", &bump),
+        Err(DecodeLegacyError::InvalidSuffix)
+    );
+}
+
+#[test]
+fn test_inline_cyphertext() {
+    let spilled = |mb: &Multibox| match mb.0 {
+        _Multibox::PrivateBox(ref bytes) | _Multibox::Other(_, ref bytes) => bytes.spilled(),
+    };
+
+    let small = Multibox::new_private_box(vec![7; INLINE_CYPHERTEXT_LEN]);
+    let (decoded, _) = Multibox::from_legacy(&small.to_legacy_vec()).unwrap();
+    assert_eq!(decoded, small);
+    assert!(!spilled(&decoded));
+
+    let large = Multibox::new_multibox(3, vec![7; INLINE_CYPHERTEXT_LEN + 1]);
+    let (decoded, _) = Multibox::from_legacy(&large.to_legacy_vec()).unwrap();
+    assert_eq!(decoded, large);
+    assert!(spilled(&decoded));
+}
+
+#[test]
+fn test_allocation_failed() {
+    assert!(alloc_cyphertext(usize::MAX).is_err());
+    assert_eq!(
+        io::Error::from(DecodeLegacyError::AllocationFailed).kind(),
+        io::ErrorKind::OutOfMemory
+    );
+}
+
+#[test]
+fn test_from_legacy_into() {
+    let mut buf = [0u8; 16];
+    let (mb, tail) = Multibox::from_legacy_into(b"AQIDBA==.boxN\"", &mut buf).unwrap();
+    assert_eq!(mb, Multibox::new_multibox(21, vec![1, 2, 3, 4]));
+    assert_eq!(tail, b"\"");
+
+    let mut buf = [0u8; 5];
+    assert_eq!(
+        Multibox::from_legacy_into(b"AQIDBA==.box", &mut buf),
+        Err(DecodeLegacyError::BufferTooSmall(6))
+    );
+}
+
+#[test]
+fn test_private_box_len() {
+    assert_eq!(PRIVATE_BOX_MIN_LEN, 121);
+    assert_eq!(
+        private_box_cyphertext_len(100, 7),
+        100 + 24 + 32 + 7 * 49 + 16
+    );
+    assert_eq!(private_box_max_plaintext_len(515, 7), Some(100));
+    assert_eq!(private_box_max_plaintext_len(120, 1), None);
+
+    let mb = Multibox::new_private_box(vec![0; private_box_cyphertext_len(5, 2)]);
+    assert_eq!(mb.to_legacy_vec().len(), private_box_legacy_len(5, 2));
+    let mb = Multibox::new_private_box(vec![0; private_box_cyphertext_len(6, 2)]);
+    assert_eq!(mb.to_legacy_vec().len(), private_box_legacy_len(6, 2));
+}
+
+#[test]
+fn test_validate_structure() {
+    let min = Multibox::new_private_box(vec![0; PRIVATE_BOX_MIN_LEN]);
+    assert_eq!(min.validate_structure(), Ok(()));
+    assert_eq!(min.as_multibox_ref().validate_structure(), Ok(()));
+
+    let (short, _) = Multibox::from_legacy(b"lA==.box").unwrap();
+    assert_eq!(
+        short.validate_structure(),
+        Err(InvalidStructureError::TooShort {
+            min: 121,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        Multibox::new_multibox(1, vec![0x94]).validate_structure(),
+        Ok(())
+    );
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_legacy_buf() {
+    use bytes::Buf;
+
+    // The suffix continues in the next chunk, which changes the kind of box.
+    let mut chain = Buf::chain(&b"lA==.box"[..], &b"V\"tail"[..]);
+    let mb = Multibox::from_legacy_buf(&mut chain).unwrap();
+    assert_eq!(mb, Multibox::from_legacy_exact(b"lA==.boxV").unwrap());
+    assert_eq!(chain.remaining(), 5);
+
+    let mut buf = bytes::BytesMut::from(&b"lA==.box\"tail"[..]);
+    assert_eq!(
+        Multibox::from_legacy_buf(&mut buf).unwrap(),
+        Multibox::from_legacy_exact(b"lA==.box").unwrap()
+    );
+    assert_eq!(&buf[..], b"\"tail");
+
+    let mut chain = Buf::chain(&b"lA==.box"[..], &b""[..]);
+    assert!(Multibox::from_legacy_buf(&mut chain).is_ok());
 }
@@ -1,9 +1,22 @@
+use std::fmt;
 use std::io::{self, Write};
 
-use super::multikey::{self, Multikey};
+#[cfg(feature = "bytes")]
+use bytes::{Buf, BufMut};
+#[cfg(feature = "futures")]
+use futures::io::AsyncWrite;
+#[cfg(feature = "test-util")]
+use rand_core::Rng;
+use serde::ser::{Serialize, Serializer};
+
+#[cfg(feature = "bytes")]
+use super::decode_buf;
+use super::engine::{Base64Engine, DefaultEngine};
+use super::multikey::{self, Multikey, SSB_ED25519_ENCODED_LEN};
+use super::{exact, serialize_error, DecodeError, LegacyEncoding, ParseOptions};
 
 /// A multifeed that owns its data.
-#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+#[derive(PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
 pub struct Multifeed(_Multifeed);
 
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
@@ -17,28 +30,163 @@ impl Multifeed {
         Multifeed(_Multifeed::Multikey(mk))
     }
 
+    /// Create a multifeed of kind `multikey` with a random key, for tests and simulations.
+    #[cfg(feature = "test-util")]
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Multifeed {
+        Multifeed::from_multikey(Multikey::random(rng))
+    }
+
+    /// The sigil that the legacy encoding starts with, always `'@'`.
+    pub fn sigil(&self) -> char {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.sigil(),
+        }
+    }
+
+    /// The suffix of the legacy encoding (without the dot), naming the kind of feed.
+    pub fn suffix_str(&self) -> &'static str {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.suffix_str(),
+        }
+    }
+
     /// Parses a
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding)
     /// into a `Multifeed`, also returning the remaining input on success.
     pub fn from_legacy(s: &[u8]) -> Result<(Multifeed, &[u8]), DecodeLegacyError> {
-        if s.len() == 0 {
+        Multifeed::from_legacy_with(s, &ParseOptions::default())
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding)
+    /// into a `Multifeed` like `from_legacy`, but tolerating the deviations from the canonical
+    /// encoding that the options allow.
+    pub fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multifeed, &'a [u8]), DecodeLegacyError> {
+        Multifeed::from_legacy_with_engine(s, opts, &DefaultEngine)
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding)
+    /// into a `Multifeed` like `from_legacy_with`, decoding the base64 with the given engine.
+    pub fn from_legacy_with_engine<'a, E: Base64Engine + ?Sized>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+        engine: &E,
+    ) -> Result<(Multifeed, &'a [u8]), DecodeLegacyError> {
+        if s.is_empty() {
             return Err(DecodeLegacyError::UnknownKind);
         }
 
         match s[0] {
             0x40 => {
-                let (mk, tail) = Multikey::from_legacy(s)?;
-                return Ok((Multifeed::from_multikey(mk), tail));
+                let (mk, tail) = Multikey::from_legacy_with_engine(s, opts, engine)?;
+                Ok((Multifeed::from_multikey(mk), tail))
             }
-            _ => return Err(DecodeLegacyError::UnknownKind),
+            _ => Err(DecodeLegacyError::UnknownKind),
         }
     }
 
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding)
+    /// into a `Multifeed`, failing with `DecodeLegacyError::TrailingBytes` if the input
+    /// contains anything after the encoding.
+    pub fn from_legacy_exact(s: &[u8]) -> Result<Multifeed, DecodeLegacyError> {
+        exact(Multifeed::from_legacy(s), DecodeLegacyError::TrailingBytes)
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding)
+    /// that makes up the whole string into a `Multifeed`.
+    pub fn from_legacy_str(s: &str) -> Result<Multifeed, DecodeLegacyError> {
+        Multifeed::from_legacy_exact(s.as_bytes())
+    }
+
+    /// Return whether the input is exactly a valid legacy encoding of a `Multifeed`, without
+    /// allocating.
+    pub fn is_valid_legacy(s: &[u8]) -> bool {
+        Multifeed::from_legacy_exact(s).is_ok()
+    }
+
     /// Serialize a `Multifeed` into a writer, using the
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding).
     pub fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        self.to_legacy_with_engine(w, &DefaultEngine)
+    }
+
+    /// Serialize a `Multifeed` into a writer like `to_legacy`, encoding the base64 with the given
+    /// engine.
+    pub fn to_legacy_with_engine<W: Write, E: Base64Engine + ?Sized>(
+        &self,
+        w: &mut W,
+        engine: &E,
+    ) -> Result<(), io::Error> {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.to_legacy_with_engine(w, engine),
+        }
+    }
+
+    /// Write a `Multifeed` into a `fmt::Write`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding).
+    pub fn fmt_legacy<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.fmt_legacy(w),
+        }
+    }
+
+    /// Asynchronously serialize a `Multifeed` into a writer, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding).
+    #[cfg(feature = "futures")]
+    pub async fn to_legacy_async<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<(), io::Error> {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.to_legacy_async(w).await,
+        }
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding)
+    /// from the start of a `Buf` into a `Multifeed`, advancing the buffer past it on success.
+    ///
+    /// The encoding may be split across the chunks of the buffer, as in a `Chain`. Contiguous
+    /// buffers such as `BytesMut` are decoded in place, others are copied first.
+    #[cfg(feature = "bytes")]
+    pub fn from_legacy_buf<B: Buf>(buf: &mut B) -> Result<Multifeed, DecodeLegacyError> {
+        decode_buf(buf, Multifeed::from_legacy)
+    }
+
+    /// Serialize a `Multifeed` into a `BufMut`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding).
+    ///
+    /// Panics if the buffer does not have enough remaining capacity, like `BufMut::put_slice`.
+    #[cfg(feature = "bytes")]
+    pub fn to_legacy_buf<B: BufMut>(&self, buf: &mut B) {
+        self.to_legacy(&mut buf.writer()).unwrap()
+    }
+
+    /// A short form of the legacy encoding for logs and user interfaces, keeping the sigil, the
+    /// first few characters of the base64 data and the suffix, e.g. `@zurF8X….ed25519`.
+    pub fn short(&self) -> String {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.short(),
+        }
+    }
+
+    /// Like `short`, but keeping the first `n` characters of the base64 data.
+    pub fn short_n(&self, n: usize) -> String {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.short_n(n),
+        }
+    }
+
+    /// Serialize a `Multifeed` into a fixed-size array, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multifeed-legacy-encoding).
+    ///
+    /// Unlike `to_legacy_vec` and `to_legacy_string`, this does not allocate.
+    pub fn to_legacy_array(&self) -> [u8; SSB_ED25519_ENCODED_LEN] {
         match self.0 {
-            _Multifeed::Multikey(ref mk) => mk.to_legacy(w),
+            _Multifeed::Multikey(ref mk) => mk.to_legacy_array(),
         }
     }
 
@@ -57,13 +205,161 @@ impl Multifeed {
     }
 }
 
+impl LegacyEncoding for Multifeed {
+    type Error = DecodeLegacyError;
+
+    fn from_legacy(s: &[u8]) -> Result<(Multifeed, &[u8]), DecodeLegacyError> {
+        Multifeed::from_legacy(s)
+    }
+
+    fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multifeed, &'a [u8]), DecodeLegacyError> {
+        Multifeed::from_legacy_with(s, opts)
+    }
+
+    fn from_legacy_exact(s: &[u8]) -> Result<Multifeed, DecodeLegacyError> {
+        Multifeed::from_legacy_exact(s)
+    }
+
+    fn from_legacy_located(s: &[u8]) -> Result<(Multifeed, &[u8]), DecodeError<DecodeLegacyError>> {
+        Multifeed::from_legacy(s).map_err(|err| DecodeError {
+            offset: legacy_error_offset(&err, s),
+            kind: err,
+        })
+    }
+
+    fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        Multifeed::to_legacy(self, w)
+    }
+}
+
+/// Formats the kind of feed and its legacy encoding, e.g. `Multifeed(Multikey, "@...=.ed25519")`.
+impl fmt::Debug for Multifeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            _Multifeed::Multikey(_) => f
+                .debug_tuple("Multifeed")
+                .field(&format_args!("Multikey"))
+                .field(&self.to_legacy_string())
+                .finish(),
+        }
+    }
+}
+
+/// Formats as the legacy encoding, without allocating.
+impl fmt::Display for Multifeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_legacy(f)
+    }
+}
+
+impl From<&Multifeed> for String {
+    fn from(value: &Multifeed) -> String {
+        value.to_legacy_string()
+    }
+}
+
+impl From<&Multifeed> for Vec<u8> {
+    fn from(value: &Multifeed) -> Vec<u8> {
+        value.to_legacy_vec()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Multifeed {
+    fn format(&self, f: defmt::Formatter) {
+        match self.0 {
+            _Multifeed::Multikey(ref mk) => mk.format(f),
+        }
+    }
+}
+
 /// Everything that can go wrong when decoding a `Multikey` from the legacy encoding.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum DecodeLegacyError {
     /// Input did not start with the `"@"` sigil.
     UnknownKind,
     /// Decoding the inner multikey failed.
     Multikey(multikey::DecodeLegacyError),
+    /// The input contained more than just the encoding.
+    TrailingBytes,
+}
+
+// The offset into `s` at which decoding a multifeed failed with the given error.
+fn legacy_error_offset(err: &DecodeLegacyError, s: &[u8]) -> usize {
+    match *err {
+        DecodeLegacyError::UnknownKind => 0,
+        DecodeLegacyError::Multikey(ref err) => multikey::legacy_error_offset(err, s, 1),
+        DecodeLegacyError::TrailingBytes => Multifeed::from_legacy(s)
+            .map(|(_, tail)| s.len() - tail.len())
+            .unwrap_or_else(|_| s.len()),
+    }
+}
+
+impl DecodeLegacyError {
+    /// A stable numeric code identifying the kind of this error. Codes are unique across all
+    /// error types of this crate, and are never changed or reused. Errors of the inner
+    /// multikey report the code of that error.
+    pub fn error_code(&self) -> u16 {
+        match *self {
+            DecodeLegacyError::UnknownKind => 501,
+            DecodeLegacyError::Multikey(ref err) => err.error_code(),
+            DecodeLegacyError::TrailingBytes => 502,
+        }
+    }
+
+    /// Convert into a serde deserialization error, for `Deserialize` impls that parse legacy
+    /// encodings.
+    pub fn into_de_error<E: serde::de::Error>(self) -> E {
+        E::custom(format!("Invalid multifeed: {}", self))
+    }
+}
+
+impl fmt::Display for DecodeLegacyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeLegacyError::UnknownKind => write!(f, "Unknown kind"),
+            DecodeLegacyError::Multikey(_) => write!(f, "Invalid multikey"),
+            DecodeLegacyError::TrailingBytes => write!(f, "Trailing bytes"),
+        }
+    }
+}
+
+/// Serializes as a map with the `kind` of the error (the name of its variant), its numeric `code`
+/// and its `message`. Errors of the inner multikey serialize as that error.
+impl Serialize for DecodeLegacyError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            DecodeLegacyError::UnknownKind => {
+                serialize_error(serializer, "UnknownKind", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::Multikey(ref err) => err.serialize(serializer),
+            DecodeLegacyError::TrailingBytes => {
+                serialize_error(serializer, "TrailingBytes", self.error_code(), self, &[])
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeLegacyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeLegacyError::Multikey(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeLegacyError> for io::Error {
+    fn from(err: DecodeLegacyError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
 }
 
 impl From<multikey::DecodeLegacyError> for DecodeLegacyError {
@@ -71,3 +367,14 @@ impl From<multikey::DecodeLegacyError> for DecodeLegacyError {
         DecodeLegacyError::Multikey(err)
     }
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DecodeLegacyError {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            DecodeLegacyError::UnknownKind => defmt::write!(f, "Unknown kind"),
+            DecodeLegacyError::Multikey(_) => defmt::write!(f, "Invalid multikey"),
+            DecodeLegacyError::TrailingBytes => defmt::write!(f, "Trailing bytes"),
+        }
+    }
+}
@@ -1,21 +1,26 @@
 //! Implementation of [ssb multihashes](https://spec.scuttlebutt.nz/datatypes.html#multihash).
+use std::convert::TryFrom;
 use std::fmt;
 use std::io::{self, Write};
 
 use base64;
 
 use serde::{
-    de::{Deserialize, Deserializer, Error},
+    de::{Deserialize, Deserializer},
     ser::{Serialize, Serializer},
 };
 
+#[cfg(feature = "async-hash")]
+use sodiumoxide::crypto::hash::sha256;
+
 use super::*;
 
 /// A multihash that owns its data.
-#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+#[derive(PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
 pub struct Multihash(pub Target, _Multihash);
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// What does the hash refer to?
 pub enum Target {
     /// An ssb [message](https://spec.scuttlebutt.nz/messages.html).
@@ -36,48 +41,208 @@ impl Multihash {
         Multihash(target, _Multihash::Sha256(digest))
     }
 
+    /// Take the sha256 digest of a message and turn it into an opaque `Multihash`.
+    pub fn message_from_digest(digest: [u8; 32]) -> Multihash {
+        Multihash::from_sha256(digest, Target::Message)
+    }
+
+    /// Take the sha256 digest of a blob and turn it into an opaque `Multihash`.
+    pub fn blob_from_digest(digest: [u8; 32]) -> Multihash {
+        Multihash::from_sha256(digest, Target::Blob)
+    }
+
+    /// Create a message hash with a random digest, for tests and simulations.
+    #[cfg(feature = "test-util")]
+    pub fn random_message<R: Rng + ?Sized>(rng: &mut R) -> Multihash {
+        let mut digest = [0u8; 32];
+        rng.fill_bytes(&mut digest);
+        Multihash::message_from_digest(digest)
+    }
+
+    /// Create a blob hash with a random digest, for tests and simulations.
+    #[cfg(feature = "test-util")]
+    pub fn random_blob<R: Rng + ?Sized>(rng: &mut R) -> Multihash {
+        let mut digest = [0u8; 32];
+        rng.fill_bytes(&mut digest);
+        Multihash::blob_from_digest(digest)
+    }
+
+    /// Read a blob from an async reader until it ends, and return its `Multihash`. The blob is
+    /// hashed incrementally, so it never has to be held in memory as a whole.
+    #[cfg(feature = "async-hash")]
+    pub async fn blob_from_async_read<R: AsyncRead + Unpin>(
+        r: &mut R,
+    ) -> Result<Multihash, io::Error> {
+        let mut state = sha256::State::new();
+        let mut buf = [0u8; 8192];
+
+        loop {
+            match r.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => state.update(&buf[..n]),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(Multihash::blob_from_digest(state.finalize().0))
+    }
+
+    /// What the hash refers to.
+    pub fn target(&self) -> Target {
+        self.0
+    }
+
+    /// Whether the hash refers to a message.
+    pub fn is_message(&self) -> bool {
+        self.0 == Target::Message
+    }
+
+    /// Whether the hash refers to a blob.
+    pub fn is_blob(&self) -> bool {
+        self.0 == Target::Blob
+    }
+
+    /// The sigil that the legacy encoding starts with, `'%'` for messages and `'&'` for blobs.
+    pub fn sigil(&self) -> char {
+        match self.0 {
+            Target::Message => '%',
+            Target::Blob => '&',
+        }
+    }
+
+    /// The suffix of the legacy encoding (without the dot), naming the hash function.
+    pub fn suffix_str(&self) -> &'static str {
+        match self.1 {
+            _Multihash::Sha256(_) => "sha256",
+        }
+    }
+
+    /// The sha256 digest, if this is a sha256 multihash.
+    pub fn as_sha256(&self) -> Option<&[u8; 32]> {
+        match self.1 {
+            _Multihash::Sha256(ref digest) => Some(digest),
+        }
+    }
+
+    /// The raw digest bytes as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        match self.1 {
+            _Multihash::Sha256(ref digest) => hex::encode_hex(digest),
+        }
+    }
+
+    /// Create a sha256 `Multihash` from the hex encoding of its digest.
+    pub fn from_hex(s: &str, target: Target) -> Result<Multihash, hex::DecodeHexError> {
+        let mut digest = [0u8; 32];
+        hex::decode_hex(s, &mut digest)?;
+        Ok(Multihash::from_sha256(digest, target))
+    }
+
     /// Parses a
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding)
     /// into a `Multihash`.
-    pub fn from_legacy(mut s: &[u8]) -> Result<(Multihash, &[u8]), DecodeLegacyError> {
+    pub fn from_legacy(s: &[u8]) -> Result<(Multihash, &[u8]), DecodeLegacyError> {
+        Multihash::from_legacy_with(s, &ParseOptions::default())
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding)
+    /// into a `Multihash` like `from_legacy`, but tolerating the deviations from the canonical
+    /// encoding that the options allow.
+    pub fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multihash, &'a [u8]), DecodeLegacyError> {
+        Multihash::from_legacy_with_engine(s, opts, &DefaultEngine)
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding)
+    /// into a `Multihash` like `from_legacy_with`, decoding the base64 with the given engine.
+    pub fn from_legacy_with_engine<'a, E: Base64Engine + ?Sized>(
+        mut s: &'a [u8],
+        opts: &ParseOptions,
+        engine: &E,
+    ) -> Result<(Multihash, &'a [u8]), DecodeLegacyError> {
         let target;
 
         if let Some(tail) = skip_prefix(s, b"%") {
             s = tail;
             target = Target::Message;
         } else {
-            let tail = skip_prefix(s, b"&").ok_or_else(|| DecodeLegacyError::Sigil)?;
+            let tail = skip_prefix(s, b"&").ok_or(DecodeLegacyError::Sigil)?;
 
             s = tail;
             target = Target::Blob;
         }
 
-        let (data, suffix) = split_at_byte(s, 0x2E).ok_or_else(|| DecodeLegacyError::NoDot)?;
+        let (data, suffix) = split_at_byte(s, 0x2E).ok_or(DecodeLegacyError::NoDot)?;
 
         let tail =
-            skip_prefix(suffix, SHA256_SUFFIX).ok_or_else(|| DecodeLegacyError::UnknownSuffix)?;
+            skip_suffix(suffix, SHA256_SUFFIX, opts).ok_or(DecodeLegacyError::UnknownSuffix)?;
 
-        if data.len() != SHA256_BASE64_LEN {
-            return Err(DecodeLegacyError::Sha256WrongSize);
+        if exceeds_max_len(data, opts) {
+            return Err(DecodeLegacyError::TooLong);
         }
 
-        if data[SHA256_BASE64_LEN - 2] == b"="[0] {
-            return Err(DecodeLegacyError::Sha256WrongSize);
+        let data = normalize_base64(data, opts);
+
+        let actual = base64_decoded_len(&data);
+        if actual != 32 {
+            return Err(DecodeLegacyError::WrongLength {
+                expected: 32,
+                actual,
+            });
         }
 
-        if data[SHA256_BASE64_LEN - 1] != b"="[0] {
-            return Err(DecodeLegacyError::Sha256WrongSize);
+        if data.len() != SHA256_BASE64_LEN {
+            return Err(DecodeLegacyError::InvalidBase64(
+                base64::DecodeError::InvalidLength,
+            ));
         }
 
         let mut dec_data = [0u8; 32];
-        base64::decode_config_slice(data, base64::STANDARD, &mut dec_data[..])
-            .map_err(|e| DecodeLegacyError::InvalidBase64(e))
+        engine
+            .decode_slice(&data, &mut dec_data[..])
+            .map_err(DecodeLegacyError::InvalidBase64)
             .map(|_| (Multihash(target, _Multihash::Sha256(dec_data)), tail))
     }
 
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding)
+    /// into a `Multihash`, failing with `DecodeLegacyError::TrailingBytes` if the input
+    /// contains anything after the encoding.
+    pub fn from_legacy_exact(s: &[u8]) -> Result<Multihash, DecodeLegacyError> {
+        exact(Multihash::from_legacy(s), DecodeLegacyError::TrailingBytes)
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding)
+    /// that makes up the whole string into a `Multihash`.
+    pub fn from_legacy_str(s: &str) -> Result<Multihash, DecodeLegacyError> {
+        Multihash::from_legacy_exact(s.as_bytes())
+    }
+
+    /// Return whether the input is exactly a valid legacy encoding of a `Multihash`, without
+    /// allocating.
+    pub fn is_valid_legacy(s: &[u8]) -> bool {
+        Multihash::from_legacy_exact(s).is_ok()
+    }
+
     /// Serialize a `Multihash` into a writer, using the
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding).
     pub fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        self.to_legacy_with_engine(w, &DefaultEngine)
+    }
+
+    /// Serialize a `Multihash` into a writer like `to_legacy`, encoding the base64 with the given
+    /// engine.
+    pub fn to_legacy_with_engine<W: Write, E: Base64Engine + ?Sized>(
+        &self,
+        w: &mut W,
+        engine: &E,
+    ) -> Result<(), io::Error> {
         match self.1 {
             _Multihash::Sha256(ref bytes) => {
                 match self.0 {
@@ -85,8 +250,7 @@ impl Multihash {
                     Target::Blob => w.write_all(b"&")?,
                 }
 
-                let data = base64::encode_config(bytes, base64::STANDARD);
-                w.write_all(data.as_bytes())?;
+                write_base64(w, bytes, engine)?;
 
                 w.write_all(b".")?;
                 w.write_all(SHA256_SUFFIX)
@@ -94,6 +258,95 @@ impl Multihash {
         }
     }
 
+    /// Write a `Multihash` into a `fmt::Write`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding).
+    pub fn fmt_legacy<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        match self.1 {
+            _Multihash::Sha256(ref bytes) => {
+                match self.0 {
+                    Target::Message => w.write_str("%")?,
+                    Target::Blob => w.write_str("&")?,
+                }
+
+                fmt_base64(w, bytes)?;
+                w.write_str(".sha256")
+            }
+        }
+    }
+
+    /// Asynchronously serialize a `Multihash` into a writer, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding).
+    #[cfg(feature = "futures")]
+    pub async fn to_legacy_async<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<(), io::Error> {
+        match self.1 {
+            _Multihash::Sha256(_) => {
+                let mut buf = [0u8; SSB_SHA256_ENCODED_LEN];
+                self.to_legacy(&mut io::Cursor::new(&mut buf[..]))?;
+                w.write_all(&buf).await
+            }
+        }
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding)
+    /// from the start of a `Buf` into a `Multihash`, advancing the buffer past it on success.
+    ///
+    /// The encoding may be split across the chunks of the buffer, as in a `Chain`. Contiguous
+    /// buffers such as `BytesMut` are decoded in place, others are copied first.
+    #[cfg(feature = "bytes")]
+    pub fn from_legacy_buf<B: Buf>(buf: &mut B) -> Result<Multihash, DecodeLegacyError> {
+        decode_buf(buf, Multihash::from_legacy)
+    }
+
+    /// Serialize a `Multihash` into a `BufMut`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding).
+    ///
+    /// Panics if the buffer does not have enough remaining capacity, like `BufMut::put_slice`.
+    #[cfg(feature = "bytes")]
+    pub fn to_legacy_buf<B: BufMut>(&self, buf: &mut B) {
+        self.to_legacy(&mut buf.writer()).unwrap()
+    }
+
+    /// A short form of the legacy encoding for logs and user interfaces, keeping the sigil, the
+    /// first few characters of the base64 data and the suffix, e.g. `%MwjdLV….sha256`.
+    pub fn short(&self) -> String {
+        self.short_n(SHORT_LEN)
+    }
+
+    /// Like `short`, but keeping the first `n` characters of the base64 data.
+    pub fn short_n(&self, n: usize) -> String {
+        let mut s = String::new();
+        let sigil = match self.0 {
+            Target::Message => "%",
+            Target::Blob => "&",
+        };
+        match self.1 {
+            _Multihash::Sha256(ref bytes) => fmt_short(&mut s, sigil, bytes, "sha256", n).unwrap(),
+        }
+        s
+    }
+
+    /// A stable 64 bit hash of the digest, for partitioning messages and blobs across shards
+    /// and for probabilistic data structures. Unlike `Hash`, it is the same in every process
+    /// and on every platform: the 64 bit [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/)
+    /// hash of the raw 32 bytes of the sha256 digest. The target is not part of it, so a message
+    /// and a blob with the same digest have the same short hash.
+    pub fn short_hash(&self) -> u64 {
+        match self.1 {
+            _Multihash::Sha256(ref bytes) => fnv1a(&[&bytes[..]]),
+        }
+    }
+
+    /// Serialize a `Multihash` into a fixed-size array, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding).
+    ///
+    /// Unlike `to_legacy_vec` and `to_legacy_string`, this does not allocate.
+    pub fn to_legacy_array(&self) -> [u8; SSB_SHA256_ENCODED_LEN] {
+        let mut out = [0u8; SSB_SHA256_ENCODED_LEN];
+        self.to_legacy(&mut &mut out[..]).unwrap();
+        out
+    }
+
     /// Serialize a `Multihash` into an owned byte vector, using the
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multihash-legacy-encoding).
     pub fn to_legacy_vec(&self) -> Vec<u8> {
@@ -113,12 +366,42 @@ impl Multihash {
     }
 }
 
+impl LegacyEncoding for Multihash {
+    type Error = DecodeLegacyError;
+
+    fn from_legacy(s: &[u8]) -> Result<(Multihash, &[u8]), DecodeLegacyError> {
+        Multihash::from_legacy(s)
+    }
+
+    fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multihash, &'a [u8]), DecodeLegacyError> {
+        Multihash::from_legacy_with(s, opts)
+    }
+
+    fn from_legacy_exact(s: &[u8]) -> Result<Multihash, DecodeLegacyError> {
+        Multihash::from_legacy_exact(s)
+    }
+
+    fn from_legacy_located(s: &[u8]) -> Result<(Multihash, &[u8]), DecodeError<DecodeLegacyError>> {
+        Multihash::from_legacy(s).map_err(|err| DecodeError {
+            offset: legacy_error_offset(&err, s),
+            kind: err,
+        })
+    }
+
+    fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        Multihash::to_legacy(self, w)
+    }
+}
+
 impl Serialize for Multihash {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_legacy_string())
+        serializer.serialize_str(std::str::from_utf8(&self.to_legacy_array()).unwrap())
     }
 }
 
@@ -128,14 +411,154 @@ impl<'de> Deserialize<'de> for Multihash {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Multihash::from_legacy(&s.as_bytes())
+        Multihash::from_legacy(s.as_bytes())
             .map(|(mh, _)| mh)
-            .map_err(|err| D::Error::custom(format!("Invalid multihash: {}", err)))
+            .map_err(DecodeLegacyError::into_de_error)
+    }
+}
+
+/// Formats the target and the legacy encoding, e.g. `Multihash(Message, "%...=.sha256")`.
+impl fmt::Debug for Multihash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Multihash")
+            .field(&self.0)
+            .field(&self.to_legacy_string())
+            .finish()
+    }
+}
+
+/// Formats as the legacy encoding, without allocating.
+impl fmt::Display for Multihash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_legacy(f)
+    }
+}
+
+impl From<&Multihash> for String {
+    fn from(value: &Multihash) -> String {
+        value.to_legacy_string()
+    }
+}
+
+impl From<&Multihash> for Vec<u8> {
+    fn from(value: &Multihash) -> Vec<u8> {
+        value.to_legacy_vec()
+    }
+}
+
+/// A multihash that borrows its digest, e.g. a hash inside a decoded buffer or a memory-mapped
+/// index. It compares and orders exactly like the `Multihash` it corresponds to.
+///
+/// `Borrow` can not hand out a type with a lifetime, so conversions go through
+/// `Multihash::as_multihash_ref` and `MultihashRef::to_multihash` (or the `From` impls) instead.
+#[derive(PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct MultihashRef<'a>(pub Target, _MultihashRef<'a>);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+enum _MultihashRef<'a> {
+    Sha256(&'a [u8; 32]),
+}
+
+impl<'a> MultihashRef<'a> {
+    /// Borrow a sha256 digest as a `MultihashRef`.
+    pub fn from_sha256(digest: &'a [u8; 32], target: Target) -> MultihashRef<'a> {
+        MultihashRef(target, _MultihashRef::Sha256(digest))
+    }
+
+    /// Borrow a sha256 digest as a `MultihashRef`, or return `None` if the slice is not exactly
+    /// 32 bytes long.
+    pub fn from_sha256_slice(digest: &'a [u8], target: Target) -> Option<MultihashRef<'a>> {
+        <&[u8; 32]>::try_from(digest)
+            .ok()
+            .map(|digest| MultihashRef::from_sha256(digest, target))
+    }
+
+    /// What the hash refers to.
+    pub fn target(&self) -> Target {
+        self.0
+    }
+
+    /// The raw digest if it is a sha256 hash.
+    pub fn as_sha256(&self) -> Option<&'a [u8; 32]> {
+        match self.1 {
+            _MultihashRef::Sha256(digest) => Some(digest),
+        }
+    }
+
+    /// Copy the hash into an owned `Multihash`.
+    pub fn to_multihash(&self) -> Multihash {
+        match self.1 {
+            _MultihashRef::Sha256(digest) => Multihash::from_sha256(*digest, self.0),
+        }
+    }
+}
+
+impl Multihash {
+    /// Borrow the hash as a `MultihashRef`.
+    pub fn as_multihash_ref(&self) -> MultihashRef<'_> {
+        match self.1 {
+            _Multihash::Sha256(ref digest) => MultihashRef::from_sha256(digest, self.0),
+        }
+    }
+}
+
+/// Formats like the `Debug` of a `Multihash`, e.g. `MultihashRef(Message, "%...=.sha256")`.
+impl fmt::Debug for MultihashRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("MultihashRef")
+            .field(&self.0)
+            .field(&self.to_string())
+            .finish()
+    }
+}
+
+/// Formats as the legacy encoding.
+impl fmt::Display for MultihashRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_multihash().fmt_legacy(f)
+    }
+}
+
+impl<'a> From<&'a Multihash> for MultihashRef<'a> {
+    fn from(value: &'a Multihash) -> MultihashRef<'a> {
+        value.as_multihash_ref()
+    }
+}
+
+impl From<MultihashRef<'_>> for Multihash {
+    fn from(value: MultihashRef<'_>) -> Multihash {
+        value.to_multihash()
+    }
+}
+
+impl PartialEq<Multihash> for MultihashRef<'_> {
+    fn eq(&self, other: &Multihash) -> bool {
+        *self == other.as_multihash_ref()
+    }
+}
+
+impl PartialEq<MultihashRef<'_>> for Multihash {
+    fn eq(&self, other: &MultihashRef<'_>) -> bool {
+        self.as_multihash_ref() == *other
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Multihash {
+    fn format(&self, f: defmt::Formatter) {
+        match self.1 {
+            _Multihash::Sha256(_) => {
+                let mut buf = [0u8; SSB_SHA256_ENCODED_LEN];
+                self.to_legacy(&mut io::Cursor::new(&mut buf[..])).unwrap();
+                defmt::write!(f, "{=str}", std::str::from_utf8(&buf).unwrap())
+            }
+        }
     }
 }
 
 /// Everything that can go wrong when decoding a `Multihash` from the legacy encoding.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum DecodeLegacyError {
     /// Input did not start with the `"@"` sigil.
     Sigil,
@@ -145,30 +568,160 @@ pub enum DecodeLegacyError {
     InvalidBase64(base64::DecodeError),
     /// The suffix is not known to this ssb implementation.
     UnknownSuffix,
-    /// The suffix declares a sha256 hash, but the data length does not match.
-    Sha256WrongSize,
+    /// The suffix declares a sha256 hash, but the data decodes to the wrong number of bytes.
+    WrongLength {
+        /// The number of bytes the suffix calls for.
+        expected: usize,
+        /// The number of bytes the data decodes to.
+        actual: usize,
+    },
+    /// The input contained more than just the encoding.
+    TrailingBytes,
+    /// The base64 portion of the hash was longer than the length limit of the `ParseOptions`.
+    TooLong,
+}
+
+// The offset into `s` at which decoding a multihash failed with the given error.
+fn legacy_error_offset(err: &DecodeLegacyError, s: &[u8]) -> usize {
+    match *err {
+        DecodeLegacyError::Sigil => 0,
+        DecodeLegacyError::NoDot => s.len(),
+        DecodeLegacyError::InvalidBase64(ref err) => 1 + base64_error_offset(err),
+        DecodeLegacyError::UnknownSuffix => 1 + suffix_offset(&s[1..]),
+        DecodeLegacyError::WrongLength { .. } => 1,
+        DecodeLegacyError::TrailingBytes => 1 + suffix_offset(&s[1..]) + SHA256_SUFFIX.len(),
+        DecodeLegacyError::TooLong => 1,
+    }
+}
+
+impl DecodeLegacyError {
+    /// A stable numeric code identifying the kind of this error. Codes are unique across all
+    /// error types of this crate, and are never changed or reused.
+    pub fn error_code(&self) -> u16 {
+        match *self {
+            DecodeLegacyError::Sigil => 301,
+            DecodeLegacyError::NoDot => 302,
+            DecodeLegacyError::InvalidBase64(_) => 303,
+            DecodeLegacyError::UnknownSuffix => 304,
+            DecodeLegacyError::WrongLength { .. } => 305,
+            DecodeLegacyError::TrailingBytes => 306,
+            DecodeLegacyError::TooLong => 307,
+        }
+    }
+
+    /// Convert into a serde deserialization error, for `Deserialize` impls that parse legacy
+    /// encodings.
+    pub fn into_de_error<E: serde::de::Error>(self) -> E {
+        E::custom(format!("Invalid multihash: {}", self))
+    }
 }
 
 impl fmt::Display for DecodeLegacyError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &DecodeLegacyError::Sigil => write!(f, "Invalid sigil"),
-            &DecodeLegacyError::InvalidBase64(ref err) => write!(f, "{}", err),
-            &DecodeLegacyError::NoDot => write!(f, "No dot"),
-            &DecodeLegacyError::UnknownSuffix => write!(f, "Unknown suffix"),
-            &DecodeLegacyError::Sha256WrongSize => write!(f, "Data of wrong length"),
+        match *self {
+            DecodeLegacyError::Sigil => write!(f, "Invalid sigil"),
+            DecodeLegacyError::InvalidBase64(_) => write!(f, "Invalid base64"),
+            DecodeLegacyError::NoDot => write!(f, "No dot"),
+            DecodeLegacyError::UnknownSuffix => write!(f, "Unknown suffix"),
+            DecodeLegacyError::WrongLength { expected, actual } => {
+                write!(
+                    f,
+                    "Data of wrong length: expected {} bytes, got {}",
+                    expected, actual
+                )
+            }
+            DecodeLegacyError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeLegacyError::TooLong => write!(f, "Too long"),
         }
     }
 }
 
-impl std::error::Error for DecodeLegacyError {}
+/// Serializes as a map with the `kind` of the error (the name of its variant), its numeric `code`
+/// and its `message`, plus the fields of the variant: the `base64_offset` for
+/// `InvalidBase64`, which counts from the start of the base64 data rather than from the start of
+/// the input (`from_legacy_located` reports the latter), and `expected` and `actual` for `WrongLength`.
+impl Serialize for DecodeLegacyError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            DecodeLegacyError::Sigil => {
+                serialize_error(serializer, "Sigil", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::NoDot => {
+                serialize_error(serializer, "NoDot", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::InvalidBase64(ref err) => serialize_error(
+                serializer,
+                "InvalidBase64",
+                self.error_code(),
+                self,
+                &[("base64_offset", base64_error_offset(err))],
+            ),
+            DecodeLegacyError::UnknownSuffix => {
+                serialize_error(serializer, "UnknownSuffix", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::WrongLength { expected, actual } => serialize_error(
+                serializer,
+                "WrongLength",
+                self.error_code(),
+                self,
+                &[("expected", expected), ("actual", actual)],
+            ),
+            DecodeLegacyError::TrailingBytes => {
+                serialize_error(serializer, "TrailingBytes", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::TooLong => {
+                serialize_error(serializer, "TooLong", self.error_code(), self, &[])
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeLegacyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeLegacyError::InvalidBase64(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeLegacyError> for io::Error {
+    fn from(err: DecodeLegacyError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DecodeLegacyError {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            DecodeLegacyError::Sigil => defmt::write!(f, "Invalid sigil"),
+            DecodeLegacyError::InvalidBase64(_) => defmt::write!(f, "Invalid base64"),
+            DecodeLegacyError::NoDot => defmt::write!(f, "No dot"),
+            DecodeLegacyError::UnknownSuffix => defmt::write!(f, "Unknown suffix"),
+            DecodeLegacyError::WrongLength { expected, actual } => {
+                defmt::write!(
+                    f,
+                    "Data of wrong length: expected {} bytes, got {}",
+                    expected,
+                    actual
+                )
+            }
+            DecodeLegacyError::TrailingBytes => defmt::write!(f, "Trailing bytes"),
+            DecodeLegacyError::TooLong => defmt::write!(f, "Too long"),
+        }
+    }
+}
 
 /// The legacy suffix indicating the sha256 cryptographic primitive.
-const SHA256_SUFFIX: &'static [u8] = b"sha256";
+const SHA256_SUFFIX: &[u8] = b"sha256";
 /// Length of a base64 encoded sha256 hash digest.
 const SHA256_BASE64_LEN: usize = 44;
 /// Length of a legacy-encoded ssb `Multihash` which uses the sha256 cryptographic primitive.
-const SSB_SHA256_ENCODED_LEN: usize = SHA256_BASE64_LEN + 9;
+pub(crate) const SSB_SHA256_ENCODED_LEN: usize = SHA256_BASE64_LEN + 8;
 
 #[test]
 fn test_from_legacy() {
@@ -197,3 +750,154 @@ fn test_from_legacy() {
         Multihash::from_legacy(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc==.sha256").is_err()
     );
 }
+
+#[test]
+fn test_to_legacy() {
+    let mh = Multihash::from_sha256([0; 32], Target::Message);
+    let enc = mh.to_legacy_vec();
+    assert_eq!(enc.len(), SSB_SHA256_ENCODED_LEN);
+    assert_eq!(&mh.to_legacy_array()[..], &enc[..]);
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_to_legacy_async() {
+    let mh =
+        Multihash::from_legacy_str("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256").unwrap();
+    let mut out = Vec::new();
+    futures::executor::block_on(mh.to_legacy_async(&mut out)).unwrap();
+    assert_eq!(out, mh.to_legacy_vec());
+}
+
+#[test]
+fn test_from_legacy_with() {
+    let url_safe = b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0rc=.sha256";
+    assert!(Multihash::from_legacy(url_safe).is_err());
+
+    let opts = ParseOptions {
+        url_safe_alphabet: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(
+        Multihash::from_legacy_with(url_safe, &opts).unwrap().0,
+        Multihash::from_legacy_exact(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256")
+            .unwrap()
+    );
+
+    // Url-safe characters alone do not make the padding optional.
+    assert!(Multihash::from_legacy_with(
+        b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc-0rc.sha256",
+        &opts
+    )
+    .is_err());
+}
+
+#[test]
+fn test_from_legacy_str() {
+    let mh =
+        Multihash::from_legacy_str("&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256").unwrap();
+    assert_eq!(mh.0, Target::Blob);
+    assert_eq!(
+        Multihash::from_legacy_str("&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256\""),
+        Err(DecodeLegacyError::TrailingBytes)
+    );
+}
+
+#[test]
+fn test_debug() {
+    let mh = Multihash::from_sha256([0; 32], Target::Message);
+    assert_eq!(
+        format!("{:?}", mh),
+        "Multihash(Message, \"%AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.sha256\")"
+    );
+    assert_eq!(
+        format!("{:?}", mh.as_multihash_ref()),
+        "MultihashRef(Message, \"%AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.sha256\")"
+    );
+}
+
+#[test]
+fn test_target() {
+    let mh = Multihash::blob_from_digest([0; 32]);
+    assert_eq!(mh.target(), Target::Blob);
+    assert!(mh.is_blob());
+    assert!(!mh.is_message());
+    assert_eq!(mh, Multihash::from_sha256([0; 32], Target::Blob));
+
+    assert!(Multihash::message_from_digest([0; 32]).is_message());
+}
+
+#[test]
+fn test_sigil_and_suffix() {
+    let mh = Multihash::blob_from_digest([0; 32]);
+    assert_eq!(format!("{}.{}", mh.sigil(), mh.suffix_str()), "&.sha256");
+    assert!(mh
+        .to_legacy_string()
+        .ends_with(&format!(".{}", mh.suffix_str())));
+}
+
+#[cfg(feature = "async-hash")]
+#[test]
+fn test_blob_from_async_read() {
+    let blob: Vec<u8> = (0..20000u32).map(|i| i as u8).collect();
+    let mh = futures::executor::block_on(Multihash::blob_from_async_read(
+        &mut futures::io::Cursor::new(&blob),
+    ))
+    .unwrap();
+    assert_eq!(mh, Multihash::blob_from_digest(sha256::hash(&blob).0));
+
+    let empty = futures::executor::block_on(Multihash::blob_from_async_read(
+        &mut futures::io::Cursor::new(&[][..]),
+    ))
+    .unwrap();
+    assert_eq!(
+        empty.to_legacy_string(),
+        "&47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=.sha256"
+    );
+}
+
+#[test]
+fn test_into_string() {
+    let mh = Multihash::blob_from_digest([0; 32]);
+    let s: String = (&mh).into();
+    let v: Vec<u8> = (&mh).into();
+    assert_eq!(s, "&AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.sha256");
+    assert_eq!(v, s.into_bytes());
+}
+
+#[test]
+fn test_multihash_ref() {
+    let mh = Multihash::from_legacy_exact(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256")
+        .unwrap();
+    let buf = mh.as_sha256().unwrap().to_vec();
+
+    let mh_ref = MultihashRef::from_sha256_slice(&buf, Target::Message).unwrap();
+    assert_eq!(mh_ref, mh);
+    assert_eq!(mh_ref.to_multihash(), mh);
+    assert_eq!(mh_ref.to_string(), mh.to_string());
+    assert_ne!(
+        MultihashRef::from_sha256_slice(&buf, Target::Blob).unwrap(),
+        mh
+    );
+    assert_eq!(
+        MultihashRef::from_sha256_slice(&buf[1..], Target::Message),
+        None
+    );
+
+    let blob = Multihash::blob_from_digest([0; 32]);
+    assert_eq!(mh_ref.cmp(&blob.as_multihash_ref()), mh.cmp(&blob));
+}
+
+#[test]
+fn test_short_hash() {
+    let mh = Multihash::message_from_digest([7; 32]);
+    assert_eq!(mh.short_hash(), 0xf160_fd08_b6d9_aac5);
+    assert_eq!(
+        Multihash::blob_from_digest([7; 32]).short_hash(),
+        mh.short_hash()
+    );
+    assert_eq!(
+        Multihash::message_from_digest([0; 32]).short_hash(),
+        0x0c82_1078_4d8a_f5a5
+    );
+}
@@ -1,19 +1,24 @@
 //! Implementation of [ssb multikeys](https://spec.scuttlebutt.nz/datatypes.html#multikey).
+#[cfg(feature = "multisig")]
 use std::cmp::{Eq, Ord, Ordering, PartialEq, PartialOrd};
+use std::convert::TryFrom;
 use std::fmt;
 use std::io::{self, Cursor, Write};
 
 use base64;
 use serde::{
-    de::{Deserialize, Deserializer, Error},
+    de::{Deserialize, Deserializer},
     ser::{Serialize, Serializer},
 };
-use ssb_crypto::{verify_detached, PublicKey, SecretKey, Signature, SECRETKEYBYTES};
+use sodiumoxide::crypto::sign;
+#[cfg(feature = "multisig")]
+use ssb_crypto::{sign_detached, verify_detached, NetworkKey, Signature};
+use ssb_crypto::{PublicKey, SecretKey, SECRETKEYBYTES};
 
 use super::*;
 
 /// A multikey that owns its data.
-#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+#[derive(PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
 pub struct Multikey(_Multikey);
 
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
@@ -32,52 +37,212 @@ impl Multikey {
         Multikey(_Multikey::Ed25519(PublicKey::from_slice(pk).unwrap()))
     }
 
-    pub fn into_ed25519_public_key(self) -> Option<PublicKey>{
+    /// Derive the ed25519 public key of the given 32 byte seed. The same seed always yields the
+    /// same key, `Multisecret::from_seed` yields the matching secret key.
+    pub fn from_seed(seed: &[u8; 32]) -> Multikey {
+        let (pk, _) = sign::keypair_from_seed(&sign::Seed(*seed));
+        Multikey(_Multikey::Ed25519(pk))
+    }
+
+    /// Create an ed25519 `Multikey` from random bytes, for tests and simulations. The result is
+    /// not necessarily a valid curve point, and there is no corresponding secret key.
+    #[cfg(feature = "test-util")]
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Multikey {
+        let mut pk = [0u8; 32];
+        rng.fill_bytes(&mut pk);
+        Multikey::from_ed25519(&pk)
+    }
+
+    pub fn into_ed25519_public_key(self) -> Option<PublicKey> {
         match self {
             Multikey(_Multikey::Ed25519(pk)) => Some(pk),
-            _ => None
+        }
+    }
+
+    /// The raw bytes of the key if it is an ed25519 public key.
+    pub fn as_ed25519(&self) -> Option<&[u8]> {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => Some(&pk[..]),
+        }
+    }
+
+    /// The raw key bytes as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => hex::encode_hex(&pk[..]),
+        }
+    }
+
+    /// Create an ed25519 `Multikey` from the hex encoding of the public key.
+    pub fn from_hex(s: &str) -> Result<Multikey, hex::DecodeHexError> {
+        let mut pk = [0u8; 32];
+        hex::decode_hex(s, &mut pk)?;
+        Ok(Multikey::from_ed25519(&pk))
+    }
+
+    /// The key in the OpenSSH public key format, `ssh-ed25519 AAAA...`, without a comment.
+    pub fn to_openssh(&self) -> String {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => openssh::encode_openssh(&pk.0),
+        }
+    }
+
+    /// Create an ed25519 `Multikey` from a key in the OpenSSH public key format, such as a line
+    /// of an `authorized_keys` file without options. A trailing comment is ignored.
+    pub fn from_openssh(s: &str) -> Result<Multikey, openssh::DecodeOpenSshError> {
+        openssh::decode_openssh(s).map(|pk| Multikey::from_ed25519(&pk))
+    }
+
+    /// The DER encoding of the key as a SubjectPublicKeyInfo.
+    #[cfg(feature = "pem")]
+    pub fn to_spki_der(&self) -> Vec<u8> {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => pem::encode_spki(&pk.0),
+        }
+    }
+
+    /// Create an ed25519 `Multikey` from the DER encoding of a SubjectPublicKeyInfo.
+    #[cfg(feature = "pem")]
+    pub fn from_spki_der(der: &[u8]) -> Result<Multikey, pem::DecodePemError> {
+        pem::decode_spki(der).map(|pk| Multikey::from_ed25519(&pk))
+    }
+
+    /// The key as a `PUBLIC KEY` PEM block.
+    #[cfg(feature = "pem")]
+    pub fn to_spki_pem(&self) -> String {
+        pem::encode_pem(&self.to_spki_der(), pem::PUBLIC_KEY_LABEL)
+    }
+
+    /// Create an ed25519 `Multikey` from a `PUBLIC KEY` PEM block.
+    #[cfg(feature = "pem")]
+    pub fn from_spki_pem(s: &str) -> Result<Multikey, pem::DecodePemError> {
+        Multikey::from_spki_der(&pem::decode_pem(s, pem::PUBLIC_KEY_LABEL)?)
+    }
+
+    /// The raw key bytes as lowercase base32 without padding, which can be used as a DNS label.
+    pub fn to_dns_label(&self) -> String {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => base32::encode_base32(&pk[..]),
+        }
+    }
+
+    /// Create an ed25519 `Multikey` from its DNS label form. Only the exact output of
+    /// `to_dns_label` is accepted, so the round trip through both forms is lossless.
+    pub fn from_dns_label(s: &str) -> Result<Multikey, base32::DecodeBase32Error> {
+        let mut pk = [0u8; 32];
+        base32::decode_base32(s, &mut pk)?;
+        Ok(Multikey::from_ed25519(&pk))
+    }
+
+    /// The sigil that the legacy encoding starts with, always `'@'`.
+    pub fn sigil(&self) -> char {
+        '@'
+    }
+
+    /// The suffix of the legacy encoding (without the dot), naming the kind of key.
+    pub fn suffix_str(&self) -> &'static str {
+        match self.0 {
+            _Multikey::Ed25519(_) => "ed25519",
         }
     }
 
     /// Parses a
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
     /// into a `Multikey`, also returning the remaining input on success.
-    pub fn from_legacy(mut s: &[u8]) -> Result<(Multikey, &[u8]), DecodeLegacyError> {
-        s = skip_prefix(s, b"@").ok_or_else(|| DecodeLegacyError::Sigil)?;
+    pub fn from_legacy(s: &[u8]) -> Result<(Multikey, &[u8]), DecodeLegacyError> {
+        Multikey::from_legacy_with(s, &ParseOptions::default())
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// into a `Multikey` like `from_legacy`, but tolerating the deviations from the canonical
+    /// encoding that the options allow.
+    pub fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multikey, &'a [u8]), DecodeLegacyError> {
+        Multikey::from_legacy_with_engine(s, opts, &DefaultEngine)
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// into a `Multikey` like `from_legacy_with`, decoding the base64 with the given engine.
+    pub fn from_legacy_with_engine<'a, E: Base64Engine + ?Sized>(
+        mut s: &'a [u8],
+        opts: &ParseOptions,
+        engine: &E,
+    ) -> Result<(Multikey, &'a [u8]), DecodeLegacyError> {
+        s = skip_prefix(s, b"@").ok_or(DecodeLegacyError::Sigil)?;
+        Multikey::from_sigilless_with_engine(s, opts, engine)
+    }
+
+    /// Parses a legacy encoding without the leading `"@"` sigil (`<base64>.ed25519`), as used
+    /// in secret files and some config files, also returning the remaining input on success.
+    pub fn from_sigilless(s: &[u8]) -> Result<(Multikey, &[u8]), DecodeLegacyError> {
+        Multikey::from_sigilless_with_engine(s, &ParseOptions::default(), &DefaultEngine)
+    }
 
-        let (data, suffix) = split_at_byte(s, 0x2E).ok_or_else(|| DecodeLegacyError::NoDot)?;
+    /// Parses a legacy encoding without the leading `"@"` sigil like `from_sigilless`, decoding
+    /// the base64 with the given engine.
+    pub fn from_sigilless_with_engine<'a, E: Base64Engine + ?Sized>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+        engine: &E,
+    ) -> Result<(Multikey, &'a [u8]), DecodeLegacyError> {
+        let (data, suffix) = split_at_byte(s, 0x2E).ok_or(DecodeLegacyError::NoDot)?;
 
         let tail =
-            skip_prefix(suffix, ED25519_SUFFIX).ok_or_else(|| DecodeLegacyError::UnknownSuffix)?;
+            skip_suffix(suffix, ED25519_SUFFIX, opts).ok_or(DecodeLegacyError::UnknownSuffix)?;
 
-        if data.len() != ED25519_PK_BASE64_LEN {
-            return Err(DecodeLegacyError::Ed25519WrongSize);
-        }
+        decode_ed25519_base64(data, opts, engine).map(|key| (key, tail))
+    }
 
-        if data[ED25519_PK_BASE64_LEN - 2] == b"="[0] {
-            return Err(DecodeLegacyError::Ed25519WrongSize);
-        }
+    /// Parses the bare base64 encoding of an ed25519 public key, without sigil or suffix, as
+    /// used in multiserver addresses and caps configs. The whole input must be the canonical
+    /// encoding of exactly 32 bytes.
+    pub fn from_base64(s: &[u8]) -> Result<Multikey, DecodeLegacyError> {
+        decode_ed25519_base64(s, &ParseOptions::default(), &DefaultEngine)
+    }
 
-        if data[ED25519_PK_BASE64_LEN - 1] != b"="[0] {
-            return Err(DecodeLegacyError::Ed25519WrongSize);
-        }
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// into a `Multikey`, failing with `DecodeLegacyError::TrailingBytes` if the input
+    /// contains anything after the encoding.
+    pub fn from_legacy_exact(s: &[u8]) -> Result<Multikey, DecodeLegacyError> {
+        exact(Multikey::from_legacy(s), DecodeLegacyError::TrailingBytes)
+    }
 
-        let mut dec_data = [0u8; 32];
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// that makes up the whole string into a `Multikey`.
+    pub fn from_legacy_str(s: &str) -> Result<Multikey, DecodeLegacyError> {
+        Multikey::from_legacy_exact(s.as_bytes())
+    }
 
-        base64::decode_config_slice(data, base64::STANDARD, &mut dec_data)
-            .map_err(|e| DecodeLegacyError::InvalidBase64(e))
-            .map(|_| (Multikey::from_ed25519(&dec_data), tail))
+    /// Return whether the input is exactly a valid legacy encoding of a `Multikey`, without
+    /// allocating.
+    pub fn is_valid_legacy(s: &[u8]) -> bool {
+        Multikey::from_legacy_exact(s).is_ok()
     }
 
     /// Serialize a `Multikey` into a writer, using the
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
     pub fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        self.to_legacy_with_engine(w, &DefaultEngine)
+    }
+
+    /// Serialize a `Multikey` into a writer like `to_legacy`, encoding the base64 with the given
+    /// engine.
+    pub fn to_legacy_with_engine<W: Write, E: Base64Engine + ?Sized>(
+        &self,
+        w: &mut W,
+        engine: &E,
+    ) -> Result<(), io::Error> {
         match self.0 {
             _Multikey::Ed25519(ref pk) => {
                 w.write_all(b"@")?;
 
-                let data = base64::encode_config(&pk[..], base64::STANDARD);
-                w.write_all(data.as_bytes())?;
+                write_base64(w, &pk[..], engine)?;
 
                 w.write_all(b".")?;
                 w.write_all(ED25519_SUFFIX)
@@ -85,6 +250,61 @@ impl Multikey {
         }
     }
 
+    /// Write a `Multikey` into a `fmt::Write`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
+    pub fn fmt_legacy<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => {
+                w.write_str("@")?;
+                fmt_base64(w, &pk[..])?;
+                w.write_str(".ed25519")
+            }
+        }
+    }
+
+    /// Asynchronously serialize a `Multikey` into a writer, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
+    #[cfg(feature = "futures")]
+    pub async fn to_legacy_async<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<(), io::Error> {
+        match self.0 {
+            _Multikey::Ed25519(_) => {
+                let mut buf = [0u8; SSB_ED25519_ENCODED_LEN];
+                self.to_legacy(&mut Cursor::new(&mut buf[..]))?;
+                w.write_all(&buf).await
+            }
+        }
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// from the start of a `Buf` into a `Multikey`, advancing the buffer past it on success.
+    ///
+    /// The encoding may be split across the chunks of the buffer, as in a `Chain`. Contiguous
+    /// buffers such as `BytesMut` are decoded in place, others are copied first.
+    #[cfg(feature = "bytes")]
+    pub fn from_legacy_buf<B: Buf>(buf: &mut B) -> Result<Multikey, DecodeLegacyError> {
+        decode_buf(buf, Multikey::from_legacy)
+    }
+
+    /// Serialize a `Multikey` into a `BufMut`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
+    ///
+    /// Panics if the buffer does not have enough remaining capacity, like `BufMut::put_slice`.
+    #[cfg(feature = "bytes")]
+    pub fn to_legacy_buf<B: BufMut>(&self, buf: &mut B) {
+        self.to_legacy(&mut buf.writer()).unwrap()
+    }
+
+    /// Serialize a `Multikey` into a fixed-size array, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
+    ///
+    /// Unlike `to_legacy_vec` and `to_legacy_string`, this does not allocate.
+    pub fn to_legacy_array(&self) -> [u8; SSB_ED25519_ENCODED_LEN] {
+        let mut out = [0u8; SSB_ED25519_ENCODED_LEN];
+        self.to_legacy(&mut &mut out[..]).unwrap();
+        out
+    }
+
     /// Serialize a `Multikey` into an owned byte vector, using the
     /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
     pub fn to_legacy_vec(&self) -> Vec<u8> {
@@ -103,22 +323,92 @@ impl Multikey {
         String::from_utf8(self.to_legacy_vec()).unwrap()
     }
 
+    /// A short form of the legacy encoding for logs and user interfaces, keeping the sigil, the
+    /// first few characters of the base64 data and the suffix, e.g. `@zurF8X….ed25519`.
+    pub fn short(&self) -> String {
+        self.short_n(SHORT_LEN)
+    }
+
+    /// Like `short`, but keeping the first `n` characters of the base64 data.
+    pub fn short_n(&self, n: usize) -> String {
+        let mut s = String::new();
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => fmt_short(&mut s, "@", &pk[..], "ed25519", n).unwrap(),
+        }
+        s
+    }
+
+    /// A stable 64 bit hash of the key, for partitioning feeds across shards and for
+    /// probabilistic data structures. Unlike `Hash`, it is the same in every process and on
+    /// every platform: the 64 bit [FNV-1a](http://www.isthe.com/chongo/tech/comp/fnv/) hash of
+    /// the raw 32 bytes of the ed25519 key.
+    pub fn short_hash(&self) -> u64 {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => fnv1a(&[&pk[..]]),
+        }
+    }
+
     /// Check whether the given signature of the given text was created by this key.
+    #[cfg(feature = "multisig")]
     pub fn is_signature_correct(&self, data: &[u8], sig: &Multisig) -> bool {
+        self.is_signature_correct_with_cap(data, sig, None)
+    }
+
+    /// Check whether the given signature of the given text was created by this key, on the
+    /// network with the given cap. Networks other than the main network sign the HMAC of the
+    /// text keyed by their cap rather than the text itself; `None` means the main network.
+    #[cfg(feature = "multisig")]
+    pub fn is_signature_correct_with_cap(
+        &self,
+        data: &[u8],
+        sig: &Multisig,
+        cap: Option<&NetworkKey>,
+    ) -> bool {
         match (&self.0, &sig.0) {
-            (_Multikey::Ed25519(ref pk), _Multisig::Ed25519(ref sig)) => {
-                verify_detached(sig, data, pk)
-            }
+            (_Multikey::Ed25519(ref pk), _Multisig::Ed25519(ref sig)) => match cap {
+                Some(cap) => verify_detached(sig, &cap.authenticate(data)[..], pk),
+                None => verify_detached(sig, data, pk),
+            },
         }
     }
 }
 
+impl LegacyEncoding for Multikey {
+    type Error = DecodeLegacyError;
+
+    fn from_legacy(s: &[u8]) -> Result<(Multikey, &[u8]), DecodeLegacyError> {
+        Multikey::from_legacy(s)
+    }
+
+    fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multikey, &'a [u8]), DecodeLegacyError> {
+        Multikey::from_legacy_with(s, opts)
+    }
+
+    fn from_legacy_exact(s: &[u8]) -> Result<Multikey, DecodeLegacyError> {
+        Multikey::from_legacy_exact(s)
+    }
+
+    fn from_legacy_located(s: &[u8]) -> Result<(Multikey, &[u8]), DecodeError<DecodeLegacyError>> {
+        Multikey::from_legacy(s).map_err(|err| DecodeError {
+            offset: legacy_error_offset(&err, s, 1),
+            kind: err,
+        })
+    }
+
+    fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        Multikey::to_legacy(self, w)
+    }
+}
+
 impl Serialize for Multikey {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_legacy_string())
+        serializer.serialize_str(std::str::from_utf8(&self.to_legacy_array()).unwrap())
     }
 }
 
@@ -128,207 +418,907 @@ impl<'de> Deserialize<'de> for Multikey {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Multikey::from_legacy(&s.as_bytes())
+        Multikey::from_legacy(s.as_bytes())
             .map(|(mk, _)| mk)
-            .map_err(|err| D::Error::custom(format!("Invalid multikey: {}", err)))
+            .map_err(DecodeLegacyError::into_de_error)
     }
 }
 
-/// Everything that can go wrong when decoding a `Multikey` from the legacy encoding.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum DecodeLegacyError {
-    /// Input did not start with the `"@"` sigil.
-    Sigil,
-    /// Input did not contain a `"."` to separate the data from the suffix.
-    NoDot,
-    /// The base64 portion of the key was invalid.
-    InvalidBase64(base64::DecodeError),
-    /// The suffix is not known to this ssb implementation.
-    UnknownSuffix,
-    /// The suffix declares an ed25519 key, but the data length does not match.
-    Ed25519WrongSize,
+/// Formats the kind of key and its legacy encoding, e.g. `Multikey(Ed25519, "@...=.ed25519")`.
+impl fmt::Debug for Multikey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            _Multikey::Ed25519(_) => f
+                .debug_tuple("Multikey")
+                .field(&format_args!("Ed25519"))
+                .field(&self.to_legacy_string())
+                .finish(),
+        }
+    }
 }
 
-impl fmt::Display for DecodeLegacyError {
+/// Formats as the legacy encoding, without allocating.
+impl fmt::Display for Multikey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &DecodeLegacyError::Sigil => write!(f, "Invalid sigil"),
-            &DecodeLegacyError::InvalidBase64(ref err) => write!(f, "{}", err),
-            &DecodeLegacyError::NoDot => write!(f, "No dot"),
-            &DecodeLegacyError::UnknownSuffix => write!(f, "Unknown suffix"),
-            &DecodeLegacyError::Ed25519WrongSize => write!(f, "Data of wrong length"),
-        }
+        self.fmt_legacy(f)
     }
 }
 
-impl std::error::Error for DecodeLegacyError {}
+impl From<&Multikey> for String {
+    fn from(value: &Multikey) -> String {
+        value.to_legacy_string()
+    }
+}
 
-/// The secret counterpart to Multikey
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Multisecret(SecretKey);
+impl From<&Multikey> for Vec<u8> {
+    fn from(value: &Multikey) -> Vec<u8> {
+        value.to_legacy_vec()
+    }
+}
 
-impl Multisecret {
-    /// Parses a
-    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
-    /// into a `Multisecret`, also returning the remaining input on success.
-    pub fn from_legacy(s: &[u8]) -> Result<(Multisecret, &[u8]), DecodeLegacyError> {
-        let (data, suffix) = split_at_byte(s, 0x2E).ok_or_else(|| DecodeLegacyError::NoDot)?;
+/// A multikey that borrows its data, e.g. a key inside a decoded buffer or a memory-mapped
+/// index. It compares and orders exactly like the `Multikey` it corresponds to.
+///
+/// `Borrow` can not hand out a type with a lifetime, so conversions go through
+/// `Multikey::as_multikey_ref` and `MultikeyRef::to_multikey` (or the `From` impls) instead.
+#[derive(PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct MultikeyRef<'a>(_MultikeyRef<'a>);
 
-        let tail =
-            skip_prefix(suffix, ED25519_SUFFIX).ok_or_else(|| DecodeLegacyError::UnknownSuffix)?;
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+enum _MultikeyRef<'a> {
+    Ed25519(&'a [u8; 32]),
+}
 
-        if data.len() != SECRETKEYBYTES {
-            return Err(DecodeLegacyError::Ed25519WrongSize);
-        }
+impl<'a> MultikeyRef<'a> {
+    /// Borrow an ed25519 public key as a `MultikeyRef`.
+    pub fn from_ed25519(pk: &'a [u8; 32]) -> MultikeyRef<'a> {
+        MultikeyRef(_MultikeyRef::Ed25519(pk))
+    }
 
-        if data[SECRETKEYBYTES - 2] == b"="[0] {
-            return Err(DecodeLegacyError::Ed25519WrongSize);
-        }
+    /// Borrow an ed25519 public key as a `MultikeyRef`, or return `None` if the slice is not
+    /// exactly 32 bytes long.
+    pub fn from_ed25519_slice(pk: &'a [u8]) -> Option<MultikeyRef<'a>> {
+        <&[u8; 32]>::try_from(pk)
+            .ok()
+            .map(MultikeyRef::from_ed25519)
+    }
 
-        if data[SECRETKEYBYTES - 1] != b"="[0] {
-            return Err(DecodeLegacyError::Ed25519WrongSize);
+    /// The raw bytes of the key if it is an ed25519 public key.
+    pub fn as_ed25519(&self) -> Option<&'a [u8; 32]> {
+        match self.0 {
+            _MultikeyRef::Ed25519(pk) => Some(pk),
         }
-
-        let mut dec_data = [0u8; 32];
-
-        base64::decode_config_slice(data, base64::STANDARD, &mut dec_data)
-            .map_err(|e| DecodeLegacyError::InvalidBase64(e))
-            .map(|_| (Multisecret(SecretKey::from_slice(&dec_data).unwrap()), tail))
     }
 
-    /// Serialize a `Multisecret` into a writer, using the
-    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
-    pub fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
-        let data = base64::encode_config(&self.0[..], base64::STANDARD);
-        w.write_all(data.as_bytes())?;
-        w.write_all(b".")?;
-        w.write_all(ED25519_SUFFIX)
+    /// Copy the key into an owned `Multikey`.
+    pub fn to_multikey(&self) -> Multikey {
+        match self.0 {
+            _MultikeyRef::Ed25519(pk) => Multikey::from_ed25519(pk),
+        }
     }
 }
 
-impl Serialize for Multisecret {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut s = [0u8; SSB_ED25519_SECRET_ENCODED_LEN];
-        self.to_legacy(&mut Cursor::new(&mut s[..])).unwrap();
-        serializer.serialize_str(std::str::from_utf8(&s).unwrap())
+impl Multikey {
+    /// Borrow the key as a `MultikeyRef`.
+    pub fn as_multikey_ref(&self) -> MultikeyRef<'_> {
+        match self.0 {
+            _Multikey::Ed25519(ref pk) => MultikeyRef::from_ed25519(&pk.0),
+        }
     }
 }
 
-impl<'de> Deserialize<'de> for Multisecret {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let s = String::deserialize(deserializer)?;
-        Multisecret::from_legacy(&s.as_bytes())
-            .map(|(mk, _)| mk)
-            .map_err(|err| D::Error::custom(format!("Invalid multikey: {}", err)))
+/// Formats like the `Debug` of a `Multikey`, e.g. `MultikeyRef(Ed25519, "@...=.ed25519")`.
+impl fmt::Debug for MultikeyRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            _MultikeyRef::Ed25519(_) => f
+                .debug_tuple("MultikeyRef")
+                .field(&format_args!("Ed25519"))
+                .field(&self.to_string())
+                .finish(),
+        }
     }
 }
 
-/// A signature that owns its data.
-#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord)]
-pub struct Multisig(_Multisig);
-
-#[derive(Clone)]
-enum _Multisig {
-    // An [ed25519](http://ed25519.cr.yp.to/) signature.
-    Ed25519(Signature),
+/// Formats as the legacy encoding.
+impl fmt::Display for MultikeyRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_multikey().fmt_legacy(f)
+    }
 }
 
-impl fmt::Debug for _Multisig {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            _Multisig::Ed25519(ref data) => write!(f, "Ed25519 signature: {:?}", &data[..]),
-        }
+impl<'a> From<&'a Multikey> for MultikeyRef<'a> {
+    fn from(value: &'a Multikey) -> MultikeyRef<'a> {
+        value.as_multikey_ref()
     }
 }
 
-impl PartialEq for _Multisig {
-    fn eq(&self, other: &_Multisig) -> bool {
-        match (self, other) {
-            (_Multisig::Ed25519(ref a), _Multisig::Ed25519(ref b)) => &a[..] == &b[..],
-        }
+impl From<MultikeyRef<'_>> for Multikey {
+    fn from(value: MultikeyRef<'_>) -> Multikey {
+        value.to_multikey()
     }
 }
 
-impl Eq for _Multisig {}
+impl PartialEq<Multikey> for MultikeyRef<'_> {
+    fn eq(&self, other: &Multikey) -> bool {
+        *self == other.as_multikey_ref()
+    }
+}
 
-impl PartialOrd for _Multisig {
-    fn partial_cmp(&self, other: &_Multisig) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl PartialEq<MultikeyRef<'_>> for Multikey {
+    fn eq(&self, other: &MultikeyRef<'_>) -> bool {
+        self.as_multikey_ref() == *other
     }
 }
 
-impl Ord for _Multisig {
-    fn cmp(&self, other: &_Multisig) -> Ordering {
-        match (self, other) {
-            (_Multisig::Ed25519(ref a), _Multisig::Ed25519(ref b)) => a.cmp(&b),
+#[cfg(feature = "defmt")]
+impl defmt::Format for Multikey {
+    fn format(&self, f: defmt::Formatter) {
+        match self.0 {
+            _Multikey::Ed25519(_) => {
+                let mut buf = [0u8; SSB_ED25519_ENCODED_LEN];
+                self.to_legacy(&mut Cursor::new(&mut buf[..])).unwrap();
+                defmt::write!(f, "{=str}", std::str::from_utf8(&buf).unwrap())
+            }
         }
     }
 }
 
-impl Multikey {
-    /// Deserialize a legacy signature corrsponding to this key type.
-    pub fn sig_from_legacy<'a>(
-        &self,
-        s: &'a [u8],
-    ) -> Result<(Multisig, &'a [u8]), DecodeSignatureError> {
-        let (data, suffix) = split_at_byte(s, 0x2E).ok_or_else(|| DecodeSignatureError::NoDot)?;
+// Decode the base64 portion of the legacy encoding of an ed25519 key.
+fn decode_ed25519_base64<E: Base64Engine + ?Sized>(
+    data: &[u8],
+    opts: &ParseOptions,
+    engine: &E,
+) -> Result<Multikey, DecodeLegacyError> {
+    if exceeds_max_len(data, opts) {
+        return Err(DecodeLegacyError::TooLong);
+    }
 
-        let suffix = skip_prefix(suffix, b"sig").ok_or_else(|| DecodeSignatureError::NoDotSig)?;
+    let data = normalize_base64(data, opts);
 
-        match self.0 {
-            _Multikey::Ed25519(_) => {
-                let tail = skip_prefix(suffix, b".ed25519")
-                    .ok_or_else(|| DecodeSignatureError::UnknownSuffix)?;
+    let actual = base64_decoded_len(&data);
+    if actual != 32 {
+        return Err(DecodeLegacyError::WrongLength {
+            expected: 32,
+            actual,
+        });
+    }
 
-                if data.len() != ED25519_SIG_BASE64_LEN {
-                    return Err(DecodeSignatureError::Ed25519WrongSize);
-                }
+    if data.len() != ED25519_PK_BASE64_LEN {
+        return Err(DecodeLegacyError::InvalidBase64(
+            base64::DecodeError::InvalidLength,
+        ));
+    }
 
-                if data[ED25519_SIG_BASE64_LEN - 2] != b"="[0] {
-                    return Err(DecodeSignatureError::Ed25519WrongSize);
-                }
+    let mut dec_data = [0u8; 32];
 
-                let mut dec_data = [0u8; 64];
+    engine
+        .decode_slice(&data, &mut dec_data)
+        .map_err(DecodeLegacyError::InvalidBase64)
+        .map(|_| Multikey::from_ed25519(&dec_data))
+}
 
-                base64::decode_config_slice(data, base64::STANDARD, &mut dec_data[..])
-                    .map_err(|e| DecodeSignatureError::InvalidBase64(e))
-                    .map(|_| (Multisig::from_ed25519(&dec_data), tail))
-            }
+/// Everything that can go wrong when decoding a `Multikey` from the legacy encoding.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeLegacyError {
+    /// Input did not start with the `"@"` sigil.
+    Sigil,
+    /// Input did not contain a `"."` to separate the data from the suffix.
+    NoDot,
+    /// The base64 portion of the key was invalid.
+    InvalidBase64(base64::DecodeError),
+    /// The suffix is not known to this ssb implementation.
+    UnknownSuffix,
+    /// The suffix declares an ed25519 key, but the data decodes to the wrong number of bytes.
+    WrongLength {
+        /// The number of bytes the suffix calls for.
+        expected: usize,
+        /// The number of bytes the data decodes to.
+        actual: usize,
+    },
+    /// The input contained more than just the encoding.
+    TrailingBytes,
+    /// The base64 portion of the key was longer than the length limit of the `ParseOptions`.
+    TooLong,
+}
+
+// The offset into `s` at which decoding a multikey or a multisecret failed with the given error.
+// `sigil_len` is the length of the sigil that the encoding of the decoded type starts with.
+pub(crate) fn legacy_error_offset(err: &DecodeLegacyError, s: &[u8], sigil_len: usize) -> usize {
+    match *err {
+        DecodeLegacyError::Sigil => 0,
+        DecodeLegacyError::NoDot => s.len(),
+        DecodeLegacyError::InvalidBase64(ref err) => sigil_len + base64_error_offset(err),
+        DecodeLegacyError::UnknownSuffix => sigil_len + suffix_offset(&s[sigil_len..]),
+        DecodeLegacyError::WrongLength { .. } => sigil_len,
+        DecodeLegacyError::TrailingBytes => {
+            sigil_len + suffix_offset(&s[sigil_len..]) + ED25519_SUFFIX.len()
         }
+        DecodeLegacyError::TooLong => sigil_len,
     }
 }
 
-impl Multisig {
-    /// Take an ed25519 signature and turn it into an opaque `Multisig`.
-    pub fn from_ed25519(sig: &[u8; 64]) -> Multisig {
-        Multisig(_Multisig::Ed25519(Signature::from_slice(sig).unwrap()))
+impl DecodeLegacyError {
+    /// A stable numeric code identifying the kind of this error. Codes are unique across all
+    /// error types of this crate, and are never changed or reused.
+    pub fn error_code(&self) -> u16 {
+        match *self {
+            DecodeLegacyError::Sigil => 101,
+            DecodeLegacyError::NoDot => 102,
+            DecodeLegacyError::InvalidBase64(_) => 103,
+            DecodeLegacyError::UnknownSuffix => 104,
+            DecodeLegacyError::WrongLength { .. } => 105,
+            DecodeLegacyError::TrailingBytes => 106,
+            DecodeLegacyError::TooLong => 107,
+        }
+    }
+
+    /// Convert into a serde deserialization error, for `Deserialize` impls that parse legacy
+    /// encodings.
+    pub fn into_de_error<E: serde::de::Error>(self) -> E {
+        E::custom(format!("Invalid multikey: {}", self))
+    }
+}
+
+impl fmt::Display for DecodeLegacyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeLegacyError::Sigil => write!(f, "Invalid sigil"),
+            DecodeLegacyError::InvalidBase64(_) => write!(f, "Invalid base64"),
+            DecodeLegacyError::NoDot => write!(f, "No dot"),
+            DecodeLegacyError::UnknownSuffix => write!(f, "Unknown suffix"),
+            DecodeLegacyError::WrongLength { expected, actual } => {
+                write!(
+                    f,
+                    "Data of wrong length: expected {} bytes, got {}",
+                    expected, actual
+                )
+            }
+            DecodeLegacyError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeLegacyError::TooLong => write!(f, "Too long"),
+        }
+    }
+}
+
+/// Serializes as a map with the `kind` of the error (the name of its variant), its numeric `code`
+/// and its `message`, plus the fields of the variant: the `base64_offset` for
+/// `InvalidBase64`, which counts from the start of the base64 data rather than from the start of
+/// the input (`from_legacy_located` reports the latter), and `expected` and `actual` for `WrongLength`.
+impl Serialize for DecodeLegacyError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            DecodeLegacyError::Sigil => {
+                serialize_error(serializer, "Sigil", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::NoDot => {
+                serialize_error(serializer, "NoDot", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::InvalidBase64(ref err) => serialize_error(
+                serializer,
+                "InvalidBase64",
+                self.error_code(),
+                self,
+                &[("base64_offset", base64_error_offset(err))],
+            ),
+            DecodeLegacyError::UnknownSuffix => {
+                serialize_error(serializer, "UnknownSuffix", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::WrongLength { expected, actual } => serialize_error(
+                serializer,
+                "WrongLength",
+                self.error_code(),
+                self,
+                &[("expected", expected), ("actual", actual)],
+            ),
+            DecodeLegacyError::TrailingBytes => {
+                serialize_error(serializer, "TrailingBytes", self.error_code(), self, &[])
+            }
+            DecodeLegacyError::TooLong => {
+                serialize_error(serializer, "TooLong", self.error_code(), self, &[])
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeLegacyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeLegacyError::InvalidBase64(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeLegacyError> for io::Error {
+    fn from(err: DecodeLegacyError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for DecodeLegacyError {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            DecodeLegacyError::Sigil => defmt::write!(f, "Invalid sigil"),
+            DecodeLegacyError::InvalidBase64(_) => defmt::write!(f, "Invalid base64"),
+            DecodeLegacyError::NoDot => defmt::write!(f, "No dot"),
+            DecodeLegacyError::UnknownSuffix => defmt::write!(f, "Unknown suffix"),
+            DecodeLegacyError::WrongLength { expected, actual } => {
+                defmt::write!(
+                    f,
+                    "Data of wrong length: expected {} bytes, got {}",
+                    expected,
+                    actual
+                )
+            }
+            DecodeLegacyError::TrailingBytes => defmt::write!(f, "Trailing bytes"),
+            DecodeLegacyError::TooLong => defmt::write!(f, "Too long"),
+        }
+    }
+}
+
+/// The secret counterpart to Multikey
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Multisecret(SecretKey);
+
+impl Multisecret {
+    /// Create a `Multisecret` from the 64 bytes of an ed25519 secret key, i.e. the seed
+    /// followed by the public key.
+    pub fn from_ed25519(sk: &[u8; 64]) -> Multisecret {
+        Multisecret(SecretKey::from_slice(sk).unwrap())
+    }
+
+    /// The 64 bytes of the ed25519 secret key.
+    pub fn as_ed25519(&self) -> Option<&[u8]> {
+        Some(&(self.0).0[..])
+    }
+
+    /// Derive the ed25519 secret key of the given 32 byte seed, the counterpart to
+    /// `Multikey::from_seed`.
+    pub fn from_seed(seed: &[u8; 32]) -> Multisecret {
+        let (_, sk) = sign::keypair_from_seed(&sign::Seed(*seed));
+        Multisecret(sk)
+    }
+
+    /// The suffix of the legacy encoding (without the dot), naming the kind of key.
+    pub fn suffix_str(&self) -> &'static str {
+        "ed25519"
+    }
+
+    /// The DER encoding of the secret key as a PKCS#8 private key, which holds the 32 byte
+    /// seed the key was derived from.
+    #[cfg(feature = "pem")]
+    pub fn to_pkcs8_der(&self) -> Vec<u8> {
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&(self.0).0[..32]);
+        pem::encode_pkcs8(&seed)
+    }
+
+    /// Create a `Multisecret` from the DER encoding of a PKCS#8 ed25519 private key.
+    #[cfg(feature = "pem")]
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<Multisecret, pem::DecodePemError> {
+        Ok(Multisecret::from_seed(&pem::decode_pkcs8(der)?))
+    }
+
+    /// The secret key as a `PRIVATE KEY` PEM block.
+    #[cfg(feature = "pem")]
+    pub fn to_pkcs8_pem(&self) -> String {
+        pem::encode_pem(&self.to_pkcs8_der(), pem::PRIVATE_KEY_LABEL)
+    }
+
+    /// Create a `Multisecret` from a `PRIVATE KEY` PEM block.
+    #[cfg(feature = "pem")]
+    pub fn from_pkcs8_pem(s: &str) -> Result<Multisecret, pem::DecodePemError> {
+        Multisecret::from_pkcs8_der(&pem::decode_pem(s, pem::PRIVATE_KEY_LABEL)?)
+    }
+
+    /// Sign the given text, on the network with the given cap. Networks other than the main
+    /// network sign the HMAC of the text keyed by their cap rather than the text itself; `None`
+    /// means the main network.
+    #[cfg(feature = "multisig")]
+    pub fn sign(&self, data: &[u8], cap: Option<&NetworkKey>) -> Multisig {
+        let sig = match cap {
+            Some(cap) => sign_detached(&cap.authenticate(data)[..], &self.0),
+            None => sign_detached(data, &self.0),
+        };
+        Multisig(_Multisig::Ed25519(sig))
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// into a `Multisecret`, also returning the remaining input on success.
+    pub fn from_legacy(s: &[u8]) -> Result<(Multisecret, &[u8]), DecodeLegacyError> {
+        Multisecret::from_legacy_with(s, &ParseOptions::default())
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// into a `Multisecret` like `from_legacy`, but tolerating the deviations from the canonical
+    /// encoding that the options allow.
+    pub fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multisecret, &'a [u8]), DecodeLegacyError> {
+        Multisecret::from_legacy_with_engine(s, opts, &DefaultEngine)
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// into a `Multisecret` like `from_legacy_with`, decoding the base64 with the given engine.
+    pub fn from_legacy_with_engine<'a, E: Base64Engine + ?Sized>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+        engine: &E,
+    ) -> Result<(Multisecret, &'a [u8]), DecodeLegacyError> {
+        let (data, suffix) = split_at_byte(s, 0x2E).ok_or(DecodeLegacyError::NoDot)?;
+
+        let tail =
+            skip_suffix(suffix, ED25519_SUFFIX, opts).ok_or(DecodeLegacyError::UnknownSuffix)?;
+
+        if exceeds_max_len(data, opts) {
+            return Err(DecodeLegacyError::TooLong);
+        }
+
+        let data = normalize_base64(data, opts);
+
+        let actual = base64_decoded_len(&data);
+        if actual != SECRETKEYBYTES {
+            return Err(DecodeLegacyError::WrongLength {
+                expected: SECRETKEYBYTES,
+                actual,
+            });
+        }
+
+        if data.len() != ED25519_SECRET_BASE64_LEN {
+            return Err(DecodeLegacyError::InvalidBase64(
+                base64::DecodeError::InvalidLength,
+            ));
+        }
+
+        let mut dec_data = [0u8; SECRETKEYBYTES];
+
+        engine
+            .decode_slice(&data, &mut dec_data)
+            .map_err(DecodeLegacyError::InvalidBase64)
+            .map(|_| (Multisecret(SecretKey::from_slice(&dec_data).unwrap()), tail))
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// into a `Multisecret`, failing with `DecodeLegacyError::TrailingBytes` if the input
+    /// contains anything after the encoding.
+    pub fn from_legacy_exact(s: &[u8]) -> Result<Multisecret, DecodeLegacyError> {
+        exact(
+            Multisecret::from_legacy(s),
+            DecodeLegacyError::TrailingBytes,
+        )
+    }
+
+    /// Parses a
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding)
+    /// that makes up the whole string into a `Multisecret`.
+    pub fn from_legacy_str(s: &str) -> Result<Multisecret, DecodeLegacyError> {
+        Multisecret::from_legacy_exact(s.as_bytes())
+    }
+
+    /// Return whether the input is exactly a valid legacy encoding of a `Multisecret`, without
+    /// allocating.
+    pub fn is_valid_legacy(s: &[u8]) -> bool {
+        Multisecret::from_legacy_exact(s).is_ok()
+    }
+
+    /// Serialize a `Multisecret` into a writer, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
+    pub fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        self.to_legacy_with_engine(w, &DefaultEngine)
+    }
+
+    /// Serialize a `Multisecret` into a writer like `to_legacy`, encoding the base64 with the
+    /// given engine.
+    pub fn to_legacy_with_engine<W: Write, E: Base64Engine + ?Sized>(
+        &self,
+        w: &mut W,
+        engine: &E,
+    ) -> Result<(), io::Error> {
+        write_base64(w, &self.0[..], engine)?;
+        w.write_all(b".")?;
+        w.write_all(ED25519_SUFFIX)
+    }
+
+    /// Write a `Multisecret` into a `fmt::Write`, using the
+    /// [legacy encoding](https://spec.scuttlebutt.nz/datatypes.html#multikey-legacy-encoding).
+    pub fn fmt_legacy<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        fmt_base64(w, &self.0[..])?;
+        w.write_str(".ed25519")
+    }
+}
+
+impl LegacyEncoding for Multisecret {
+    type Error = DecodeLegacyError;
+
+    fn from_legacy(s: &[u8]) -> Result<(Multisecret, &[u8]), DecodeLegacyError> {
+        Multisecret::from_legacy(s)
+    }
+
+    fn from_legacy_with<'a>(
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multisecret, &'a [u8]), DecodeLegacyError> {
+        Multisecret::from_legacy_with(s, opts)
+    }
+
+    fn from_legacy_exact(s: &[u8]) -> Result<Multisecret, DecodeLegacyError> {
+        Multisecret::from_legacy_exact(s)
+    }
+
+    fn from_legacy_located(
+        s: &[u8],
+    ) -> Result<(Multisecret, &[u8]), DecodeError<DecodeLegacyError>> {
+        Multisecret::from_legacy(s).map_err(|err| DecodeError {
+            offset: legacy_error_offset(&err, s, 0),
+            kind: err,
+        })
+    }
+
+    fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        Multisecret::to_legacy(self, w)
+    }
+}
+
+impl Serialize for Multisecret {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = [0u8; SSB_ED25519_SECRET_ENCODED_LEN];
+        self.to_legacy(&mut Cursor::new(&mut s[..])).unwrap();
+        serializer.serialize_str(std::str::from_utf8(&s).unwrap())
+    }
+}
+
+impl<'de> Deserialize<'de> for Multisecret {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Multisecret::from_legacy(s.as_bytes())
+            .map(|(mk, _)| mk)
+            .map_err(DecodeLegacyError::into_de_error)
+    }
+}
+
+/// A signature that owns its data.
+#[cfg(feature = "multisig")]
+#[derive(PartialEq, Eq, Clone, PartialOrd, Ord)]
+pub struct Multisig(_Multisig);
+
+#[cfg(feature = "multisig")]
+#[derive(Clone)]
+enum _Multisig {
+    // An [ed25519](http://ed25519.cr.yp.to/) signature.
+    Ed25519(Signature),
+}
+
+#[cfg(feature = "multisig")]
+impl fmt::Debug for _Multisig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            _Multisig::Ed25519(ref data) => write!(f, "Ed25519 signature: {:?}", &data[..]),
+        }
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl PartialEq for _Multisig {
+    fn eq(&self, other: &_Multisig) -> bool {
+        match (self, other) {
+            (_Multisig::Ed25519(ref a), _Multisig::Ed25519(ref b)) => a[..] == b[..],
+        }
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl Eq for _Multisig {}
+
+#[cfg(feature = "multisig")]
+impl PartialOrd for _Multisig {
+    fn partial_cmp(&self, other: &_Multisig) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl Ord for _Multisig {
+    fn cmp(&self, other: &_Multisig) -> Ordering {
+        match (self, other) {
+            (_Multisig::Ed25519(ref a), _Multisig::Ed25519(ref b)) => a.cmp(b),
+        }
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl Multikey {
+    /// Deserialize a legacy signature corrsponding to this key type.
+    pub fn sig_from_legacy<'a>(
+        &self,
+        s: &'a [u8],
+    ) -> Result<(Multisig, &'a [u8]), DecodeSignatureError> {
+        self.sig_from_legacy_with(s, &ParseOptions::default())
+    }
+
+    /// Deserialize a legacy signature corrsponding to this key type like `sig_from_legacy`, but
+    /// tolerating the deviations from the canonical encoding that the options allow.
+    pub fn sig_from_legacy_with<'a>(
+        &self,
+        s: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<(Multisig, &'a [u8]), DecodeSignatureError> {
+        self.sig_from_legacy_with_engine(s, opts, &DefaultEngine)
+    }
+
+    /// Deserialize a legacy signature corrsponding to this key type like `sig_from_legacy_with`,
+    /// decoding the base64 with the given engine.
+    pub fn sig_from_legacy_with_engine<'a, E: Base64Engine + ?Sized>(
+        &self,
+        s: &'a [u8],
+        opts: &ParseOptions,
+        engine: &E,
+    ) -> Result<(Multisig, &'a [u8]), DecodeSignatureError> {
+        let (data, suffix) = split_at_byte(s, 0x2E).ok_or(DecodeSignatureError::NoDot)?;
+
+        let suffix = skip_suffix(suffix, b"sig", opts).ok_or(DecodeSignatureError::NoDotSig)?;
+
+        match self.0 {
+            _Multikey::Ed25519(_) => {
+                let tail = skip_suffix(suffix, b".ed25519", opts)
+                    .ok_or(DecodeSignatureError::UnknownSuffix)?;
+
+                if exceeds_max_len(data, opts) {
+                    return Err(DecodeSignatureError::TooLong);
+                }
+
+                let data = normalize_base64(data, opts);
+
+                let actual = base64_decoded_len(&data);
+                if actual != 64 {
+                    return Err(DecodeSignatureError::WrongLength {
+                        expected: 64,
+                        actual,
+                    });
+                }
+
+                if data.len() != ED25519_SIG_BASE64_LEN {
+                    return Err(DecodeSignatureError::InvalidBase64(
+                        base64::DecodeError::InvalidLength,
+                    ));
+                }
+
+                let mut dec_data = [0u8; 64];
+
+                engine
+                    .decode_slice(&data, &mut dec_data[..])
+                    .map_err(DecodeSignatureError::InvalidBase64)
+                    .map(|_| (Multisig::from_ed25519(&dec_data), tail))
+            }
+        }
+    }
+
+    /// Deserialize a legacy signature corrsponding to this key type, also returning the number
+    /// of bytes of the input that the encoding took up.
+    pub fn sig_from_legacy_counted(
+        &self,
+        s: &[u8],
+    ) -> Result<(Multisig, usize), DecodeSignatureError> {
+        self.sig_from_legacy(s)
+            .map(|(sig, tail)| (sig, s.len() - tail.len()))
+    }
+
+    /// Deserialize a legacy signature corrsponding to this key type, also returning the remaining
+    /// input on success. On failure, the error reports the offset in the input at which decoding
+    /// failed.
+    pub fn sig_from_legacy_located<'a>(
+        &self,
+        s: &'a [u8],
+    ) -> Result<(Multisig, &'a [u8]), DecodeError<DecodeSignatureError>> {
+        self.sig_from_legacy(s).map_err(|err| DecodeError {
+            offset: sig_error_offset(&err, s),
+            kind: err,
+        })
+    }
+
+    /// Deserialize a legacy signature corrsponding to this key type, failing with
+    /// `DecodeSignatureError::TrailingBytes` if the input contains anything after the encoding.
+    pub fn sig_from_legacy_exact(&self, s: &[u8]) -> Result<Multisig, DecodeSignatureError> {
+        exact(self.sig_from_legacy(s), DecodeSignatureError::TrailingBytes)
+    }
+
+    /// Deserialize a legacy signature corrsponding to this key type that makes up the whole
+    /// string.
+    pub fn sig_from_legacy_str(&self, s: &str) -> Result<Multisig, DecodeSignatureError> {
+        self.sig_from_legacy_exact(s.as_bytes())
+    }
+
+    /// Return whether the input is exactly a valid legacy encoding of a signature corrsponding to
+    /// this key type, without allocating.
+    pub fn is_valid_legacy_sig(&self, s: &[u8]) -> bool {
+        self.sig_from_legacy_exact(s).is_ok()
+    }
+
+    /// Deserialize a legacy signature corrsponding to this key type from the start of a `Buf`,
+    /// advancing the buffer past it on success.
+    ///
+    /// The encoding may be split across the chunks of the buffer, as in a `Chain`. Contiguous
+    /// buffers such as `BytesMut` are decoded in place, others are copied first.
+    #[cfg(feature = "bytes")]
+    pub fn sig_from_legacy_buf<B: Buf>(
+        &self,
+        buf: &mut B,
+    ) -> Result<Multisig, DecodeSignatureError> {
+        decode_buf(buf, |s| self.sig_from_legacy(s))
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl Multisig {
+    /// Take an ed25519 signature and turn it into an opaque `Multisig`.
+    pub fn from_ed25519(sig: &[u8; 64]) -> Multisig {
+        Multisig(_Multisig::Ed25519(Signature::from_slice(sig).unwrap()))
+    }
+
+    /// Create an ed25519 `Multisig` from random bytes, for tests and simulations. It does not
+    /// verify for any message.
+    #[cfg(feature = "test-util")]
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Multisig {
+        let mut sig = [0u8; 64];
+        rng.fill_bytes(&mut sig);
+        Multisig::from_ed25519(&sig)
+    }
+
+    /// The raw signature bytes, if this is an ed25519 signature.
+    pub fn as_ed25519(&self) -> Option<&[u8]> {
+        match self.0 {
+            _Multisig::Ed25519(ref sig) => Some(&sig[..]),
+        }
+    }
+
+    /// The raw signature bytes as lowercase hex.
+    pub fn to_hex(&self) -> String {
+        match self.0 {
+            _Multisig::Ed25519(ref sig) => hex::encode_hex(&sig[..]),
+        }
+    }
+
+    /// Create an ed25519 `Multisig` from the hex encoding of the signature.
+    pub fn from_hex(s: &str) -> Result<Multisig, hex::DecodeHexError> {
+        let mut sig = [0u8; 64];
+        hex::decode_hex(s, &mut sig)?;
+        Ok(Multisig::from_ed25519(&sig))
+    }
+
+    /// The signature field of a Gabby Grove transfer: the raw signature bytes. The transfer
+    /// carries no separate type tag, the kind of signature follows from the binary feed ref of
+    /// the author, so only ed25519 signatures have this form.
+    pub fn to_gabbygrove(&self) -> Option<Vec<u8>> {
+        match self.0 {
+            _Multisig::Ed25519(ref sig) => Some(sig[..].to_vec()),
+        }
+    }
+
+    /// Read the signature field of a Gabby Grove transfer whose author is an ed25519 feed. The
+    /// signature can then be written in its legacy `.sig.ed25519` form.
+    pub fn from_gabbygrove(sig: &[u8]) -> Result<Multisig, DecodeSignatureError> {
+        if sig.len() != 64 {
+            return Err(DecodeSignatureError::WrongLength {
+                expected: 64,
+                actual: sig.len(),
+            });
+        }
+        let mut raw = [0u8; 64];
+        raw.copy_from_slice(sig);
+        Ok(Multisig::from_ed25519(&raw))
+    }
+
+    /// The suffix of the legacy encoding (without the dot), naming the kind of signature.
+    pub fn suffix_str(&self) -> &'static str {
+        match self.0 {
+            _Multisig::Ed25519(_) => "sig.ed25519",
+        }
     }
 
     /// Serialize a signature into a writer, in the appropriate
     /// form for a [legacy message](https://spec.scuttlebutt.nz/messages.html#legacy-json-encoding).
     pub fn to_legacy<W: Write>(&self, w: &mut W) -> Result<(), io::Error> {
+        self.to_legacy_with_engine(w, &DefaultEngine)
+    }
+
+    /// Serialize a signature into a writer like `to_legacy`, encoding the base64 with the given
+    /// engine.
+    pub fn to_legacy_with_engine<W: Write, E: Base64Engine + ?Sized>(
+        &self,
+        w: &mut W,
+        engine: &E,
+    ) -> Result<(), io::Error> {
         match self.0 {
             _Multisig::Ed25519(ref sig) => {
-                let data = base64::encode_config(&sig[..], base64::STANDARD);
-                w.write_all(data.as_bytes())?;
+                write_base64(w, &sig[..], engine)?;
                 w.write_all(b".sig.ed25519")
             }
         }
     }
 
+    /// A short form of the legacy encoding for logs and user interfaces, keeping the first few
+    /// characters of the base64 data and the suffix, e.g. `W6sYZl….sig.ed25519`.
+    pub fn short(&self) -> String {
+        self.short_n(SHORT_LEN)
+    }
+
+    /// Like `short`, but keeping the first `n` characters of the base64 data.
+    pub fn short_n(&self, n: usize) -> String {
+        let mut s = String::new();
+        match self.0 {
+            _Multisig::Ed25519(ref sig) => {
+                fmt_short(&mut s, "", &sig[..], "sig.ed25519", n).unwrap()
+            }
+        }
+        s
+    }
+
+    /// Write a signature into a `fmt::Write`, in the appropriate
+    /// form for a [legacy message](https://spec.scuttlebutt.nz/messages.html#legacy-json-encoding).
+    pub fn fmt_legacy<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        match self.0 {
+            _Multisig::Ed25519(ref sig) => {
+                fmt_base64(w, &sig[..])?;
+                w.write_str(".sig.ed25519")
+            }
+        }
+    }
+
+    /// Asynchronously serialize a signature into a writer, in the appropriate
+    /// form for a [legacy message](https://spec.scuttlebutt.nz/messages.html#legacy-json-encoding).
+    #[cfg(feature = "futures")]
+    pub async fn to_legacy_async<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<(), io::Error> {
+        match self.0 {
+            _Multisig::Ed25519(_) => {
+                let mut buf = [0u8; SSB_ED25519_SIG_ENCODED_LEN];
+                self.to_legacy(&mut Cursor::new(&mut buf[..]))?;
+                w.write_all(&buf).await
+            }
+        }
+    }
+
+    /// Serialize a signature into a `BufMut`, in the appropriate
+    /// form for a [legacy message](https://spec.scuttlebutt.nz/messages.html#legacy-json-encoding).
+    ///
+    /// Panics if the buffer does not have enough remaining capacity, like `BufMut::put_slice`.
+    #[cfg(feature = "bytes")]
+    pub fn to_legacy_buf<B: BufMut>(&self, buf: &mut B) {
+        self.to_legacy(&mut buf.writer()).unwrap()
+    }
+
+    /// Serialize a signature into a fixed-size array,
+    /// in the appropriate form for a
+    /// [legacy message](https://spec.scuttlebutt.nz/messages.html#legacy-json-encoding).
+    ///
+    /// Unlike `to_legacy_vec` and `to_legacy_string`, this does not allocate.
+    pub fn to_legacy_array(&self) -> [u8; SSB_ED25519_SIG_ENCODED_LEN] {
+        let mut out = [0u8; SSB_ED25519_SIG_ENCODED_LEN];
+        self.to_legacy(&mut &mut out[..]).unwrap();
+        out
+    }
+
     /// Serialize a signature into an owned byte vector,
     /// in the appropriate form for a
     /// [legacy message](https://spec.scuttlebutt.nz/messages.html#legacy-json-encoding).
     pub fn to_legacy_vec(&self) -> Vec<u8> {
         match self.0 {
             _Multisig::Ed25519(_) => {
-                let mut out = Vec::with_capacity(ED25519_SIG_BASE64_LEN);
+                let mut out = Vec::with_capacity(SSB_ED25519_SIG_ENCODED_LEN);
                 self.to_legacy(&mut out).unwrap();
                 out
             }
@@ -343,8 +1333,105 @@ impl Multisig {
     }
 }
 
+/// Check a whole batch of signatures at once, using
+/// [ed25519-dalek](https://docs.rs/ed25519-dalek)'s batch verification, which is much faster
+/// than checking them one by one. Each item is a key, a signature and the signed text.
+///
+/// If the batch does not verify, the items are checked individually with
+/// `Multikey::is_signature_correct`, and the index of the first incorrect signature is
+/// returned. That check is authoritative: a batch that fails although every signature is
+/// correct on its own is accepted.
+#[cfg(all(feature = "multisig", feature = "ed25519-dalek"))]
+pub fn verify_batch(items: &[(Multikey, Multisig, &[u8])]) -> Result<(), usize> {
+    if dalek_verify_batch(items) {
+        return Ok(());
+    }
+
+    match items
+        .iter()
+        .position(|(key, sig, data)| !key.is_signature_correct(data, sig))
+    {
+        Some(i) => Err(i),
+        None => Ok(()),
+    }
+}
+
+#[cfg(all(feature = "multisig", feature = "ed25519-dalek"))]
+fn dalek_verify_batch(items: &[(Multikey, Multisig, &[u8])]) -> bool {
+    let mut messages = Vec::with_capacity(items.len());
+    let mut signatures = Vec::with_capacity(items.len());
+    let mut keys = Vec::with_capacity(items.len());
+
+    for (key, sig, data) in items {
+        match (&key.0, &sig.0) {
+            (_Multikey::Ed25519(ref pk), _Multisig::Ed25519(ref sig)) => {
+                match ed25519_dalek::VerifyingKey::from_bytes(&pk.0) {
+                    Ok(key) => keys.push(key),
+                    Err(_) => return false,
+                }
+                signatures.push(ed25519_dalek::Signature::from_bytes(&sig.0));
+                messages.push(*data);
+            }
+        }
+    }
+
+    ed25519_dalek::verify_batch(&messages, &signatures, &keys).is_ok()
+}
+
+/// Formats the kind of signature and its legacy encoding, e.g.
+/// `Multisig(Ed25519, "...==.sig.ed25519")`.
+#[cfg(feature = "multisig")]
+impl fmt::Debug for Multisig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            _Multisig::Ed25519(_) => f
+                .debug_tuple("Multisig")
+                .field(&format_args!("Ed25519"))
+                .field(&self.to_legacy_string())
+                .finish(),
+        }
+    }
+}
+
+/// Formats as the legacy encoding, without allocating.
+#[cfg(feature = "multisig")]
+impl fmt::Display for Multisig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_legacy(f)
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl From<&Multisig> for String {
+    fn from(value: &Multisig) -> String {
+        value.to_legacy_string()
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl From<&Multisig> for Vec<u8> {
+    fn from(value: &Multisig) -> Vec<u8> {
+        value.to_legacy_vec()
+    }
+}
+
+#[cfg(all(feature = "multisig", feature = "defmt"))]
+impl defmt::Format for Multisig {
+    fn format(&self, f: defmt::Formatter) {
+        match self.0 {
+            _Multisig::Ed25519(_) => {
+                let mut buf = [0u8; SSB_ED25519_SIG_ENCODED_LEN];
+                self.to_legacy(&mut Cursor::new(&mut buf[..])).unwrap();
+                defmt::write!(f, "{=str}", std::str::from_utf8(&buf).unwrap())
+            }
+        }
+    }
+}
+
 /// Everything that can go wrong when decoding a signature from the legacy encoding.
+#[cfg(feature = "multisig")]
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum DecodeSignatureError {
     /// Input did not contain a `"."` to separate the data from the suffix.
     NoDot,
@@ -354,34 +1441,178 @@ pub enum DecodeSignatureError {
     InvalidBase64(base64::DecodeError),
     /// The suffix is not known to this ssb implementation.
     UnknownSuffix,
-    /// The suffix declares an ed25519 signature, but the data length does not match.
-    Ed25519WrongSize,
+    /// The suffix declares an ed25519 signature, but the data decodes to the wrong number of
+    /// bytes.
+    WrongLength {
+        /// The number of bytes the suffix calls for.
+        expected: usize,
+        /// The number of bytes the data decodes to.
+        actual: usize,
+    },
+    /// The input contained more than just the encoding.
+    TrailingBytes,
+    /// The base64 portion of the signature was longer than the length limit of the
+    /// `ParseOptions`.
+    TooLong,
+}
+
+// The offset into `s` at which decoding a signature failed with the given error.
+#[cfg(feature = "multisig")]
+fn sig_error_offset(err: &DecodeSignatureError, s: &[u8]) -> usize {
+    match *err {
+        DecodeSignatureError::NoDot => s.len(),
+        DecodeSignatureError::NoDotSig => suffix_offset(s),
+        DecodeSignatureError::InvalidBase64(ref err) => base64_error_offset(err),
+        DecodeSignatureError::UnknownSuffix => suffix_offset(s) + b"sig".len(),
+        DecodeSignatureError::WrongLength { .. } => 0,
+        DecodeSignatureError::TrailingBytes => suffix_offset(s) + b"sig.ed25519".len(),
+        DecodeSignatureError::TooLong => 0,
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl DecodeSignatureError {
+    /// A stable numeric code identifying the kind of this error. Codes are unique across all
+    /// error types of this crate, and are never changed or reused.
+    pub fn error_code(&self) -> u16 {
+        match *self {
+            DecodeSignatureError::NoDot => 201,
+            DecodeSignatureError::NoDotSig => 202,
+            DecodeSignatureError::InvalidBase64(_) => 203,
+            DecodeSignatureError::UnknownSuffix => 204,
+            DecodeSignatureError::WrongLength { .. } => 205,
+            DecodeSignatureError::TrailingBytes => 206,
+            DecodeSignatureError::TooLong => 207,
+        }
+    }
+
+    /// Convert into a serde deserialization error, for `Deserialize` impls that parse legacy
+    /// encodings.
+    pub fn into_de_error<E: serde::de::Error>(self) -> E {
+        E::custom(format!("Invalid signature: {}", self))
+    }
 }
 
+#[cfg(feature = "multisig")]
 impl fmt::Display for DecodeSignatureError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &DecodeSignatureError::InvalidBase64(ref err) => write!(f, "{}", err),
-            &DecodeSignatureError::NoDot => write!(f, "No dot"),
-            &DecodeSignatureError::NoDotSig => write!(f, "No .sig"),
-            &DecodeSignatureError::UnknownSuffix => write!(f, "Unknown suffix"),
-            &DecodeSignatureError::Ed25519WrongSize => write!(f, "Data of wrong length"),
+        match *self {
+            DecodeSignatureError::InvalidBase64(_) => write!(f, "Invalid base64"),
+            DecodeSignatureError::NoDot => write!(f, "No dot"),
+            DecodeSignatureError::NoDotSig => write!(f, "No .sig"),
+            DecodeSignatureError::UnknownSuffix => write!(f, "Unknown suffix"),
+            DecodeSignatureError::WrongLength { expected, actual } => {
+                write!(
+                    f,
+                    "Data of wrong length: expected {} bytes, got {}",
+                    expected, actual
+                )
+            }
+            DecodeSignatureError::TrailingBytes => write!(f, "Trailing bytes"),
+            DecodeSignatureError::TooLong => write!(f, "Too long"),
         }
     }
 }
 
-impl std::error::Error for DecodeSignatureError {}
+/// Serializes as a map with the `kind` of the error (the name of its variant), its numeric `code`
+/// and its `message`, plus the fields of the variant: the `base64_offset` for
+/// `InvalidBase64`, which counts from the start of the base64 data rather than from the start of
+/// the input (`from_legacy_located` reports the latter), and `expected` and `actual` for `WrongLength`.
+#[cfg(feature = "multisig")]
+impl Serialize for DecodeSignatureError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            DecodeSignatureError::NoDot => {
+                serialize_error(serializer, "NoDot", self.error_code(), self, &[])
+            }
+            DecodeSignatureError::NoDotSig => {
+                serialize_error(serializer, "NoDotSig", self.error_code(), self, &[])
+            }
+            DecodeSignatureError::InvalidBase64(ref err) => serialize_error(
+                serializer,
+                "InvalidBase64",
+                self.error_code(),
+                self,
+                &[("base64_offset", base64_error_offset(err))],
+            ),
+            DecodeSignatureError::UnknownSuffix => {
+                serialize_error(serializer, "UnknownSuffix", self.error_code(), self, &[])
+            }
+            DecodeSignatureError::WrongLength { expected, actual } => serialize_error(
+                serializer,
+                "WrongLength",
+                self.error_code(),
+                self,
+                &[("expected", expected), ("actual", actual)],
+            ),
+            DecodeSignatureError::TrailingBytes => {
+                serialize_error(serializer, "TrailingBytes", self.error_code(), self, &[])
+            }
+            DecodeSignatureError::TooLong => {
+                serialize_error(serializer, "TooLong", self.error_code(), self, &[])
+            }
+        }
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl std::error::Error for DecodeSignatureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeSignatureError::InvalidBase64(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "multisig")]
+impl From<DecodeSignatureError> for io::Error {
+    fn from(err: DecodeSignatureError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(all(feature = "multisig", feature = "defmt"))]
+impl defmt::Format for DecodeSignatureError {
+    fn format(&self, f: defmt::Formatter) {
+        match *self {
+            DecodeSignatureError::InvalidBase64(_) => defmt::write!(f, "Invalid base64"),
+            DecodeSignatureError::NoDot => defmt::write!(f, "No dot"),
+            DecodeSignatureError::NoDotSig => defmt::write!(f, "No .sig"),
+            DecodeSignatureError::UnknownSuffix => defmt::write!(f, "Unknown suffix"),
+            DecodeSignatureError::WrongLength { expected, actual } => {
+                defmt::write!(
+                    f,
+                    "Data of wrong length: expected {} bytes, got {}",
+                    expected,
+                    actual
+                )
+            }
+            DecodeSignatureError::TrailingBytes => defmt::write!(f, "Trailing bytes"),
+            DecodeSignatureError::TooLong => defmt::write!(f, "Too long"),
+        }
+    }
+}
 
 /// The legacy suffix indicating the ed25519 cryptographic primitive.
-const ED25519_SUFFIX: &'static [u8] = b"ed25519";
+const ED25519_SUFFIX: &[u8] = b"ed25519";
 /// Length of a base64 encoded ed25519 public key.
 const ED25519_PK_BASE64_LEN: usize = 44;
 /// Length of a legacy-encoded ssb `Multikey` which uses the ed25519 cryptographic primitive.
-const SSB_ED25519_ENCODED_LEN: usize = ED25519_PK_BASE64_LEN + 9;
-/// Length of a base64 encoded ed25519 public key.
+pub(crate) const SSB_ED25519_ENCODED_LEN: usize = ED25519_PK_BASE64_LEN + 9;
+/// Length of a base64 encoded ed25519 signature.
+#[cfg(feature = "multisig")]
 const ED25519_SIG_BASE64_LEN: usize = 88;
+/// Length of a legacy-encoded ssb ed25519 signature.
+#[cfg(feature = "multisig")]
+const SSB_ED25519_SIG_ENCODED_LEN: usize = ED25519_SIG_BASE64_LEN + 12;
+/// Length of a base64 encoded ed25519 secret key, which consists of the seed and the public key.
+const ED25519_SECRET_BASE64_LEN: usize = 88;
 /// Length of a legacy-encoded ssb ed25519 secret key.
-const SSB_ED25519_SECRET_ENCODED_LEN: usize = 96;
+const SSB_ED25519_SECRET_ENCODED_LEN: usize = ED25519_SECRET_BASE64_LEN + 8;
 
 #[test]
 fn test_from_legacy() {
@@ -407,3 +1638,453 @@ fn test_from_legacy() {
         Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA==.ed25519").is_err()
     );
 }
+
+#[cfg(feature = "futures")]
+#[test]
+fn test_to_legacy_async() {
+    let (mk, _) =
+        Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519").unwrap();
+    let mut out = Vec::new();
+    futures::executor::block_on(mk.to_legacy_async(&mut out)).unwrap();
+    assert_eq!(out, mk.to_legacy_vec());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_legacy_buf() {
+    let enc = &b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\"tail"[..];
+    let mut buf = bytes::BytesMut::from(enc);
+
+    let mk = Multikey::from_legacy_buf(&mut buf).unwrap();
+    assert_eq!(&buf[..], b"\"tail");
+
+    let mut out = bytes::BytesMut::new();
+    mk.to_legacy_buf(&mut out);
+    assert_eq!(&out[..], &enc[..enc.len() - 5]);
+
+    let mut buf = bytes::BytesMut::from(&b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed2"[..]);
+    assert!(Multikey::from_legacy_buf(&mut buf).is_err());
+    assert_eq!(buf.len(), 49);
+
+    // Split across chunks.
+    use bytes::Buf;
+    let mut chain = Buf::chain(&enc[..20], &enc[20..]);
+    assert_eq!(Multikey::from_legacy_buf(&mut chain).unwrap(), mk);
+    assert_eq!(chain.remaining(), 5);
+
+    let mut chain = Buf::chain(&enc[..20], &b"@nope"[..]);
+    assert!(Multikey::from_legacy_buf(&mut chain).is_err());
+    assert_eq!(chain.remaining(), 25);
+}
+
+#[test]
+fn test_from_legacy_exact() {
+    assert!(
+        Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+            .is_ok()
+    );
+    assert_eq!(
+        Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\""),
+        Err(DecodeLegacyError::TrailingBytes)
+    );
+    assert_eq!(
+        Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed2551"),
+        Err(DecodeLegacyError::UnknownSuffix)
+    );
+}
+
+#[cfg(feature = "multisig")]
+#[test]
+fn test_from_legacy_counted() {
+    let enc = b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\"";
+    let (mk, consumed) = Multikey::from_legacy_counted(enc).unwrap();
+    assert_eq!(consumed, SSB_ED25519_ENCODED_LEN);
+
+    let sig = Multisig::from_ed25519(&[7; 64]);
+    let mut enc = sig.to_legacy_vec();
+    enc.push(b'"');
+    assert_eq!(
+        mk.sig_from_legacy_counted(&enc).unwrap(),
+        (sig, SSB_ED25519_SIG_ENCODED_LEN)
+    );
+}
+
+#[cfg(feature = "multisig")]
+#[test]
+fn test_from_legacy_located() {
+    let fails = [
+        (
+            &b"&zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"[..],
+            0,
+        ),
+        (&b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA="[..], 45),
+        (
+            &b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.dd25519"[..],
+            46,
+        ),
+        (
+            &b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8h==.ed25519"[..],
+            1,
+        ),
+        (
+            &b"@zurF8X68ArfRM71dF3mKh36W0xDM8Q!OnAS5bYOq8hA=.ed25519"[..],
+            31,
+        ),
+    ];
+
+    for &(input, offset) in fails.iter() {
+        assert_eq!(
+            Multikey::from_legacy_located(input).unwrap_err().offset,
+            offset
+        );
+    }
+
+    let mk = Multikey::from_ed25519(&[0; 32]);
+    let sig = b"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA==.sig.dd25519";
+    assert_eq!(mk.sig_from_legacy_located(sig).unwrap_err().offset, 92);
+}
+
+#[test]
+fn test_error_source() {
+    use std::error::Error;
+
+    let err = Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB=.ed25519")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Invalid base64");
+    assert!(err.source().is_some());
+    assert!(DecodeLegacyError::NoDot.source().is_none());
+}
+
+#[test]
+fn test_from_legacy_with() {
+    let expected =
+        Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+            .unwrap();
+
+    let deviations: &[&[u8]] = &[
+        b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA.ed25519",
+        b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB=.ed25519",
+        b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.Ed25519",
+    ];
+
+    for s in deviations {
+        assert!(Multikey::from_legacy(s).is_err());
+        assert_eq!(
+            Multikey::from_legacy_with(s, &ParseOptions::lenient()).unwrap(),
+            (expected.clone(), &[][..])
+        );
+    }
+
+    let opts = ParseOptions {
+        max_len: Some(43),
+        ..ParseOptions::default()
+    };
+    assert_matches!(
+        Multikey::from_legacy_with(
+            b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519",
+            &opts
+        ),
+        Err(DecodeLegacyError::TooLong)
+    );
+}
+
+#[cfg(feature = "multisig")]
+#[test]
+fn test_fmt_legacy() {
+    let mk = Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+        .unwrap();
+    assert_eq!(mk.to_string(), mk.to_legacy_string());
+    assert_eq!(&mk.to_legacy_array()[..], mk.to_string().as_bytes());
+
+    let sig = Multisig::from_ed25519(&[7; 64]);
+    assert_eq!(sig.to_string(), sig.to_legacy_string());
+    assert_eq!(&sig.to_legacy_array()[..], sig.to_string().as_bytes());
+
+    let mut s = String::new();
+    Multisecret(SecretKey::from_slice(&[3; 64]).unwrap())
+        .fmt_legacy(&mut s)
+        .unwrap();
+    assert_eq!(s.len(), SSB_ED25519_SECRET_ENCODED_LEN);
+}
+
+#[test]
+fn test_short_hash() {
+    assert_eq!(
+        Multikey::from_ed25519(&[7; 32]).short_hash(),
+        0xf160_fd08_b6d9_aac5
+    );
+}
+
+#[test]
+fn test_short() {
+    let mk = Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+        .unwrap();
+    assert_eq!(mk.short(), "@zurF8X….ed25519");
+    assert_eq!(mk.short_n(9), "@zurF8X68A….ed25519");
+    assert_eq!(mk.short_n(44), mk.to_legacy_string());
+}
+
+#[test]
+fn test_error_conversions() {
+    let err =
+        Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed2551").unwrap_err();
+
+    let de: serde::de::value::Error = err.clone().into_de_error();
+    assert_eq!(de.to_string(), "Invalid multikey: Unknown suffix");
+
+    let io_err: io::Error = err.into();
+    assert_eq!(io_err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        io_err.into_inner().unwrap().to_string(),
+        DecodeLegacyError::UnknownSuffix.to_string()
+    );
+}
+
+#[cfg(feature = "multisig")]
+#[test]
+fn test_hex() {
+    let mk = Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+        .unwrap();
+    assert_eq!(
+        mk.to_hex(),
+        "ceeac5f17ebc02b7d133bd5d17798a877e96d310ccf1098e9c04b96d83aaf210"
+    );
+    assert_eq!(Multikey::from_hex(&mk.to_hex()).unwrap(), mk);
+    assert_eq!(
+        Multikey::from_hex("ceea"),
+        Err(hex::DecodeHexError::WrongLength)
+    );
+
+    let sig = Multisig::from_ed25519(&[0xab; 64]);
+    assert_eq!(sig.to_hex(), "ab".repeat(64));
+    assert_eq!(Multisig::from_hex(&sig.to_hex()).unwrap(), sig);
+}
+
+#[test]
+fn test_dns_label() {
+    let enc = b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    let mk = Multikey::from_legacy_exact(enc).unwrap();
+
+    let label = mk.to_dns_label();
+    assert_eq!(label.len(), 52);
+    assert!(label
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit()));
+    let back = Multikey::from_dns_label(&label).unwrap();
+    assert_eq!(back.to_legacy_vec(), enc.to_vec());
+
+    assert!(Multikey::from_dns_label(&label.to_uppercase()).is_err());
+    assert!(Multikey::from_dns_label(&label[..51]).is_err());
+}
+
+#[test]
+fn test_multikey_ref() {
+    let key = Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+        .unwrap();
+    let buf = key.as_ed25519().unwrap().to_vec();
+
+    let key_ref = MultikeyRef::from_ed25519_slice(&buf).unwrap();
+    assert_eq!(key_ref, key);
+    assert_eq!(key_ref, key.as_multikey_ref());
+    assert_eq!(key_ref.to_multikey(), key);
+    assert_eq!(key_ref.to_string(), key.to_string());
+    assert_eq!(MultikeyRef::from_ed25519_slice(&buf[1..]), None);
+
+    let other = Multikey::from_ed25519(&[0; 32]);
+    assert_eq!(key_ref.cmp(&other.as_multikey_ref()), key.cmp(&other));
+}
+
+#[cfg(feature = "multisig")]
+#[test]
+fn test_gabbygrove_sig() {
+    let sig = Multisig::from_ed25519(&[7; 64]);
+    let raw = sig.to_gabbygrove().unwrap();
+    assert_eq!(raw, vec![7; 64]);
+    assert_eq!(Multisig::from_gabbygrove(&raw).unwrap(), sig);
+    assert_eq!(
+        Multisig::from_gabbygrove(&raw[1..]),
+        Err(DecodeSignatureError::WrongLength {
+            expected: 64,
+            actual: 63
+        })
+    );
+}
+
+#[test]
+fn test_sigilless() {
+    let key = Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+        .unwrap();
+
+    assert_eq!(
+        Multikey::from_sigilless(b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519\n"),
+        Ok((key.clone(), &b"\n"[..]))
+    );
+    assert_matches!(
+        Multikey::from_sigilless(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"),
+        Err(DecodeLegacyError::WrongLength { .. })
+    );
+    assert_eq!(
+        Multikey::from_base64(b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA="),
+        Ok(key)
+    );
+    assert_eq!(
+        Multikey::from_base64(b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8g=="),
+        Err(DecodeLegacyError::WrongLength {
+            expected: 32,
+            actual: 31
+        })
+    );
+    assert_matches!(
+        Multikey::from_base64(b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB="),
+        Err(DecodeLegacyError::InvalidBase64(_))
+    );
+}
+
+#[test]
+fn test_multisecret_legacy() {
+    let secret = Multisecret::from_seed(&[7; 32]);
+    let mut enc = Vec::new();
+    secret.to_legacy(&mut enc).unwrap();
+    assert_eq!(enc.len(), SSB_ED25519_SECRET_ENCODED_LEN);
+
+    assert_eq!(Multisecret::from_legacy_exact(&enc).unwrap(), secret);
+    let mut with_tail = enc.clone();
+    with_tail.extend_from_slice(b"\"");
+    assert_eq!(
+        Multisecret::from_legacy(&with_tail).unwrap(),
+        (secret.clone(), &b"\""[..])
+    );
+    assert_eq!(
+        Multisecret::from_legacy_with_engine(&enc, &ParseOptions::default(), &DefaultEngine)
+            .unwrap()
+            .0,
+        secret
+    );
+    let mut fmt = String::new();
+    secret.fmt_legacy(&mut fmt).unwrap();
+    assert_eq!(fmt.as_bytes(), &enc[..]);
+
+    assert_eq!(
+        Multisecret::from_legacy(b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"),
+        Err(DecodeLegacyError::WrongLength {
+            expected: 64,
+            actual: 32
+        })
+    );
+    assert_eq!(
+        Multisecret::from_legacy_exact(&with_tail),
+        Err(DecodeLegacyError::TrailingBytes)
+    );
+}
+
+#[test]
+fn test_wrong_length() {
+    assert_eq!(
+        Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8g==.ed25519"),
+        Err(DecodeLegacyError::WrongLength {
+            expected: 32,
+            actual: 31
+        })
+    );
+    assert_eq!(
+        Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hAA.ed25519"),
+        Err(DecodeLegacyError::WrongLength {
+            expected: 32,
+            actual: 33
+        })
+    );
+    assert_eq!(
+        Multikey::from_legacy(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA.ed25519"),
+        Err(DecodeLegacyError::InvalidBase64(
+            base64::DecodeError::InvalidLength
+        ))
+    );
+    assert_eq!(
+        DecodeLegacyError::WrongLength {
+            expected: 32,
+            actual: 31
+        }
+        .to_string(),
+        "Data of wrong length: expected 32 bytes, got 31"
+    );
+}
+
+#[cfg(feature = "multisig")]
+#[test]
+fn test_sign_with_cap() {
+    ssb_crypto::init();
+    let (pk, sk) = ssb_crypto::generate_longterm_keypair();
+    let key = Multikey(_Multikey::Ed25519(pk));
+    let secret = Multisecret(sk);
+    let test_net = NetworkKey::from_slice(&[9; 32]).unwrap();
+
+    let sig = secret.sign(b"hello", None);
+    assert!(key.is_signature_correct(b"hello", &sig));
+    assert!(!key.is_signature_correct_with_cap(b"hello", &sig, Some(&test_net)));
+
+    let sig = secret.sign(b"hello", Some(&test_net));
+    assert!(key.is_signature_correct_with_cap(b"hello", &sig, Some(&test_net)));
+    assert!(!key.is_signature_correct(b"hello", &sig));
+    assert!(!key.is_signature_correct_with_cap(b"hellO", &sig, Some(&test_net)));
+}
+
+#[cfg(all(feature = "multisig", feature = "ed25519-dalek"))]
+#[test]
+fn test_verify_batch() {
+    ssb_crypto::init();
+    let (pk, sk) = ssb_crypto::generate_longterm_keypair();
+    let key = Multikey(_Multikey::Ed25519(pk));
+    let secret = Multisecret(sk);
+
+    let texts: Vec<Vec<u8>> = (0..8u8).map(|i| vec![i; 100]).collect();
+    let mut items: Vec<(Multikey, Multisig, &[u8])> = texts
+        .iter()
+        .map(|text| (key.clone(), secret.sign(text, None), &text[..]))
+        .collect();
+    assert_eq!(verify_batch(&items), Ok(()));
+    assert_eq!(verify_batch(&[]), Ok(()));
+
+    items[5].2 = &texts[0];
+    items[6].1 = Multisig::from_ed25519(&[0; 64]);
+    assert_eq!(verify_batch(&items), Err(5));
+}
+
+#[test]
+fn test_from_seed() {
+    // Test vector 1 of RFC 8032.
+    let mut seed = [0u8; 32];
+    hex::decode_hex(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        &mut seed,
+    )
+    .unwrap();
+
+    let key = Multikey::from_seed(&seed);
+    assert_eq!(
+        key.to_hex(),
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"
+    );
+    assert_eq!(Multikey::from_seed(&seed), key);
+
+    let secret = Multisecret::from_seed(&seed);
+    assert_eq!(&secret.as_ed25519().unwrap()[..32], &seed[..]);
+    assert_eq!(
+        &secret.as_ed25519().unwrap()[32..],
+        key.as_ed25519().unwrap()
+    );
+    #[cfg(feature = "multisig")]
+    assert!(key.is_signature_correct(b"hello", &secret.sign(b"hello", None)));
+}
+
+#[test]
+fn test_debug() {
+    let mk = Multikey::from_ed25519(&[0; 32]);
+    assert_eq!(
+        format!("{:?}", mk),
+        "Multikey(Ed25519, \"@AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519\")"
+    );
+    assert_eq!(
+        format!("{:?}", mk.as_multikey_ref()),
+        "MultikeyRef(Ed25519, \"@AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519\")"
+    );
+}
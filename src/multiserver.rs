@@ -0,0 +1,690 @@
+//! [Multiserver addresses](https://github.com/ssbc/multiserver-address), which describe how to
+//! connect to a peer.
+//!
+//! An address is a protocol followed by any number of transforms, separated by `~`, e.g.
+//! `net:192.168.1.5:8008~shs:zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=`. Each of these
+//! components is a name followed by any number of data fields, separated by `:`. Within data,
+//! the reserved characters `!`, `:`, `;` and `~` are escaped by prefixing them with `!`.
+use std::fmt;
+
+use serde::{
+    de::{Deserialize, Deserializer},
+    ser::{Serialize, Serializer},
+};
+
+use super::multikey::Multikey;
+use super::{fmt_base64, percent_decode, percent_encode};
+
+// Characters that must be escaped within data.
+const RESERVED: &[char] = &['!', ':', ';', '~'];
+
+/// A single component of a multiserver address: a protocol or a transform.
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+pub struct Component {
+    name: String,
+    data: Vec<String>,
+}
+
+impl Component {
+    /// Create a component from its name and its (unescaped) data fields.
+    pub fn new<N: Into<String>>(name: N, data: Vec<String>) -> Component {
+        Component {
+            name: name.into(),
+            data,
+        }
+    }
+
+    /// The name of the protocol or transform, e.g. `net` or `shs`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The unescaped data fields.
+    pub fn data(&self) -> &[String] {
+        &self.data
+    }
+}
+
+/// A multiserver address: a protocol followed by transforms.
+///
+/// Addresses are ordered by their protocol, then by their transforms in order. Components are
+/// ordered by name, then by their data fields. Note that this is not the order of the string
+/// forms, e.g. `net:b~shs:x` sorts before `net:b:1~shs:x`.
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+pub struct MultiserverAddress {
+    protocol: Component,
+    transforms: Vec<Component>,
+}
+
+impl MultiserverAddress {
+    /// Start building an address for the `net` protocol (plain TCP).
+    pub fn net<H: Into<String>>(host: H, port: u16) -> MultiserverAddressBuilder {
+        MultiserverAddress::with_protocol("net", vec![host.into(), port.to_string()])
+    }
+
+    /// Start building an address for the `ws` protocol (websockets).
+    pub fn ws<H: Into<String>>(host: H, port: u16) -> MultiserverAddressBuilder {
+        MultiserverAddress::with_protocol("ws", vec![host.into(), port.to_string()])
+    }
+
+    /// Start building an address for the `onion` protocol (tor).
+    pub fn onion<H: Into<String>>(host: H, port: u16) -> MultiserverAddressBuilder {
+        MultiserverAddress::with_protocol("onion", vec![host.into(), port.to_string()])
+    }
+
+    /// The address of a peer that is reached through a portal, such as a room, which tunnels
+    /// the connection: `tunnel:<portal id>:<target id>~shs:<target key>`.
+    pub fn tunnel(portal: &Multikey, target: &Multikey) -> MultiserverAddress {
+        MultiserverAddress::with_protocol(
+            "tunnel",
+            vec![portal.to_legacy_string(), target.to_legacy_string()],
+        )
+        .shs(target)
+        .build()
+        .unwrap()
+    }
+
+    /// Start building an address for an arbitrary protocol.
+    pub fn with_protocol<N: Into<String>>(name: N, data: Vec<String>) -> MultiserverAddressBuilder {
+        MultiserverAddressBuilder {
+            protocol: Component::new(name, data),
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Parse a single multiserver address. An unescaped `;` separates addresses, so input
+    /// containing one is rejected; use [`parse_address_list`](fn.parse_address_list.html) for
+    /// lists of addresses.
+    pub fn parse(s: &str) -> Result<MultiserverAddress, DecodeMultiserverError> {
+        if has_unfinished_escape(s) {
+            return Err(DecodeMultiserverError::InvalidEscape);
+        }
+        if split_unescaped(s, ';').len() > 1 {
+            return Err(DecodeMultiserverError::UnescapedSemicolon);
+        }
+
+        let mut components = split_unescaped(s, '~')
+            .into_iter()
+            .map(parse_component)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let protocol = components.remove(0);
+        Ok(MultiserverAddress {
+            protocol,
+            transforms: components,
+        })
+    }
+
+    /// Parse an address that was percent-encoded as a component of a URI, such as the
+    /// `multiserverAddress` query parameter of room alias URIs, e.g.
+    /// `net%3Aroom.example%3A8008~shs%3A...`.
+    pub fn from_uri_component(s: &str) -> Result<MultiserverAddress, DecodeMultiserverError> {
+        let decoded =
+            percent_decode(s.as_bytes()).ok_or(DecodeMultiserverError::PercentEncoding)?;
+        let decoded =
+            String::from_utf8(decoded).map_err(|_| DecodeMultiserverError::PercentEncoding)?;
+        MultiserverAddress::parse(&decoded)
+    }
+
+    /// The address percent-encoded for use as a component of a URI, such as a query parameter.
+    pub fn to_uri_component(&self) -> String {
+        let mut out = String::new();
+        percent_encode(self.to_string().as_bytes(), &mut out);
+        out
+    }
+
+    /// The protocol of the address.
+    pub fn protocol(&self) -> &Component {
+        &self.protocol
+    }
+
+    /// The transforms of the address, in order.
+    pub fn transforms(&self) -> &[Component] {
+        &self.transforms
+    }
+
+    /// The key of the first secret-handshake transform, if there is one and its first data field
+    /// is the base64 encoding of an ed25519 public key.
+    pub fn shs_key(&self) -> Option<Multikey> {
+        let encoded = self
+            .transforms
+            .iter()
+            .find(|t| t.name == "shs")?
+            .data
+            .first()?;
+
+        Multikey::from_base64(encoded.as_bytes()).ok()
+    }
+
+    /// The portal and the target of a `tunnel` address, if both of its data fields are feed ids
+    /// and any secret-handshake key is that of the target.
+    pub fn tunnel_keys(&self) -> Option<(Multikey, Multikey)> {
+        if self.protocol.name != "tunnel" || self.protocol.data.len() != 2 {
+            return None;
+        }
+
+        let portal = Multikey::from_legacy_str(&self.protocol.data[0]).ok()?;
+        let target = Multikey::from_legacy_str(&self.protocol.data[1]).ok()?;
+        let has_shs = self.transforms.iter().any(|t| t.name == "shs");
+        if has_shs && self.shs_key().as_ref() != Some(&target) {
+            return None;
+        }
+        Some((portal, target))
+    }
+
+    /// Return a copy of this address that authenticates the peer with the given key. The key
+    /// replaces that of the first secret-handshake transform, or is added as a new transform if
+    /// there is none. Any further data of the transform (e.g. a cap) is kept.
+    pub fn with_shs_key(&self, key: &Multikey) -> MultiserverAddress {
+        let mut encoded = String::new();
+        let _ = fmt_base64(&mut encoded, key.as_ed25519().unwrap());
+
+        let mut addr = self.clone();
+        match addr.transforms.iter_mut().find(|t| t.name == "shs") {
+            Some(shs) if shs.data.is_empty() => shs.data.push(encoded),
+            Some(shs) => shs.data[0] = encoded,
+            None => addr.transforms.push(Component::new("shs", vec![encoded])),
+        }
+        addr
+    }
+}
+
+impl fmt::Display for MultiserverAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_component(f, &self.protocol)?;
+        for transform in &self.transforms {
+            f.write_str("~")?;
+            fmt_component(f, transform)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes as the string form.
+impl Serialize for MultiserverAddress {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for MultiserverAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        MultiserverAddress::parse(&s).map_err(DecodeMultiserverError::into_de_error)
+    }
+}
+
+/// Parse a `;`-separated list of multiserver addresses, as found in peer records and invites.
+///
+/// Every nonempty entry yields its own result, so one malformed entry does not discard the
+/// others.
+pub fn parse_address_list(s: &str) -> Vec<Result<MultiserverAddress, DecodeMultiserverError>> {
+    split_unescaped(s, ';')
+        .into_iter()
+        .filter(|entry| !entry.is_empty())
+        .map(MultiserverAddress::parse)
+        .collect()
+}
+
+/// Join addresses into a `;`-separated list.
+pub fn address_list_to_string(addrs: &[MultiserverAddress]) -> String {
+    addrs
+        .iter()
+        .map(MultiserverAddress::to_string)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// A peer announced in a local network discovery broadcast.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LanPeer {
+    /// The announced address.
+    pub address: MultiserverAddress,
+    /// The feed id of the peer, taken from the address.
+    pub key: Multikey,
+}
+
+/// Extract the peers from the payload of a UDP local discovery datagram.
+///
+/// The payload is a `;`-separated list of addresses. Parsing is tolerant of what old clients
+/// send: invalid UTF-8, `null` entries, surrounding whitespace and NUL padding, duplicates and
+/// addresses without a secret-handshake key are skipped rather than rejected.
+pub fn parse_lan_broadcast(payload: &[u8]) -> Vec<LanPeer> {
+    let text = String::from_utf8_lossy(payload);
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+
+    let mut peers: Vec<LanPeer> = Vec::new();
+    for entry in split_unescaped(text, ';') {
+        let entry = entry.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if entry.is_empty() || entry == "null" {
+            continue;
+        }
+
+        if let Ok(address) = MultiserverAddress::parse(entry) {
+            if let Some(key) = address.shs_key() {
+                if !peers.iter().any(|p| p.address == address) {
+                    peers.push(LanPeer { address, key });
+                }
+            }
+        }
+    }
+    peers
+}
+
+fn fmt_component(f: &mut fmt::Formatter, component: &Component) -> fmt::Result {
+    f.write_str(&component.name)?;
+    for data in &component.data {
+        f.write_str(":")?;
+        for c in data.chars() {
+            if RESERVED.contains(&c) {
+                f.write_str("!")?;
+            }
+            write!(f, "{}", c)?;
+        }
+    }
+    Ok(())
+}
+
+// Split at every unescaped occurrence of the separator, keeping escapes intact.
+fn split_unescaped(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut chars = s.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        if c == '!' {
+            chars.next();
+        } else if c == sep {
+            parts.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&s[start..]);
+
+    parts
+}
+
+// Whether the input ends with a `!` that does not escape anything.
+fn has_unfinished_escape(s: &str) -> bool {
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '!' && chars.next().is_none() {
+            return true;
+        }
+    }
+    false
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut escaped = false;
+    for c in s.chars() {
+        if c == '!' && !escaped {
+            escaped = true;
+        } else {
+            out.push(c);
+            escaped = false;
+        }
+    }
+    out
+}
+
+// Component names are nonempty and consist of ascii alphanumerics and `-`.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn parse_component(s: &str) -> Result<Component, DecodeMultiserverError> {
+    let mut fields = split_unescaped(s, ':').into_iter();
+    let name = fields.next().unwrap_or("");
+
+    if !is_valid_name(name) {
+        return Err(DecodeMultiserverError::InvalidName);
+    }
+
+    Ok(Component {
+        name: name.to_string(),
+        data: fields.map(unescape).collect(),
+    })
+}
+
+/// Builds a `MultiserverAddress` component by component, escaping all data so that the result
+/// is always syntactically valid.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MultiserverAddressBuilder {
+    protocol: Component,
+    transforms: Vec<Component>,
+}
+
+impl MultiserverAddressBuilder {
+    /// Add a secret-handshake transform authenticating the peer with the given key.
+    pub fn shs(self, key: &Multikey) -> MultiserverAddressBuilder {
+        let mut encoded = String::new();
+        let _ = fmt_base64(&mut encoded, key.as_ed25519().unwrap());
+        self.transform("shs", vec![encoded])
+    }
+
+    /// Add an arbitrary transform.
+    pub fn transform<N: Into<String>>(
+        mut self,
+        name: N,
+        data: Vec<String>,
+    ) -> MultiserverAddressBuilder {
+        self.transforms.push(Component::new(name, data));
+        self
+    }
+
+    /// Finish the address, checking that all names are valid and that network protocols are
+    /// followed by a secret-handshake transform.
+    pub fn build(self) -> Result<MultiserverAddress, BuildMultiserverError> {
+        if !is_valid_name(&self.protocol.name)
+            || !self.transforms.iter().all(|t| is_valid_name(&t.name))
+        {
+            return Err(BuildMultiserverError::InvalidName);
+        }
+
+        let needs_shs = ["net", "ws", "wss", "onion"].contains(&self.protocol.name.as_str());
+        if needs_shs && !self.transforms.iter().any(|t| t.name == "shs") {
+            return Err(BuildMultiserverError::MissingShs);
+        }
+
+        Ok(MultiserverAddress {
+            protocol: self.protocol,
+            transforms: self.transforms,
+        })
+    }
+}
+
+/// Everything that can go wrong when parsing a multiserver address.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeMultiserverError {
+    /// A component name was empty or contained characters other than ascii alphanumerics
+    /// and `-`.
+    InvalidName,
+    /// The address ended with an unfinished `!` escape.
+    InvalidEscape,
+    /// The input contained an unescaped `;`, which separates the addresses of a list. Lists
+    /// are parsed with [`parse_address_list`](fn.parse_address_list.html).
+    UnescapedSemicolon,
+    /// A percent-encoded address contained a malformed escape, or did not decode to UTF-8.
+    PercentEncoding,
+}
+
+impl DecodeMultiserverError {
+    /// Convert into a serde deserialization error, for `Deserialize` impls that parse
+    /// multiserver addresses.
+    pub fn into_de_error<E: serde::de::Error>(self) -> E {
+        E::custom(format!("Invalid multiserver address: {}", self))
+    }
+}
+
+impl fmt::Display for DecodeMultiserverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeMultiserverError::InvalidName => write!(f, "Invalid component name"),
+            DecodeMultiserverError::InvalidEscape => write!(f, "Invalid escape"),
+            DecodeMultiserverError::PercentEncoding => write!(f, "Invalid percent-encoding"),
+            DecodeMultiserverError::UnescapedSemicolon => {
+                write!(f, "Unescaped semicolon, use parse_address_list for lists")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeMultiserverError {}
+
+/// Everything that can go wrong when building a multiserver address.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum BuildMultiserverError {
+    /// A component name was empty or contained characters other than ascii alphanumerics
+    /// and `-`.
+    InvalidName,
+    /// A network protocol was not followed by a secret-handshake (`shs`) transform.
+    MissingShs,
+}
+
+impl fmt::Display for BuildMultiserverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuildMultiserverError::InvalidName => write!(f, "Invalid component name"),
+            BuildMultiserverError::MissingShs => write!(f, "Missing shs transform"),
+        }
+    }
+}
+
+impl std::error::Error for BuildMultiserverError {}
+
+#[test]
+fn test_builder() {
+    let key = Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+        .unwrap();
+
+    let addr = MultiserverAddress::net("192.168.1.5", 8008)
+        .shs(&key)
+        .build()
+        .unwrap();
+    assert_eq!(
+        addr.to_string(),
+        "net:192.168.1.5:8008~shs:zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA="
+    );
+    assert_eq!(addr.protocol().name(), "net");
+    assert_eq!(
+        addr.transforms()[0].data(),
+        &["zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA="]
+    );
+
+    let ipv6 = MultiserverAddress::net("::1", 8008)
+        .shs(&key)
+        .build()
+        .unwrap();
+    assert_eq!(
+        ipv6.to_string(),
+        "net:!:!:1:8008~shs:zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA="
+    );
+    assert_eq!(MultiserverAddress::parse(&ipv6.to_string()).unwrap(), ipv6);
+
+    assert_eq!(
+        MultiserverAddress::net("localhost", 8008).build(),
+        Err(BuildMultiserverError::MissingShs)
+    );
+    assert_eq!(
+        MultiserverAddress::net("localhost", 8008)
+            .shs(&key)
+            .transform("no~pe", vec![])
+            .build(),
+        Err(BuildMultiserverError::InvalidName)
+    );
+    assert!(
+        MultiserverAddress::with_protocol("unix", vec!["/tmp/socket".to_string()])
+            .build()
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_parse() {
+    let addr = MultiserverAddress::parse("ws:host:80~shs:key~noauth").unwrap();
+    assert_eq!(addr.protocol().data(), &["host", "80"]);
+    assert_eq!(addr.transforms().len(), 2);
+    assert_eq!(addr.transforms()[1].name(), "noauth");
+    assert!(addr.transforms()[1].data().is_empty());
+
+    assert_eq!(
+        MultiserverAddress::parse("net:host!"),
+        Err(DecodeMultiserverError::InvalidEscape)
+    );
+    assert_eq!(
+        MultiserverAddress::parse("~shs:key"),
+        Err(DecodeMultiserverError::InvalidName)
+    );
+    assert_eq!(
+        MultiserverAddress::parse(""),
+        Err(DecodeMultiserverError::InvalidName)
+    );
+
+    assert_eq!(
+        MultiserverAddress::parse("net:a:1;ws:b:2"),
+        Err(DecodeMultiserverError::UnescapedSemicolon)
+    );
+    let addr =
+        MultiserverAddress::parse("net:!:!:1:8008~shs:zurF8X68ArfRM71dF3mKh36W0xDM8QmO+S5bYOq8hA=")
+            .unwrap();
+    let component = addr.to_uri_component();
+    assert_eq!(
+        component,
+        "net%3A%21%3A%21%3A1%3A8008~shs%3AzurF8X68ArfRM71dF3mKh36W0xDM8QmO%2BS5bYOq8hA%3D"
+    );
+    assert_eq!(
+        MultiserverAddress::from_uri_component(&component).unwrap(),
+        addr
+    );
+    assert_eq!(
+        MultiserverAddress::from_uri_component("net%3Ahost%3A80~noauth").unwrap(),
+        MultiserverAddress::parse("net:host:80~noauth").unwrap()
+    );
+    assert_eq!(
+        MultiserverAddress::from_uri_component("net%3Ahost%3"),
+        Err(DecodeMultiserverError::PercentEncoding)
+    );
+
+    let escaped = MultiserverAddress::parse("unix:a!;b").unwrap();
+    assert_eq!(escaped.protocol().data(), &["a;b"]);
+    assert_eq!(
+        MultiserverAddress::parse(&escaped.to_string()).unwrap(),
+        escaped
+    );
+}
+
+#[test]
+fn test_serde_and_ord() {
+    use serde::de::value::{Error, StrDeserializer};
+    use serde::de::IntoDeserializer;
+
+    let de: StrDeserializer<Error> = "net:host:8008~shs:key".into_deserializer();
+    let addr = MultiserverAddress::deserialize(de).unwrap();
+    assert_eq!(addr.to_string(), "net:host:8008~shs:key");
+
+    let de: StrDeserializer<Error> = "net:host!".into_deserializer();
+    assert_eq!(
+        MultiserverAddress::deserialize(de).unwrap_err().to_string(),
+        "Invalid multiserver address: Invalid escape"
+    );
+
+    let short = MultiserverAddress::parse("net:b~shs:x").unwrap();
+    let long = MultiserverAddress::parse("net:b:1~shs:x").unwrap();
+    assert!(short < long);
+    assert!(short.to_string() > long.to_string());
+}
+
+#[test]
+fn test_tunnel() {
+    let portal =
+        Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+            .unwrap();
+    let target = Multikey::from_ed25519(&[0; 32]);
+
+    let addr = MultiserverAddress::tunnel(&portal, &target);
+    assert_eq!(
+        addr.to_string(),
+        "tunnel:@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519:\
+         @AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=.ed25519\
+         ~shs:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="
+    );
+    let parsed = MultiserverAddress::parse(&addr.to_string()).unwrap();
+    assert_eq!(parsed.tunnel_keys(), Some((portal.clone(), target.clone())));
+
+    assert_eq!(
+        addr.with_shs_key(&portal).tunnel_keys(),
+        None,
+        "the shs key must be that of the target"
+    );
+    assert_eq!(
+        MultiserverAddress::parse("tunnel:@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+            .unwrap()
+            .tunnel_keys(),
+        None
+    );
+    assert_eq!(
+        MultiserverAddress::net("host", 8008)
+            .shs(&target)
+            .build()
+            .unwrap()
+            .tunnel_keys(),
+        None
+    );
+}
+
+#[test]
+fn test_shs_key() {
+    let key = Multikey::from_legacy_exact(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519")
+        .unwrap();
+    let other = Multikey::from_ed25519(&[0; 32]);
+
+    let addr = MultiserverAddress::net("host", 8008)
+        .shs(&key)
+        .build()
+        .unwrap();
+    assert_eq!(addr.shs_key(), Some(key.clone()));
+    assert_eq!(addr.with_shs_key(&other).shs_key(), Some(other.clone()));
+    assert_eq!(addr.with_shs_key(&other).transforms().len(), 1);
+
+    let template = MultiserverAddress::parse("net:host:8008").unwrap();
+    assert_eq!(template.shs_key(), None);
+    assert_eq!(template.with_shs_key(&key), addr);
+
+    let with_cap = MultiserverAddress::parse("net:host:8008~shs:placeholder:cap").unwrap();
+    assert_eq!(with_cap.shs_key(), None);
+    assert_eq!(
+        with_cap.with_shs_key(&other).to_string(),
+        "net:host:8008~shs:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=:cap"
+    );
+}
+
+#[test]
+fn test_parse_lan_broadcast() {
+    let a = "net:192.168.1.5:8008~shs:zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=";
+    let b = "ws:192.168.1.5:8989~shs:zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=";
+
+    let mut payload = format!("{};null; {};{};net:host:1;junk~~;", a, b, a).into_bytes();
+    payload.extend_from_slice(&[0xff, b';', 0, 0]);
+
+    let peers = parse_lan_broadcast(&payload);
+    assert_eq!(peers.len(), 2);
+    assert_eq!(peers[0].address.to_string(), a);
+    assert_eq!(peers[1].address.to_string(), b);
+    assert_eq!(
+        peers[0].key.to_legacy_string(),
+        "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"
+    );
+
+    assert!(parse_lan_broadcast(b"null").is_empty());
+    assert_eq!(parse_lan_broadcast(format!("{};x!", a).as_bytes()).len(), 1);
+}
+
+#[test]
+fn test_address_list() {
+    let list = parse_address_list("net:a:1~shs:k;;~shs:k;ws:b:2~shs:k!;x;net:c:3!");
+    assert_eq!(list.len(), 4);
+    assert_eq!(list[0].as_ref().unwrap().to_string(), "net:a:1~shs:k");
+    assert_eq!(list[1], Err(DecodeMultiserverError::InvalidName));
+    assert_eq!(list[2].as_ref().unwrap().transforms()[0].data(), &["k;x"]);
+    assert_eq!(list[3], Err(DecodeMultiserverError::InvalidEscape));
+
+    let addrs: Vec<_> = parse_address_list("net:a:1~shs:k;ws:b:2~shs:k!;x")
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        address_list_to_string(&addrs),
+        "net:a:1~shs:k;ws:b:2~shs:k!;x"
+    );
+    assert!(parse_address_list("").is_empty());
+}
@@ -0,0 +1,97 @@
+//! Node.js bindings via [napi-rs](https://napi.rs), so that the JavaScript SSB ecosystem can
+//! validate and convert multiformats with the same rules as Rust code using this crate.
+//!
+//! All functions take and return legacy encodings as strings, and raw keys and digests as
+//! `Buffer`s. To produce a loadable addon, build with
+//! `cargo rustc --release --features node --crate-type cdylib` and rename the resulting shared
+//! library to `ssb_multiformats.node`.
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
+use napi_derive::napi;
+
+use super::bindings;
+
+// Turn an error into a JavaScript error carrying its message.
+fn to_js_error<E: std::fmt::Display>(err: E) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multikey.
+#[napi(js_name = "isMultikey")]
+pub fn is_multikey(s: String) -> bool {
+    bindings::is_multikey(&s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multifeed.
+#[napi(js_name = "isMultifeed")]
+pub fn is_multifeed(s: String) -> bool {
+    bindings::is_multifeed(&s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// message.
+#[napi(js_name = "isMessageHash")]
+pub fn is_message_hash(s: String) -> bool {
+    bindings::is_message_hash(&s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// blob.
+#[napi(js_name = "isBlobHash")]
+pub fn is_blob_hash(s: String) -> bool {
+    bindings::is_blob_hash(&s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multibox.
+#[napi(js_name = "isMultibox")]
+pub fn is_multibox(s: String) -> bool {
+    bindings::is_multibox(&s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of an ed25519 signature.
+#[napi(js_name = "isSignature")]
+pub fn is_signature(s: String) -> bool {
+    bindings::is_signature(&s)
+}
+
+/// Determine what kind of value the string is the legacy encoding of: `"feed"`, `"message"`,
+/// `"blob"` or `"box"`. Returns `null` if it is none of these.
+#[napi]
+pub fn classify(s: String) -> Option<String> {
+    bindings::classify(&s).map(str::to_string)
+}
+
+/// Decode the legacy encoding of an ed25519 multikey into the 32 bytes of the public key.
+#[napi(js_name = "decodeMultikey")]
+pub fn decode_multikey(s: String) -> Result<Buffer> {
+    let pk = bindings::decode_multikey(&s).map_err(to_js_error)?;
+    Ok(pk.into())
+}
+
+/// Encode the 32 bytes of an ed25519 public key as a legacy multikey.
+#[napi(js_name = "encodeMultikey")]
+pub fn encode_multikey(pk: Buffer) -> Result<String> {
+    bindings::encode_multikey(&pk).map_err(to_js_error)
+}
+
+/// Decode the legacy encoding of a sha256 multihash (referring to either a message or a blob)
+/// into the 32 bytes of the digest.
+#[napi(js_name = "decodeMultihash")]
+pub fn decode_multihash(s: String) -> Result<Buffer> {
+    let digest = bindings::decode_multihash(&s).map_err(to_js_error)?;
+    Ok(digest.into())
+}
+
+/// Encode the 32 bytes of a sha256 digest as a legacy multihash referring to a message if
+/// `blob` is false, or to a blob if it is true.
+#[napi(js_name = "encodeMultihash")]
+pub fn encode_multihash(digest: Buffer, blob: bool) -> Result<String> {
+    bindings::encode_multihash(&digest, blob).map_err(to_js_error)
+}
+
+/// Decode the legacy encoding of an ed25519 signature into the 64 signature bytes.
+#[napi(js_name = "decodeSignature")]
+pub fn decode_signature(s: String) -> Result<Buffer> {
+    let sig = bindings::decode_signature(&s).map_err(to_js_error)?;
+    Ok(sig.into())
+}
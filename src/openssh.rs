@@ -0,0 +1,152 @@
+//! The [OpenSSH public key format](https://tools.ietf.org/html/rfc8709#section-4) of ed25519
+//! keys, `ssh-ed25519 AAAA... comment`, for reusing ssb identities with ssh tooling.
+use std::fmt;
+
+use base64;
+
+// The key type, both in the text form and inside the key blob.
+const KEY_TYPE: &[u8] = b"ssh-ed25519";
+
+// The length of the key blob: the length-prefixed key type followed by the length-prefixed key.
+const BLOB_LEN: usize = 4 + 11 + 4 + 32;
+
+// The length of the base64 encoding of the key blob.
+const BLOB_BASE64_LEN: usize = 68;
+
+// The text form of an ed25519 public key, without a comment.
+pub(crate) fn encode_openssh(pk: &[u8; 32]) -> String {
+    let mut blob = Vec::with_capacity(BLOB_LEN);
+    blob.extend_from_slice(&(KEY_TYPE.len() as u32).to_be_bytes());
+    blob.extend_from_slice(KEY_TYPE);
+    blob.extend_from_slice(&32u32.to_be_bytes());
+    blob.extend_from_slice(pk);
+
+    format!("ssh-ed25519 {}", base64::encode(&blob))
+}
+
+// Decode the text form of an ed25519 public key, ignoring the comment if there is one.
+pub(crate) fn decode_openssh(s: &str) -> Result<[u8; 32], DecodeOpenSshError> {
+    let mut fields = s.split_whitespace();
+    let key_type = fields.next().ok_or(DecodeOpenSshError::Malformed)?;
+    let data = fields.next().ok_or(DecodeOpenSshError::Malformed)?;
+
+    if key_type.as_bytes() != KEY_TYPE {
+        return Err(DecodeOpenSshError::UnknownKeyType);
+    }
+    if data.len() != BLOB_BASE64_LEN {
+        return Err(DecodeOpenSshError::InvalidBlob);
+    }
+
+    // Padded data of the right length decodes to a shorter blob.
+    let mut blob = [0u8; BLOB_LEN];
+    match base64::decode_config_slice(data, base64::STANDARD, &mut blob) {
+        Ok(BLOB_LEN) => {}
+        Ok(_) => return Err(DecodeOpenSshError::InvalidBlob),
+        Err(err) => return Err(DecodeOpenSshError::InvalidBase64(err)),
+    }
+
+    let (type_len, rest) = blob.split_at(4);
+    let (blob_type, rest) = rest.split_at(KEY_TYPE.len());
+    let (key_len, key) = rest.split_at(4);
+    if type_len != (KEY_TYPE.len() as u32).to_be_bytes()
+        || blob_type != KEY_TYPE
+        || key_len != 32u32.to_be_bytes()
+    {
+        return Err(DecodeOpenSshError::InvalidBlob);
+    }
+
+    let mut pk = [0u8; 32];
+    pk.copy_from_slice(key);
+    Ok(pk)
+}
+
+/// Everything that can go wrong when decoding a key from the OpenSSH public key format.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeOpenSshError {
+    /// The input was not a key type followed by the base64 key data.
+    Malformed,
+    /// The key was not an ed25519 key.
+    UnknownKeyType,
+    /// The key data was not valid base64.
+    InvalidBase64(base64::DecodeError),
+    /// The decoded key data was not an ed25519 key blob.
+    InvalidBlob,
+}
+
+impl fmt::Display for DecodeOpenSshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeOpenSshError::Malformed => write!(f, "Malformed OpenSSH key"),
+            DecodeOpenSshError::UnknownKeyType => write!(f, "Not an ssh-ed25519 key"),
+            DecodeOpenSshError::InvalidBase64(ref err) => write!(f, "{}", err),
+            DecodeOpenSshError::InvalidBlob => write!(f, "Invalid key blob"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeOpenSshError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeOpenSshError::InvalidBase64(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_openssh() {
+    use super::multikey::Multikey;
+
+    // Generated with `ssh-keygen -t ed25519 -C alice@host`.
+    let s = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINjQpjyjoJmhmpA54CJDpPaHRtJVblxGo8qsUfgJmmqE alice@host";
+    let key = Multikey::from_openssh(s).unwrap();
+    assert_eq!(
+        key.to_legacy_string(),
+        "@2NCmPKOgmaGakDngIkOk9odG0lVuXEajyqxR+AmaaoQ=.ed25519"
+    );
+    assert_eq!(
+        key.to_openssh(),
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINjQpjyjoJmhmpA54CJDpPaHRtJVblxGo8qsUfgJmmqE"
+    );
+    assert_eq!(Multikey::from_openssh(&key.to_openssh()).unwrap(), key);
+
+    assert_eq!(
+        decode_openssh("ssh-ed25519"),
+        Err(DecodeOpenSshError::Malformed)
+    );
+    assert_eq!(
+        decode_openssh(
+            "ssh-rsa AAAAC3NzaC1lZDI1NTE5AAAAINjQpjyjoJmhmpA54CJDpPaHRtJVblxGo8qsUfgJmmqE"
+        ),
+        Err(DecodeOpenSshError::UnknownKeyType)
+    );
+    assert_eq!(
+        decode_openssh(
+            "ssh-ed25519 AAAAD3NzaC1lZDI1NTE5AAAAINjQpjyjoJmhmpA54CJDpPaHRtJVblxGo8qsUfgJmmqE"
+        ),
+        Err(DecodeOpenSshError::InvalidBlob)
+    );
+    assert_eq!(
+        decode_openssh("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINjQpjyj"),
+        Err(DecodeOpenSshError::InvalidBlob)
+    );
+    // Truncated blobs whose padded encoding still has the full length.
+    let blob =
+        base64::decode("AAAAC3NzaC1lZDI1NTE5AAAAINjQpjyjoJmhmpA54CJDpPaHRtJVblxGo8qsUfgJmmqE")
+            .unwrap();
+    for len in &[49, 50] {
+        let data = base64::encode(&blob[..*len]);
+        assert_eq!(data.len(), BLOB_BASE64_LEN);
+        assert_eq!(
+            decode_openssh(&format!("ssh-ed25519 {}", data)),
+            Err(DecodeOpenSshError::InvalidBlob)
+        );
+    }
+    assert_matches!(
+        decode_openssh(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINjQpjyjoJmhmpA54CJDpPaHRtJVblxGo8qsUfgJmmq!"
+        ),
+        Err(DecodeOpenSshError::InvalidBase64(_))
+    );
+}
@@ -0,0 +1,166 @@
+//! The low-level building blocks of the legacy decoders, for writing parsers of adjacent ssb
+//! formats that should accept and reject exactly what this crate does.
+use std::borrow::Cow;
+
+use super::{base64_value, is_valid_base64, ParseOptions, BASE64_ALPHABET};
+
+/// Split the input slice at the first occurence of the given byte, the byte itself is not
+/// part of any of the returned slices. Return `None` if the byte is not found in the input.
+pub fn split_at_byte(input: &[u8], byte: u8) -> Option<(&[u8], &[u8])> {
+    for i in 0..input.len() {
+        if unsafe { *input.get_unchecked(i) } == byte {
+            let (start, end) = input.split_at(i);
+            return Some((start, &end[1..]));
+        }
+    }
+
+    None
+}
+
+/// If the slice begins with the given prefix, return everything after that prefix.
+pub fn skip_prefix<'a>(input: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
+    if input.starts_with(prefix) {
+        Some(&input[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+/// Like `skip_prefix`, but ignoring ASCII case if the options say so. Useful for matching the
+/// suffix of a legacy encoding.
+pub fn skip_suffix<'a>(input: &'a [u8], suffix: &[u8], opts: &ParseOptions) -> Option<&'a [u8]> {
+    if opts.case_insensitive_suffix {
+        if input.len() >= suffix.len() && input[..suffix.len()].eq_ignore_ascii_case(suffix) {
+            Some(&input[suffix.len()..])
+        } else {
+            None
+        }
+    } else {
+        skip_prefix(input, suffix)
+    }
+}
+
+/// The number of bytes that the (normalized) base64 portion of a legacy encoding decodes to,
+/// ignoring any padding.
+pub fn base64_decoded_len(data: &[u8]) -> usize {
+    let padding = data.iter().rev().take_while(|&&b| b == b'=').count();
+    (data.len() - padding) * 6 / 8
+}
+
+/// Bring the base64 portion of a legacy encoding into the canonical form that the strict
+/// decoders expect, undoing the deviations the options allow. Input that is already canonical,
+/// and any input under strict options, is borrowed unchanged. Anything that can not be repaired
+/// is left for the decoder to reject.
+pub fn normalize_base64<'a>(data: &'a [u8], opts: &ParseOptions) -> Cow<'a, [u8]> {
+    if (!opts.lenient_base64 && !opts.url_safe_alphabet) || is_canonical_base64(data) {
+        return Cow::Borrowed(data);
+    }
+
+    let mut out: Vec<u8> = data
+        .iter()
+        .map(|&b| match b {
+            b'-' if opts.url_safe_alphabet => b'+',
+            b'_' if opts.url_safe_alphabet => b'/',
+            b => b,
+        })
+        .collect();
+
+    if opts.lenient_base64 {
+        while out.last() == Some(&b'=') {
+            out.pop();
+        }
+
+        // Clear the bits of the last symbol that do not contribute to the decoded bytes.
+        let mask = match out.len() % 4 {
+            2 => 0b11_0000,
+            3 => 0b11_1100,
+            _ => 0b11_1111,
+        };
+        if let Some(last) = out.last_mut() {
+            if let Some(value) = base64_value(*last) {
+                *last = BASE64_ALPHABET[(value & mask) as usize];
+            }
+        }
+
+        while !out.len().is_multiple_of(4) {
+            out.push(b'=');
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+/// Whether the input is the canonical standard base64 encoding of some bytes: correctly padded,
+/// and with the unused bits of the last symbol set to zero. Every byte string has exactly one
+/// such encoding, which is what the strict decoders of this crate accept.
+pub fn is_canonical_base64(data: &[u8]) -> bool {
+    if !data.len().is_multiple_of(4) || !is_valid_base64(data) {
+        return false;
+    }
+
+    let padding = data.iter().rev().take_while(|&&b| b == b'=').count();
+    let mask = match padding {
+        1 => 0b00_0011,
+        2 => 0b00_1111,
+        _ => return true,
+    };
+    match base64_value(data[data.len() - padding - 1]) {
+        Some(value) => value & mask == 0,
+        None => false,
+    }
+}
+
+#[test]
+fn test_split_and_skip() {
+    assert_eq!(
+        split_at_byte(b"abc.sha256", b'.'),
+        Some((&b"abc"[..], &b"sha256"[..]))
+    );
+    assert_eq!(split_at_byte(b"abc", b'.'), None);
+
+    assert_eq!(skip_prefix(b"@abc", b"@"), Some(&b"abc"[..]));
+    assert_eq!(skip_prefix(b"abc", b"@"), None);
+
+    assert_eq!(skip_suffix(b"BOX", b"box", &ParseOptions::strict()), None);
+    assert_eq!(
+        skip_suffix(b"BOX,", b"box", &ParseOptions::lenient()),
+        Some(&b","[..])
+    );
+}
+
+#[test]
+fn test_canonical_base64() {
+    assert!(is_canonical_base64(b""));
+    assert!(is_canonical_base64(b"lA=="));
+    assert!(is_canonical_base64(
+        b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA="
+    ));
+    assert!(!is_canonical_base64(
+        b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB="
+    ));
+    assert!(!is_canonical_base64(b"lB=="));
+    assert!(!is_canonical_base64(b"lA"));
+    assert!(!is_canonical_base64(b"lA-_"));
+
+    assert_eq!(
+        base64_decoded_len(b"zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA="),
+        32
+    );
+    assert_eq!(base64_decoded_len(b"lA"), 1);
+
+    let lenient = ParseOptions::lenient();
+    for &(input, canonical) in &[(&b"lB"[..], &b"lA=="[..]), (b"-_8=", b"+/8=")] {
+        let normalized = normalize_base64(input, &lenient);
+        assert!(matches!(normalized, Cow::Owned(_)));
+        assert_eq!(&normalized[..], canonical);
+    }
+    for input in &[&b"lA=="[..], b"+/8="] {
+        let normalized = normalize_base64(input, &lenient);
+        assert!(matches!(normalized, Cow::Borrowed(_)));
+        assert_eq!(&normalized[..], *input);
+    }
+    assert!(matches!(
+        normalize_base64(b"lB", &ParseOptions::default()),
+        Cow::Borrowed(b"lB")
+    ));
+}
@@ -0,0 +1,138 @@
+//! Python bindings via [PyO3](https://pyo3.rs), so that scripts analyzing ssb data can apply
+//! exactly the same validation rules as Rust code using this crate.
+//!
+//! All functions take and return legacy encodings as `str`, and raw keys and digests as
+//! `bytes`. Invalid input raises a `ValueError`.
+use std::borrow::Cow;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use super::bindings;
+
+// Raise a `ValueError` with the message of the given error.
+fn value_error<E: std::fmt::Display>(err: E) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multikey.
+#[pyfunction]
+pub fn is_multikey(s: &str) -> bool {
+    bindings::is_multikey(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multifeed.
+#[pyfunction]
+pub fn is_multifeed(s: &str) -> bool {
+    bindings::is_multifeed(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// message.
+#[pyfunction]
+pub fn is_message_hash(s: &str) -> bool {
+    bindings::is_message_hash(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// blob.
+#[pyfunction]
+pub fn is_blob_hash(s: &str) -> bool {
+    bindings::is_blob_hash(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multibox.
+#[pyfunction]
+pub fn is_multibox(s: &str) -> bool {
+    bindings::is_multibox(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of an ed25519 signature.
+#[pyfunction]
+pub fn is_signature(s: &str) -> bool {
+    bindings::is_signature(s)
+}
+
+/// Determine what kind of value the string is the legacy encoding of: `"feed"`, `"message"`,
+/// `"blob"` or `"box"`. Returns `None` if it is none of these.
+#[pyfunction]
+pub fn classify(s: &str) -> Option<&'static str> {
+    bindings::classify(s)
+}
+
+/// Bring a feed id, message or blob hash, or box that may deviate from the canonical encoding
+/// (in padding, base64 alphabet or suffix case) into its canonical legacy encoding. Returns
+/// `None` if the string is none of these, even leniently.
+#[pyfunction]
+pub fn canonical_ref(s: &str) -> Option<String> {
+    bindings::canonical_ref(s)
+}
+
+/// Decode the legacy encoding of an ed25519 multikey into the 32 bytes of the public key.
+#[pyfunction]
+pub fn decode_multikey(s: &str) -> PyResult<Cow<'static, [u8]>> {
+    let pk = bindings::decode_multikey(s).map_err(value_error)?;
+    Ok(Cow::Owned(pk))
+}
+
+/// Encode the 32 bytes of an ed25519 public key as a legacy multikey.
+#[pyfunction]
+pub fn encode_multikey(pk: &[u8]) -> PyResult<String> {
+    bindings::encode_multikey(pk).map_err(value_error)
+}
+
+/// Decode the legacy encoding of a sha256 multihash (referring to either a message or a blob)
+/// into the 32 bytes of the digest.
+#[pyfunction]
+pub fn decode_multihash(s: &str) -> PyResult<Cow<'static, [u8]>> {
+    let digest = bindings::decode_multihash(s).map_err(value_error)?;
+    Ok(Cow::Owned(digest))
+}
+
+/// Encode the 32 bytes of a sha256 digest as a legacy multihash referring to a message if
+/// `blob` is false, or to a blob if it is true.
+#[pyfunction]
+#[pyo3(signature = (digest, blob = false))]
+pub fn encode_multihash(digest: &[u8], blob: bool) -> PyResult<String> {
+    bindings::encode_multihash(digest, blob).map_err(value_error)
+}
+
+/// Decode the legacy encoding of an ed25519 signature into the 64 signature bytes.
+#[pyfunction]
+pub fn decode_signature(s: &str) -> PyResult<Cow<'static, [u8]>> {
+    let sig = bindings::decode_signature(s).map_err(value_error)?;
+    Ok(Cow::Owned(sig))
+}
+
+/// The `ssb_multiformats` Python module.
+#[pymodule]
+fn ssb_multiformats(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(is_multikey, m)?)?;
+    m.add_function(wrap_pyfunction!(is_multifeed, m)?)?;
+    m.add_function(wrap_pyfunction!(is_message_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(is_blob_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(is_multibox, m)?)?;
+    m.add_function(wrap_pyfunction!(is_signature, m)?)?;
+    m.add_function(wrap_pyfunction!(classify, m)?)?;
+    m.add_function(wrap_pyfunction!(canonical_ref, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_multikey, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_multikey, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_multihash, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_multihash, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_signature, m)?)?;
+    Ok(())
+}
+
+#[test]
+fn test_classify() {
+    assert_eq!(
+        classify("@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519"),
+        Some("feed")
+    );
+    assert_eq!(
+        classify("&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256"),
+        Some("blob")
+    );
+    assert_eq!(classify("lA==.box\""), None);
+    assert_eq!(canonical_ref("lB.box").as_deref(), Some("lA==.box"));
+}
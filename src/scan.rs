@@ -0,0 +1,135 @@
+//! Find the feed ids and message and blob hashes inside arbitrary bytes, such as a memory-mapped
+//! segment of a log, without allocating.
+use std::fmt;
+
+use super::multihash::{Multihash, Target, SSB_SHA256_ENCODED_LEN};
+use super::multikey::{Multikey, SSB_ED25519_ENCODED_LEN};
+
+/// The kind of ref found by a [`RefScanner`](struct.RefScanner.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RefKind {
+    /// A feed id (`@...=.ed25519`).
+    Feed,
+    /// A message hash (`%...=.sha256`).
+    Message,
+    /// A blob hash (`&...=.sha256`).
+    Blob,
+}
+
+impl fmt::Display for RefKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RefKind::Feed => write!(f, "feed"),
+            RefKind::Message => write!(f, "message"),
+            RefKind::Blob => write!(f, "blob"),
+        }
+    }
+}
+
+/// Iterate over the canonical legacy encodings of feed ids and message and blob hashes in the
+/// input, yielding the offset at which each one starts together with its kind.
+///
+/// Every sigil in the input is a candidate, and only candidates that are followed by a valid
+/// encoding are yielded. Whatever precedes or follows an encoding is ignored, so refs are found
+/// inside of JSON strings, markdown and binary data alike. Nothing is allocated, neither per
+/// candidate nor per hit, which makes this suitable for building indexes over large logs.
+pub fn scan_refs(data: &[u8]) -> RefScanner<'_> {
+    RefScanner { data, pos: 0 }
+}
+
+/// An iterator over the refs in some bytes, created by [`scan_refs`](fn.scan_refs.html).
+#[derive(Debug, Clone)]
+pub struct RefScanner<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RefScanner<'a> {
+    /// The offset in the input at which scanning continues.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+impl<'a> Iterator for RefScanner<'a> {
+    type Item = (usize, RefKind);
+
+    fn next(&mut self) -> Option<(usize, RefKind)> {
+        while let Some(i) = self.data[self.pos..]
+            .iter()
+            .position(|&b| b == b'@' || b == b'%' || b == b'&')
+        {
+            let start = self.pos + i;
+            // No candidate is longer than the longest canonical encoding, which keeps the
+            // decoders from searching the rest of the input for a suffix separator.
+            let max_len = if self.data[start] == b'@' {
+                SSB_ED25519_ENCODED_LEN
+            } else {
+                SSB_SHA256_ENCODED_LEN
+            };
+            let candidate = &self.data[start..self.data.len().min(start + max_len)];
+
+            let found = if candidate[0] == b'@' {
+                Multikey::from_legacy(candidate)
+                    .ok()
+                    .map(|(_, tail)| (RefKind::Feed, tail.len()))
+            } else {
+                Multihash::from_legacy(candidate).ok().map(|(mh, tail)| {
+                    let kind = match mh.0 {
+                        Target::Message => RefKind::Message,
+                        Target::Blob => RefKind::Blob,
+                    };
+                    (kind, tail.len())
+                })
+            };
+
+            match found {
+                Some((kind, tail_len)) => {
+                    self.pos = start + candidate.len() - tail_len;
+                    return Some((start, kind));
+                }
+                None => self.pos = start + 1,
+            }
+        }
+
+        self.pos = self.data.len();
+        None
+    }
+}
+
+#[test]
+fn test_scan_refs() {
+    let data = br#"{"author":"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519","text":"50% off & more @ [%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256](&MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256), but not @zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hB=.ed25519"}"#;
+
+    let refs: Vec<_> = scan_refs(data).collect();
+    assert_eq!(
+        refs,
+        vec![
+            (11, RefKind::Feed),
+            (92, RefKind::Message),
+            (146, RefKind::Blob)
+        ]
+    );
+    for &(offset, kind) in &refs {
+        let sigil = match kind {
+            RefKind::Feed => b'@',
+            RefKind::Message => b'%',
+            RefKind::Blob => b'&',
+        };
+        assert_eq!(data[offset], sigil);
+    }
+
+    let mut scanner = scan_refs(b"@@%&");
+    assert_eq!(scanner.next(), None);
+    assert_eq!(scanner.position(), 4);
+}
+
+#[test]
+fn test_scan_refs_without_dots() {
+    // Candidates are bounded, so input without suffix separators is scanned in linear time.
+    let mut data = vec![b'@'; 1 << 20];
+    data.extend_from_slice(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256");
+    let refs: Vec<_> = scan_refs(&data).collect();
+    assert_eq!(refs, vec![(1 << 20, RefKind::Message)]);
+}
@@ -0,0 +1,101 @@
+//! Suggestions for fixing invalid legacy encodings, for user-facing tools that want to explain
+//! what is wrong with a pasted ref instead of just reporting the error.
+use super::{canonicalize, LegacyEncoding};
+
+// The suffixes of all legacy encodings.
+const SUFFIXES: &[&str] = &["ed25519", "sha256", "box"];
+
+// Suffixes at most this many edits away from a known suffix count as typos of it.
+const MAX_SUFFIX_DISTANCE: usize = 2;
+
+/// If the input is not a valid legacy encoding of a `T`, but is close to one, return the
+/// canonical legacy encoding it was probably meant to be.
+///
+/// This catches the deviations that `ParseOptions::lenient()` tolerates (missing padding,
+/// url-safe base64, suffix case, such as `.SHA256`), as well as typos in the suffix, such as
+/// `.ed25518`. Returns `None` if the input is valid, or if no close valid encoding was found.
+pub fn did_you_mean<T: LegacyEncoding>(s: &[u8]) -> Option<String> {
+    if T::from_legacy_exact(s).is_ok() {
+        return None;
+    }
+
+    if let Some(suggestion) = repair::<T>(s) {
+        return Some(suggestion);
+    }
+
+    let dot = s.iter().rposition(|&b| b == b'.')?;
+    let suffix = String::from_utf8_lossy(&s[dot + 1..]).to_lowercase();
+    SUFFIXES
+        .iter()
+        .filter(|known| edit_distance(suffix.as_bytes(), known.as_bytes()) <= MAX_SUFFIX_DISTANCE)
+        .find_map(|known| {
+            let mut candidate = s[..=dot].to_vec();
+            candidate.extend_from_slice(known.as_bytes());
+            repair::<T>(&candidate)
+        })
+}
+
+// Parse the whole input leniently, returning its canonical encoding.
+fn repair<T: LegacyEncoding>(s: &[u8]) -> Option<String> {
+    match canonicalize::<T>(s) {
+        Ok((enc, [])) => String::from_utf8(enc).ok(),
+        _ => None,
+    }
+}
+
+// The Levenshtein distance between two byte strings.
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, &ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = prev[j] + if ca == cb { 0 } else { 1 };
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(all(feature = "multikey", feature = "multihash"))]
+#[test]
+fn test_did_you_mean() {
+    use super::multihash::Multihash;
+    use super::multikey::Multikey;
+
+    let key = "@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25519";
+    assert_eq!(did_you_mean::<Multikey>(key.as_bytes()), None);
+    assert_eq!(
+        did_you_mean::<Multikey>(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA=.ed25518"),
+        Some(key.to_string())
+    );
+    assert_eq!(
+        did_you_mean::<Multikey>(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA.ed25519"),
+        Some(key.to_string())
+    );
+    assert_eq!(
+        did_you_mean::<Multikey>(b"@zurF8X68ArfRM71dF3mKh36W0xDM8QmOnAS5bYOq8hA.Ed2519"),
+        Some(key.to_string())
+    );
+
+    assert_eq!(
+        did_you_mean::<Multihash>(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.SHA256"),
+        Some("%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.sha256".to_string())
+    );
+    assert_eq!(
+        did_you_mean::<Multihash>(b"%MwjdLV95P7VqHfrgS49nScXsyIwJfL229e5OSKc+0rc=.md5"),
+        None
+    );
+    assert_eq!(did_you_mean::<Multikey>(b"@abc.ed25519"), None);
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance(b"ed25519", b"ed25519"), 0);
+    assert_eq!(edit_distance(b"ed25518", b"ed25519"), 1);
+    assert_eq!(edit_distance(b"ed2519", b"ed25519"), 1);
+    assert_eq!(edit_distance(b"", b"box"), 3);
+}
@@ -0,0 +1,443 @@
+//! Feeds, messages, blobs and private groups as
+//! [ssb URIs](https://github.com/ssb-ngi-pointer/ssb-uri-spec), such as
+//! `ssb:message/classic/<base64url>`.
+//!
+//! Older clients linked to refs in a number of nonstandard ways, which
+//! `SsbUri::from_legacy_uri` still understands, so that links in old messages keep working.
+//!
+//! URIs of other types and formats, e.g. from newer clients, are kept verbatim as
+//! `SsbUri::Unknown`, so that passing them on never changes them.
+//!
+//! Every feed, message, blob and group has exactly one canonical URI: a lowercase type and
+//! format, and url-safe base64 without padding. Only canonical URIs can be compared as strings,
+//! e.g. to deduplicate them in a database, so `normalize_uri` turns any URI into the canonical
+//! one.
+use std::fmt;
+
+use base64;
+
+use super::group::GroupId;
+use super::multihash::{self, Multihash, Target};
+use super::multikey::{self, Multikey};
+use super::{decode_base64url_32, percent_decode, Base64UrlError, ParseOptions};
+
+/// The scheme of ssb URIs, including the colon.
+pub const URI_SCHEME: &str = "ssb:";
+
+/// A feed, message, blob or group, as identified by an ssb URI, or an ssb URI of any other kind.
+#[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord, Hash)]
+pub enum SsbUri {
+    /// `ssb:feed/classic/<base64url>`
+    Feed(Multikey),
+    /// `ssb:message/classic/<base64url>`
+    Message(Multihash),
+    /// `ssb:blob/classic/<base64url>`
+    Blob(Multihash),
+    /// `ssb:identity/group/<base64url>`
+    Group(GroupId),
+    /// A URI of a type and format this crate does not know, split at its first two slashes.
+    /// The parts are kept exactly as they were, so that the URI serializes back unchanged.
+    Unknown {
+        /// The type, i.e. everything up to the first slash.
+        ty: String,
+        /// The format, if there is a slash after the type.
+        format: Option<String>,
+        /// Everything after the format, if there is a slash after it.
+        data: Option<String>,
+    },
+}
+
+impl SsbUri {
+    /// Parse a URI of the form `ssb:<type>/<format>/<base64url>`. The url-safe base64 may be
+    /// padded and percent-encoded, as in `%3D` for the padding. Any other URI with the `ssb:` scheme becomes `SsbUri::Unknown`.
+    pub fn from_uri(s: &str) -> Result<SsbUri, DecodeUriError> {
+        let rest = s
+            .strip_prefix(URI_SCHEME)
+            .ok_or(DecodeUriError::NotAnSsbUri)?;
+        let mut parts = rest.splitn(3, '/');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(ty), Some(format), Some(data)) if is_known(ty, format) => {
+                SsbUri::from_parts(ty, data)
+            }
+            _ => Ok(SsbUri::unknown(rest)),
+        }
+    }
+
+    /// Parse a URI that must be exactly the canonical URI of a feed, message, blob or group, as
+    /// produced by `to_uri`. URIs that are unknown even to `from_legacy_uri` are accepted as they
+    /// are.
+    pub fn from_uri_strict(s: &str) -> Result<SsbUri, DecodeUriError> {
+        let uri = SsbUri::from_legacy_uri(s)?;
+        if uri.to_uri() != s {
+            return Err(DecodeUriError::Noncanonic);
+        }
+        Ok(uri)
+    }
+
+    /// Parse an ssb URI, also accepting the variants emitted by older clients:
+    ///
+    /// - `ssb://` instead of `ssb:`, and percent-encoding anywhere after the scheme
+    /// - `:` instead of `/` as the separator, as in `ssb:message:sha256:<base64>`
+    /// - the format names `ed25519` and `sha256` instead of `classic`
+    /// - uppercase letters in the type and format
+    /// - standard instead of url-safe base64
+    /// - a legacy encoding instead of the type and format, as in `ssb:%25<base64>.sha256`
+    ///
+    /// URIs that are of no known type and format even then become `SsbUri::Unknown`, with the
+    /// parts exactly as they appear in the input.
+    pub fn from_legacy_uri(s: &str) -> Result<SsbUri, DecodeUriError> {
+        let raw = s
+            .strip_prefix(URI_SCHEME)
+            .ok_or(DecodeUriError::NotAnSsbUri)?;
+        let rest = raw.strip_prefix("//").unwrap_or(raw);
+        let decoded = percent_decode(rest.as_bytes()).ok_or(DecodeUriError::PercentEncoding)?;
+        let decoded = decoded.strip_prefix(b"/").unwrap_or(&decoded);
+
+        match decoded.first() {
+            Some(b'@') => {
+                let (key, tail) = Multikey::from_legacy_with(decoded, &ParseOptions::lenient())
+                    .map_err(DecodeUriError::Key)?;
+                return match tail {
+                    [] => Ok(SsbUri::Feed(key)),
+                    _ => Err(DecodeUriError::TrailingData),
+                };
+            }
+            Some(b'%') | Some(b'&') => {
+                let (mh, tail) = Multihash::from_legacy_with(decoded, &ParseOptions::lenient())
+                    .map_err(DecodeUriError::Hash)?;
+                return match (tail, mh.target()) {
+                    ([], Target::Message) => Ok(SsbUri::Message(mh)),
+                    ([], Target::Blob) => Ok(SsbUri::Blob(mh)),
+                    _ => Err(DecodeUriError::TrailingData),
+                };
+            }
+            _ => {}
+        }
+
+        let decoded = std::str::from_utf8(decoded).map_err(|_| DecodeUriError::PercentEncoding)?;
+        let mut parts = decoded.splitn(3, ['/', ':']);
+        let (ty, format, data) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(ty), Some(format), Some(data)) => (ty, format, data),
+            _ => return Ok(SsbUri::unknown(raw)),
+        };
+        let (ty, format) = (ty.to_ascii_lowercase(), format.to_ascii_lowercase());
+        let format = match (ty.as_str(), format.as_str()) {
+            ("feed", "ed25519") | ("message", "sha256") | ("blob", "sha256") => "classic",
+            (_, format) => format,
+        };
+        if !is_known(&ty, format) {
+            return Ok(SsbUri::unknown(raw));
+        }
+
+        let data: String = data
+            .chars()
+            .map(|c| match c {
+                '+' => '-',
+                '/' => '_',
+                c => c,
+            })
+            .collect();
+        SsbUri::from_parts(&ty, &data)
+    }
+
+    // The URI of the given known type and its data.
+    fn from_parts(ty: &str, data: &str) -> Result<SsbUri, DecodeUriError> {
+        let data = decode_data(data)?;
+        Ok(match ty {
+            "feed" => SsbUri::Feed(Multikey::from_ed25519(&data)),
+            "message" => SsbUri::Message(Multihash::message_from_digest(data)),
+            "blob" => SsbUri::Blob(Multihash::blob_from_digest(data)),
+            _ => SsbUri::Group(GroupId::from_bytes(data)),
+        })
+    }
+
+    // An unknown URI, given everything after the scheme.
+    fn unknown(rest: &str) -> SsbUri {
+        let mut parts = rest.splitn(3, '/');
+        SsbUri::Unknown {
+            ty: parts.next().unwrap_or("").to_string(),
+            format: parts.next().map(str::to_string),
+            data: parts.next().map(str::to_string),
+        }
+    }
+
+    /// The canonical `ssb:<type>/<format>/<base64url>` URI, without padding. Unknown URIs are
+    /// reassembled from their parts unchanged.
+    pub fn to_uri(&self) -> String {
+        let (ty, format, data) = match *self {
+            SsbUri::Unknown {
+                ref ty,
+                ref format,
+                ref data,
+            } => {
+                let mut uri = format!("{}{}", URI_SCHEME, ty);
+                for part in format.iter().chain(data.iter()) {
+                    uri.push('/');
+                    uri.push_str(part);
+                }
+                return uri;
+            }
+            SsbUri::Feed(ref key) => ("feed", "classic", key.as_ed25519().unwrap()),
+            SsbUri::Message(ref mh) => ("message", "classic", &mh.as_sha256().unwrap()[..]),
+            SsbUri::Blob(ref mh) => ("blob", "classic", &mh.as_sha256().unwrap()[..]),
+            SsbUri::Group(ref id) => ("identity", "group", &id.as_bytes()[..]),
+        };
+        format!(
+            "{}{}/{}/{}",
+            URI_SCHEME,
+            ty,
+            format,
+            base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+        )
+    }
+}
+
+// Whether the type and format are those of a feed, message, blob or group.
+fn is_known(ty: &str, format: &str) -> bool {
+    matches!(
+        (ty, format),
+        ("feed", "classic") | ("message", "classic") | ("blob", "classic") | ("identity", "group")
+    )
+}
+
+// Decode the data of a URI, which is exactly 32 bytes of url-safe base64.
+fn decode_data(data: &str) -> Result<[u8; 32], DecodeUriError> {
+    decode_base64url_32(data).map_err(|err| match err {
+        Base64UrlError::PercentEncoding => DecodeUriError::PercentEncoding,
+        Base64UrlError::InvalidBase64(err) => DecodeUriError::InvalidBase64(err),
+        Base64UrlError::WrongLength => DecodeUriError::WrongLength,
+    })
+}
+
+/// Formats as the canonical URI.
+impl fmt::Display for SsbUri {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_uri())
+    }
+}
+
+/// Turn an ssb URI, including the variants accepted by `SsbUri::from_legacy_uri`, into the
+/// canonical URI of the same feed, message, blob or group. Unknown URIs are returned unchanged.
+pub fn normalize_uri(s: &str) -> Result<String, DecodeUriError> {
+    SsbUri::from_legacy_uri(s).map(|uri| uri.to_uri())
+}
+
+/// Everything that can go wrong when parsing an ssb URI.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+pub enum DecodeUriError {
+    /// The input did not start with `ssb:`.
+    NotAnSsbUri,
+    /// The URI contained a malformed percent-encoded character.
+    PercentEncoding,
+    /// The data was not valid url-safe base64.
+    InvalidBase64(base64::DecodeError),
+    /// The data did not encode exactly 32 bytes.
+    WrongLength,
+    /// An embedded legacy encoding of a feed was invalid.
+    Key(multikey::DecodeLegacyError),
+    /// An embedded legacy encoding of a message or blob was invalid.
+    Hash(multihash::DecodeLegacyError),
+    /// An embedded legacy encoding was followed by further data.
+    TrailingData,
+    /// The URI was valid, but not in canonical form.
+    Noncanonic,
+}
+
+impl fmt::Display for DecodeUriError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeUriError::NotAnSsbUri => write!(f, "Not an ssb URI"),
+            DecodeUriError::PercentEncoding => write!(f, "Invalid percent-encoding"),
+            DecodeUriError::InvalidBase64(ref err) => write!(f, "{}", err),
+            DecodeUriError::WrongLength => write!(f, "Wrong length"),
+            DecodeUriError::Key(ref err) => write!(f, "{}", err),
+            DecodeUriError::Hash(ref err) => write!(f, "{}", err),
+            DecodeUriError::TrailingData => write!(f, "Trailing data"),
+            DecodeUriError::Noncanonic => write!(f, "Noncanonic URI"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeUriError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            DecodeUriError::InvalidBase64(ref err) => Some(err),
+            DecodeUriError::Key(ref err) => Some(err),
+            DecodeUriError::Hash(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_uri() {
+    let mh = Multihash::message_from_digest([0xfb; 32]);
+    let uri = SsbUri::Message(mh.clone()).to_uri();
+    assert_eq!(
+        uri,
+        "ssb:message/classic/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s"
+    );
+    assert_eq!(SsbUri::from_uri(&uri).unwrap(), SsbUri::Message(mh.clone()));
+    assert_eq!(
+        SsbUri::from_uri(&format!("{}=", uri)).unwrap(),
+        SsbUri::Message(mh.clone())
+    );
+    assert_eq!(
+        SsbUri::from_uri(&format!("{}%3D", uri)).unwrap(),
+        SsbUri::Message(mh.clone())
+    );
+    assert_eq!(
+        SsbUri::from_uri(&format!("{}%3", uri)),
+        Err(DecodeUriError::PercentEncoding)
+    );
+
+    assert_eq!(
+        SsbUri::from_uri("ssb:thread/classic/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s="),
+        Ok(SsbUri::Unknown {
+            ty: "thread".to_string(),
+            format: Some("classic".to_string()),
+            data: Some("-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s=".to_string()),
+        })
+    );
+    assert_eq!(
+        SsbUri::from_uri("https://example.com"),
+        Err(DecodeUriError::NotAnSsbUri)
+    );
+}
+
+#[test]
+fn test_legacy_uri() {
+    let key = Multikey::from_ed25519(&[0xfb; 32]);
+    let mh = Multihash::message_from_digest([0xfb; 32]);
+    let blob = Multihash::blob_from_digest([0xfb; 32]);
+
+    for uri in &[
+        "ssb:message/classic/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s=",
+        "ssb:message/sha256/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s",
+        "ssb:message:sha256:+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s=",
+        "ssb://message/sha256/+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s=",
+        "ssb://%2Fmessage%2Fsha256%2F-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s%3D",
+        "ssb:%25%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fv7%2B%2Fs%3D.sha256",
+        "ssb://%25+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s.SHA256",
+    ] {
+        assert_eq!(
+            SsbUri::from_legacy_uri(uri),
+            Ok(SsbUri::Message(mh.clone())),
+            "{}",
+            uri
+        );
+    }
+
+    assert_eq!(
+        SsbUri::from_legacy_uri("ssb:feed:ed25519:+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s="),
+        Ok(SsbUri::Feed(key.clone()))
+    );
+    assert_eq!(
+        SsbUri::from_legacy_uri("ssb:@+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s=.ed25519"),
+        Ok(SsbUri::Feed(key))
+    );
+    assert_eq!(
+        SsbUri::from_legacy_uri("ssb:blob/sha256/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s="),
+        Ok(SsbUri::Blob(blob))
+    );
+
+    assert_matches!(
+        SsbUri::from_legacy_uri("ssb:blob/ed25519/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s="),
+        Ok(SsbUri::Unknown { .. })
+    );
+    assert_eq!(
+        SsbUri::from_legacy_uri("ssb:%2"),
+        Err(DecodeUriError::PercentEncoding)
+    );
+    assert_matches!(
+        SsbUri::from_legacy_uri("ssb:%25abc.sha256"),
+        Err(DecodeUriError::Hash(_))
+    );
+    assert_eq!(
+        SsbUri::from_legacy_uri("ssb:%25+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s=.sha256?foo"),
+        Err(DecodeUriError::TrailingData)
+    );
+}
+
+#[test]
+fn test_canonical_uri() {
+    let canonical = "ssb:blob/classic/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s";
+    assert_eq!(
+        SsbUri::from_uri_strict(canonical),
+        Ok(SsbUri::Blob(Multihash::blob_from_digest([0xfb; 32])))
+    );
+    assert_eq!(normalize_uri(canonical).unwrap(), canonical);
+
+    for uri in &[
+        "ssb:blob/classic/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s=",
+        "ssb:Blob/CLASSIC/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s",
+        "ssb:blob/classic/+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s",
+    ] {
+        assert_eq!(normalize_uri(uri).unwrap(), canonical, "{}", uri);
+        assert!(SsbUri::from_uri_strict(uri).is_err(), "{}", uri);
+    }
+
+    assert_eq!(
+        SsbUri::from_uri_strict("ssb:blob/classic/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s="),
+        Err(DecodeUriError::Noncanonic)
+    );
+    assert_matches!(
+        SsbUri::from_uri_strict("ssb:blob/classic/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_t"),
+        Err(DecodeUriError::InvalidBase64(_))
+    );
+    assert_eq!(
+        normalize_uri("ssb:blob/classic/-_v7"),
+        Err(DecodeUriError::WrongLength)
+    );
+
+    let group = "ssb:identity/group/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s";
+    assert_eq!(
+        SsbUri::from_uri_strict(group),
+        Ok(SsbUri::Group(GroupId::from_bytes([0xfb; 32])))
+    );
+    assert_eq!(normalize_uri(&format!("{}=", group)).unwrap(), group);
+    assert_eq!(
+        normalize_uri("ssb:Identity/Group/+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s").unwrap(),
+        group
+    );
+    assert_eq!(GroupId::from_bytes([0xfb; 32]).to_uri(), group);
+}
+
+#[test]
+fn test_unknown_uri() {
+    for uri in &[
+        "ssb:feed/bendybutt-v1/APaWWDs8g73EZFUMfW8zqJ9E9DZfWQ-kTyKQh3m1SUQ=",
+        "ssb:experimental?action=consume-alias&roomId=abc",
+        "ssb:address/multiserver?multiserverAddress=net%3Aexample.com%3A8008",
+        "ssb:Thread/Classic/a/b/c",
+        "ssb:",
+        "ssb:feed/",
+    ] {
+        let parsed = SsbUri::from_uri(uri).unwrap();
+        assert_matches!(parsed, SsbUri::Unknown { .. });
+        assert_eq!(parsed.to_uri(), *uri);
+        assert_eq!(SsbUri::from_uri_strict(uri), Ok(parsed));
+        assert_eq!(normalize_uri(uri).unwrap(), *uri);
+    }
+
+    assert_eq!(
+        SsbUri::from_uri("ssb:experimental?action=join"),
+        Ok(SsbUri::Unknown {
+            ty: "experimental?action=join".to_string(),
+            format: None,
+            data: None,
+        })
+    );
+    // Only `from_legacy_uri` knows the older format names.
+    let sha256 = "ssb:message/sha256/-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_s=";
+    assert_matches!(SsbUri::from_uri(sha256), Ok(SsbUri::Unknown { .. }));
+    assert_eq!(
+        SsbUri::from_uri_strict(sha256),
+        Err(DecodeUriError::Noncanonic)
+    );
+
+    // Legacy variants of unknown URIs are kept as they are as well.
+    let legacy = "ssb://thread:sha256:+/v7%3D";
+    assert_eq!(SsbUri::from_legacy_uri(legacy).unwrap().to_uri(), legacy);
+}
@@ -0,0 +1,97 @@
+//! JavaScript bindings via [wasm-bindgen](https://docs.rs/wasm-bindgen), so that web clients can
+//! apply exactly the same validation rules as Rust code using this crate.
+//!
+//! All functions take and return legacy encodings and `ssb:` URIs as strings, and raw keys and
+//! digests as `Uint8Array`s.
+use wasm_bindgen::prelude::*;
+
+use super::bindings;
+
+/// Return whether the string is the (canonical) legacy encoding of a multikey.
+#[wasm_bindgen(js_name = isMultikey)]
+pub fn is_multikey(s: &str) -> bool {
+    bindings::is_multikey(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multifeed.
+#[wasm_bindgen(js_name = isMultifeed)]
+pub fn is_multifeed(s: &str) -> bool {
+    bindings::is_multifeed(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// message.
+#[wasm_bindgen(js_name = isMessageHash)]
+pub fn is_message_hash(s: &str) -> bool {
+    bindings::is_message_hash(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multihash referring to a
+/// blob.
+#[wasm_bindgen(js_name = isBlobHash)]
+pub fn is_blob_hash(s: &str) -> bool {
+    bindings::is_blob_hash(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of a multibox.
+#[wasm_bindgen(js_name = isMultibox)]
+pub fn is_multibox(s: &str) -> bool {
+    bindings::is_multibox(s)
+}
+
+/// Return whether the string is the (canonical) legacy encoding of an ed25519 signature.
+#[wasm_bindgen(js_name = isSignature)]
+pub fn is_signature(s: &str) -> bool {
+    bindings::is_signature(s)
+}
+
+/// Determine what kind of value the string is the legacy encoding of: `"feed"`, `"message"`,
+/// `"blob"` or `"box"`. Returns `undefined` if it is none of these.
+#[wasm_bindgen]
+pub fn classify(s: &str) -> Option<String> {
+    bindings::classify(s).map(str::to_string)
+}
+
+/// Decode the legacy encoding of an ed25519 multikey into the 32 bytes of the public key.
+#[wasm_bindgen(js_name = decodeMultikey)]
+pub fn decode_multikey(s: &str) -> Result<Vec<u8>, JsError> {
+    Ok(bindings::decode_multikey(s)?)
+}
+
+/// Encode the 32 bytes of an ed25519 public key as a legacy multikey.
+#[wasm_bindgen(js_name = encodeMultikey)]
+pub fn encode_multikey(pk: &[u8]) -> Result<String, JsError> {
+    Ok(bindings::encode_multikey(pk)?)
+}
+
+/// Decode the legacy encoding of a sha256 multihash (referring to either a message or a blob)
+/// into the 32 bytes of the digest.
+#[wasm_bindgen(js_name = decodeMultihash)]
+pub fn decode_multihash(s: &str) -> Result<Vec<u8>, JsError> {
+    Ok(bindings::decode_multihash(s)?)
+}
+
+/// Encode the 32 bytes of a sha256 digest as a legacy multihash referring to a message if
+/// `blob` is false, or to a blob if it is true.
+#[wasm_bindgen(js_name = encodeMultihash)]
+pub fn encode_multihash(digest: &[u8], blob: bool) -> Result<String, JsError> {
+    Ok(bindings::encode_multihash(digest, blob)?)
+}
+
+/// Decode the legacy encoding of an ed25519 signature into the 64 signature bytes.
+#[wasm_bindgen(js_name = decodeSignature)]
+pub fn decode_signature(s: &str) -> Result<Vec<u8>, JsError> {
+    Ok(bindings::decode_signature(s)?)
+}
+
+/// Convert the legacy encoding of a feed id or a message or blob hash into its `ssb:` URI.
+#[wasm_bindgen(js_name = toUri)]
+pub fn to_uri(s: &str) -> Result<String, JsError> {
+    Ok(bindings::to_uri(s)?)
+}
+
+/// Convert the `ssb:` URI of a feed, message or blob into its legacy encoding.
+#[wasm_bindgen(js_name = fromUri)]
+pub fn from_uri(s: &str) -> Result<String, JsError> {
+    Ok(bindings::from_uri(s)?)
+}